@@ -0,0 +1,108 @@
+//
+// image.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::path::Path;
+
+use cairo;
+use gdk::{self, ContextExt};
+use gdk_pixbuf::Pixbuf;
+
+use error::Error;
+use common::types::*;
+
+// Picture keeps the decoded surface (used for drawing) together with its PNG
+// encoded bytes (used when saving or copying), so we never re-encode an
+// image that didn't change.
+#[derive(Clone)]
+pub struct Picture {
+    surface: Surface,
+    png: Rc<Vec<u8>>,
+}
+
+impl Picture {
+    pub fn from_surface(surface: Surface) -> Result<Self, Error> {
+        let mut png = vec![];
+        if let Err(_) = surface.write_to_png(&mut png) {
+            return Err(Error::from("Cairo: couldn't encode surface as PNG"));
+        }
+
+        Ok(Picture {
+            surface: surface,
+            png: Rc::new(png),
+        })
+    }
+
+    pub fn from_png(png: Vec<u8>) -> Result<Self, Error> {
+        let surface = match Surface::create_from_png(&mut png.as_slice()) {
+            Ok(val) => val,
+            Err(_) => return Err(Error::from("Cairo: couldn't decode PNG data")),
+        };
+
+        Ok(Picture {
+            surface: surface,
+            png: Rc::new(png),
+        })
+    }
+
+    pub fn from_pixbuf(pixbuf: &Pixbuf) -> Result<Self, Error> {
+        let surface = match Surface::create(
+            cairo::Format::ARgb32, pixbuf.get_width(), pixbuf.get_height()
+        ) {
+            Ok(val) => val,
+            Err(_) => return Err(Error::from("Cairo: couldn't create surface")),
+        };
+
+        {
+            let cr = cairo::Context::new(&surface);
+            cr.set_source_pixbuf(pixbuf, 0.0, 0.0);
+            cr.paint();
+        }
+
+        Picture::from_surface(surface)
+    }
+
+    // this accept any format that GdkPixbuf can load (PNG, JPEG..etc)
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let pixbuf = Pixbuf::new_from_file(path)?;
+        Picture::from_pixbuf(&pixbuf)
+    }
+
+    pub fn to_pixbuf(&self) -> Option<Pixbuf> {
+        gdk::pixbuf_get_from_surface(
+            &self.surface, 0, 0, self.get_width(), self.get_height()
+        )
+    }
+
+    pub fn get_surface(&self) -> &Surface {
+        &self.surface
+    }
+
+    pub fn get_png(&self) -> &Vec<u8> {
+        &self.png
+    }
+
+    pub fn get_width(&self) -> i32 {
+        self.surface.get_width()
+    }
+
+    pub fn get_height(&self) -> i32 {
+        self.surface.get_height()
+    }
+}
@@ -19,6 +19,8 @@
 
 pub mod string;
 pub mod types;
+pub mod image;
 
 pub use self::string::*;
 pub use self::types::*;
+pub use self::image::*;
@@ -18,22 +18,229 @@
 //
 
 use cairo;
+use std::cell::RefCell;
 use std::ops::{Deref};
 use common::types::*;
+use super::snap::{SnapTargets, Guide};
+use super::draw_objects::{Background, ShapeTrait, Draw, Visible, Container,
+                          Mode};
+use super::draw_objects::layer::LayerTrait;
+
+// what the page draw and how, set by the page before drawing its shapes
+#[derive(Clone)]
+pub struct RenderOptions<'a> {
+    // false when drawing the page as it's exported, so the selected shapes
+    // don't draw their controllers.
+    pub show_controllers: bool,
+    // the last reveal step drawn while playing the page step by step, shapes
+    // with a later step are not drawn. None draws all the shapes.
+    pub reveal_step: Option<u32>,
+    // the page color, None when the page is transparent, and the background
+    // image. shapes that show the page content (e.g. magnifiers) draw them
+    // again.
+    pub page_color: Option<RgbColor>,
+    pub background: Option<&'a Background>,
+    // the page layers, shapes that show the page content draw the shapes
+    // below them again.
+    pub layers: Option<&'a Vec<Box<LayerTrait>>>,
+    // the page area, in the page coordinates
+    pub page_bounds: Option<Rectangle>,
+}
+
+impl<'a> Default for RenderOptions<'a> {
+    fn default() -> Self {
+        RenderOptions {
+            show_controllers: true,
+            reveal_step: None,
+            page_color: None,
+            background: None,
+            layers: None,
+            page_bounds: None,
+        }
+    }
+}
+
+impl<'a> RenderOptions<'a> {
+    // return true if shapes with @step are revealed
+    pub fn is_revealed(&self, step: u32) -> bool {
+        self.reveal_step.map_or(true, |reveal_step| step <= reveal_step)
+    }
+}
+
+// the snapping of the points moved by the events, and the smart guides they
+// got aligned to
+pub struct SnapState {
+    // the distance between the grid lines that points snap to, None when
+    // snapping is off.
+    step: Option<f64>,
+    // the shapes edges and centers that dragged points snap to
+    targets: Option<SnapTargets>,
+    // the smart guides that points got aligned to while handling an event
+    guides: RefCell<Vec<Guide>>,
+}
+
+impl SnapState {
+    pub fn new() -> Self {
+        SnapState {
+            step: None,
+            targets: None,
+            guides: RefCell::new(vec![]),
+        }
+    }
+
+    // return @pos snapped to the nearest smart guide target, or to the
+    // nearest grid intersection if snapping is on, otherwise return @pos as
+    // it is.
+    pub fn snap(&self, pos: &Point) -> Point {
+        if let Some(ref targets) = self.targets {
+            let (point, guides) = targets.snap_point(pos);
+            if point != *pos || !guides.is_empty() {
+                self.guides.borrow_mut().extend(guides);
+                return point;
+            }
+        }
+
+        self.snap_to_grid(pos)
+    }
+
+    // return the nearest grid intersection to @pos if snapping is on,
+    // otherwise return @pos as it is.
+    pub fn snap_to_grid(&self, pos: &Point) -> Point {
+        match self.step {
+            Some(step) if step > 0.0 => Point::new(
+                (pos.x / step).round() * step,
+                (pos.y / step).round() * step
+            ),
+            _ => pos.clone(),
+        }
+    }
+
+    // return the offset that align @rect with the smart guide targets
+    pub fn snap_rectangle(&self, rect: &Rectangle) -> Vector {
+        let targets = match self.targets {
+            Some(ref val) => val,
+            None => return Vector::new(0.0, 0.0),
+        };
+
+        let (offset, guides) = targets.snap_rectangle(rect);
+        self.guides.borrow_mut().extend(guides);
+        offset
+    }
+}
 
 pub struct Context<'a>{
     cr: &'a cairo::Context,
+    render: RenderOptions<'a>,
+    snap: SnapState,
 }
 
 impl<'a> Context<'a> {
     pub fn new(cr: &'a cairo::Context, zoom_level: f64, translate: &Vector) -> Context<'a> {
         cr.translate(translate.x, translate.y);
         cr.scale(zoom_level, zoom_level);
+        Context::from_transformed(cr)
+    }
+
+    // wrap @cr that is already translated and scaled, e.g. the measuring
+    // context cached by the pages.
+    pub fn from_transformed(cr: &'a cairo::Context) -> Context<'a> {
         Context {
             cr: cr,
+            render: RenderOptions::default(),
+            snap: SnapState::new(),
+        }
+    }
+
+    pub fn get_render_options(&self) -> &RenderOptions<'a> {
+        &self.render
+    }
+
+    pub fn set_render_options(&mut self, options: RenderOptions<'a>) {
+        self.render = options;
+    }
+
+    pub fn get_show_controllers(&self) -> bool {
+        self.render.show_controllers
+    }
+
+    // return true if shapes with @step are revealed
+    pub fn is_revealed(&self, step: u32) -> bool {
+        self.render.is_revealed(step)
+    }
+
+    pub fn get_page_bounds(&self) -> Option<Rectangle> {
+        self.render.page_bounds.clone()
+    }
+
+    // draw the page color and the background image in the current user
+    // space, it's the page coordinates unless the caller transformed it.
+    pub fn draw_backdrop(&self) {
+        if let Some(ref color) = self.render.page_color {
+            self.set_source_rgb(color);
+            self.cr.paint();
+        }
+        if let Some(background) = self.render.background {
+            background.draw(self.cr, 1.0);
+        }
+    }
+
+    // draw the shapes of the visible layers that are drawn before the shape
+    // at @address (the address of its box content), in the current user
+    // space and without their controllers. if the shape is a child of other
+    // shapes, its ancestors are not drawn since they draw it too.
+    pub fn draw_shapes_below(&self, address: usize) {
+        let layers = match self.render.layers {
+            Some(val) => val,
+            None => return,
+        };
+
+        let mut context = Context::from_transformed(self.cr);
+        context.render = RenderOptions {
+            show_controllers: false,
+            .. self.render.clone()
+        };
+
+        for layer in layers.iter() {
+            if !layer.is_visible() {
+                continue;
+            }
+            if draw_children_below(layer.get_children(), address, &context) {
+                return;
+            }
         }
     }
 
+    pub fn get_snap_step(&self) -> Option<f64> {
+        self.snap.step
+    }
+
+    pub fn set_snap_step(&mut self, step: Option<f64>) {
+        self.snap.step = step;
+    }
+
+    pub fn set_snap_targets(&mut self, targets: Option<SnapTargets>) {
+        self.snap.targets = targets;
+    }
+
+    // return the guides that were used by snap() and snap_extents()
+    pub fn take_guides(&self) -> Vec<Guide> {
+        self.snap.guides.borrow_mut().drain(..).collect()
+    }
+
+    pub fn snap(&self, pos: &Point) -> Point {
+        self.snap.snap(pos)
+    }
+
+    pub fn snap_to_grid(&self, pos: &Point) -> Point {
+        self.snap.snap_to_grid(pos)
+    }
+
+    // return the offset that align the given extents (in device space) with
+    // the smart guide targets, the offset is in user space.
+    pub fn snap_extents(&self, extents: &Rectangle) -> Vector {
+        self.snap.snap_rectangle(&self.device_to_user_rect(extents))
+    }
+
     pub fn get_zoom_level(&self) -> f64 {
         let matrix = self.cr.get_matrix();
         matrix.x0
@@ -185,3 +392,33 @@ impl<'a> Deref for Context<'a> {
         &self.cr
     }
 }
+
+fn shape_address(shape: &Box<ShapeTrait>) -> usize {
+    &**shape as *const ShapeTrait as *const () as usize
+}
+
+fn has_descendant(shape: &Box<ShapeTrait>, address: usize) -> bool {
+    shape.get_children()
+         .iter()
+         .any(|child| shape_address(child) == address ||
+                      has_descendant(child, address))
+}
+
+// draw @children until the shape at @address, return true if it was found
+fn draw_children_below(
+    children: &Vec<Box<ShapeTrait>>, address: usize, cr: &Context
+) -> bool {
+    for child in children.iter() {
+        if shape_address(child) == address {
+            return true;
+        }
+        if has_descendant(child, address) {
+            return draw_children_below(child.get_children(), address, cr);
+        }
+        // half created shapes are not part of the page yet
+        if !child.in_creating_mode() {
+            child.draw(cr);
+        }
+    }
+    false
+}
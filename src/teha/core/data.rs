@@ -0,0 +1,391 @@
+//
+// data.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+// This module contains plain data structs that mirror our draw objects, they
+// are used to serialize shapes (e.g. for the clipboard) since draw objects
+// hold cairo and Rc<RefCell<..>> fields that cannot be serialized directly.
+
+use std::path::PathBuf;
+
+use cairo;
+use base64;
+use serde_json;
+
+use error::Error;
+use common::types::*;
+use common::image::Picture;
+use super::draw_objects::{page, ShapeTrait, LineArrow, Image, Magnifier,
+                          Spotlight};
+use super::draw_objects::magnifier::{LensShape, Connector};
+use super::draw_objects::spotlight::HoleShape;
+use super::snap::Guide;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+impl From<cairo::LineCap> for LineCap {
+    fn from(cap: cairo::LineCap) -> Self {
+        match cap {
+            cairo::LineCap::Butt => LineCap::Butt,
+            cairo::LineCap::Round => LineCap::Round,
+            cairo::LineCap::Square => LineCap::Square,
+        }
+    }
+}
+
+impl Into<cairo::LineCap> for LineCap {
+    fn into(self) -> cairo::LineCap {
+        match self {
+            LineCap::Butt => cairo::LineCap::Butt,
+            LineCap::Round => cairo::LineCap::Round,
+            LineCap::Square => cairo::LineCap::Square,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum LineJoin {
+    Bevel,
+    Miter,
+    Round,
+}
+
+impl From<cairo::LineJoin> for LineJoin {
+    fn from(join: cairo::LineJoin) -> Self {
+        match join {
+            cairo::LineJoin::Bevel => LineJoin::Bevel,
+            cairo::LineJoin::Miter => LineJoin::Miter,
+            cairo::LineJoin::Round => LineJoin::Round,
+        }
+    }
+}
+
+impl Into<cairo::LineJoin> for LineJoin {
+    fn into(self) -> cairo::LineJoin {
+        match self {
+            LineJoin::Bevel => cairo::LineJoin::Bevel,
+            LineJoin::Miter => cairo::LineJoin::Miter,
+            LineJoin::Round => cairo::LineJoin::Round,
+        }
+    }
+}
+
+pub fn point_to_data(point: &Point) -> [f64; 2] {
+    [point.x, point.y]
+}
+
+pub fn point_from_data(data: &[f64; 2]) -> Point {
+    Point::new(data[0], data[1])
+}
+
+pub fn vector_to_data(vector: &Vector) -> [f64; 2] {
+    [vector.x, vector.y]
+}
+
+pub fn vector_from_data(data: &[f64; 2]) -> Vector {
+    Vector::new(data[0], data[1])
+}
+
+pub fn matrix_to_data(matrix: &Matrix) -> [f64; 4] {
+    [matrix[(0, 0)], matrix[(0, 1)], matrix[(1, 0)], matrix[(1, 1)]]
+}
+
+pub fn matrix_from_data(data: &[f64; 4]) -> Matrix {
+    Matrix::new(data[0], data[1], data[2], data[3])
+}
+
+pub fn rgba_to_data(color: &RgbaColor) -> [f64; 4] {
+    [color.color.red, color.color.green, color.color.blue, color.alpha]
+}
+
+pub fn rgba_from_data(data: &[f64; 4]) -> RgbaColor {
+    RgbaColor::new(data[0], data[1], data[2], data[3])
+}
+
+pub fn rgb_to_data(color: &RgbColor) -> [f64; 3] {
+    [color.red, color.green, color.blue]
+}
+
+pub fn rgb_from_data(data: &[f64; 3]) -> RgbColor {
+    RgbColor::new(data[0], data[1], data[2])
+}
+
+pub fn guide_to_data(guide: &Guide) -> GuideData {
+    match *guide {
+        Guide::Vertical(x) => GuideData::Vertical(x),
+        Guide::Horizontal(y) => GuideData::Horizontal(y),
+    }
+}
+
+pub fn guide_from_data(data: &GuideData) -> Guide {
+    match *data {
+        GuideData::Vertical(x) => Guide::Vertical(x),
+        GuideData::Horizontal(y) => Guide::Horizontal(y),
+    }
+}
+
+// images are stored as base64 encoded PNG
+pub fn picture_to_data(picture: &Picture) -> String {
+    base64::encode(picture.get_png())
+}
+
+pub fn picture_from_data(data: &str) -> Result<Picture, Error> {
+    match base64::decode(data) {
+        Ok(png) => Picture::from_png(png),
+        Err(_) => Err(Error::from("Couldn't decode image data")),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LineArrowData {
+    pub name: String,
+    pub lock: bool,
+    pub visible: bool,
+    #[serde(default)]
+    pub step: u32,
+    pub color: [f64; 4],
+    pub width: f64,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    pub dashes: Vec<f64>,
+    pub offset: f64,
+    pub start: [f64; 2],
+    pub end: [f64; 2],
+    pub curve_like: bool,
+    pub have_head: bool,
+    pub go_dir: [f64; 2],
+    pub arrive_dir: [f64; 2],
+    pub children: Vec<ShapeData>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ImageData {
+    pub name: String,
+    pub lock: bool,
+    pub visible: bool,
+    #[serde(default)]
+    pub step: u32,
+    pub opacity: f64,
+    pub position: [f64; 2],
+    pub scale: f64,
+    pub transform: [f64; 4],
+    pub picture: String,
+    pub children: Vec<ShapeData>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MagnifierData {
+    pub name: String,
+    pub lock: bool,
+    pub visible: bool,
+    pub step: u32,
+    pub source_mins: [f64; 2],
+    pub source_maxs: [f64; 2],
+    pub lens_center: [f64; 2],
+    pub zoom: f64,
+    pub lens_shape: LensShape,
+    pub connector: Connector,
+    pub border_color: [f64; 4],
+    pub border_width: f64,
+    pub children: Vec<ShapeData>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HoleData {
+    pub shape: HoleShape,
+    pub mins: [f64; 2],
+    pub maxs: [f64; 2],
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpotlightData {
+    pub name: String,
+    pub lock: bool,
+    pub visible: bool,
+    pub step: u32,
+    pub dim_color: [f64; 4],
+    pub feather: f64,
+    pub holes: Vec<HoleData>,
+    pub children: Vec<ShapeData>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum ShapeData {
+    LineArrow(LineArrowData),
+    Image(ImageData),
+    Magnifier(MagnifierData),
+    Spotlight(SpotlightData),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LayerData {
+    pub name: String,
+    pub lock: bool,
+    pub visible: bool,
+    pub children: Vec<ShapeData>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum GuideData {
+    Vertical(f64),
+    Horizontal(f64),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PageData {
+    pub name: String,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub show_caption: bool,
+    pub size: [i32; 2],
+    pub color: Option<[f64; 3]>,
+    pub border: Option<[f64; 3]>,
+    pub grid: Option<[f64; 3]>,
+    #[serde(default = "default_grid_spacing")]
+    pub grid_spacing: f64,
+    #[serde(default = "default_grid_subdivisions")]
+    pub grid_subdivisions: u32,
+    #[serde(default)]
+    pub snap_to_grid: bool,
+    #[serde(default)]
+    pub guides: Vec<GuideData>,
+    #[serde(default)]
+    pub background: Option<BackgroundData>,
+    pub zoom_level: f64,
+    pub active_layer_index: usize,
+    pub layers: Vec<LayerData>,
+}
+
+fn default_grid_spacing() -> f64 {
+    page::GRID_SPACING
+}
+
+fn default_grid_subdivisions() -> u32 {
+    page::GRID_SUBDIVISIONS
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BackgroundData {
+    pub picture: String,
+    pub position: [f64; 2],
+    pub scale: f64,
+    pub locked: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DocumentData {
+    pub transparent: bool,
+    pub active_page_index: usize,
+    pub pages: Vec<PageData>,
+}
+
+// a document saved by the autosave together with the path of its file
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RecoveryData {
+    pub path: PathBuf,
+    pub document: DocumentData,
+}
+
+impl ShapeData {
+    pub fn into_shape(self) -> Result<Box<ShapeTrait>, Error> {
+        match self {
+            ShapeData::LineArrow(data) => {
+                Ok(Box::new(LineArrow::from_data(data)?))
+            },
+            ShapeData::Image(data) => Ok(Box::new(Image::from_data(data)?)),
+            ShapeData::Magnifier(data) => {
+                Ok(Box::new(Magnifier::from_data(data)?))
+            },
+            ShapeData::Spotlight(data) => {
+                Ok(Box::new(Spotlight::from_data(data)?))
+            },
+        }
+    }
+}
+
+pub fn shapes_to_string(shapes: &Vec<ShapeData>) -> Result<String, Error> {
+    Ok(serde_json::to_string(shapes)?)
+}
+
+pub fn shapes_from_string(text: &str) -> Result<Vec<ShapeData>, Error> {
+    Ok(serde_json::from_str(text)?)
+}
+
+pub fn shapes_from_data(
+    shapes: Vec<ShapeData>
+) -> Result<Vec<Box<ShapeTrait>>, Error> {
+    let mut result = vec![];
+    for shape in shapes.into_iter() {
+        result.push(shape.into_shape()?);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use cairo;
+
+    use common::types::*;
+    use common::image::Picture;
+    use super::*;
+    use super::super::draw_objects::{ShapeTrait, LineArrow, Image, Container,
+                                     Snapshot};
+
+    fn to_string(shapes: &Vec<Box<ShapeTrait>>) -> String {
+        let data = shapes.iter().map(|shape| shape.snapshot()).collect();
+        shapes_to_string(&data).unwrap()
+    }
+
+    #[test]
+    fn shapes_round_trip() {
+        let mut arrow = LineArrow::new(
+            Segment::new(Point::new(10.0, 20.0), Point::new(110.0, 70.0))
+        );
+        arrow.set_width(3.5);
+        arrow.set_dashes(&vec![4.0, 2.0]);
+        arrow.set_curve_like(true);
+
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 3, 2)
+            .expect("Cairo: couldn't create surface");
+        let mut image = Image::new(
+            Picture::from_surface(surface).unwrap(), Point::new(50.0, 60.0)
+        );
+        image.set_opacity(0.5);
+        image.set_scale(2.0);
+        arrow.add(Box::new(image));
+
+        let shapes: Vec<Box<ShapeTrait>> = vec![Box::new(arrow)];
+        let text = to_string(&shapes);
+        let copies = shapes_from_data(shapes_from_string(&text).unwrap()).unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].get_children().len(), 1);
+        assert_eq!(to_string(&copies), text);
+    }
+
+    #[test]
+    fn invalid_pictures_are_rejected() {
+        assert!(picture_from_data("not base64!").is_err());
+        assert!(picture_from_data("aGVsbG8=").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//
+// background.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use cairo;
+
+use core::data::{self, BackgroundData};
+use error::Error;
+use common::types::*;
+use common::image::Picture;
+
+// an image (usually a screenshot) drawn below all the layers of a page, it's
+// not a shape so it can't be selected, but it can be dragged when unlocked.
+pub struct Background {
+    picture: Picture,
+    // the top left corner of the image in the page
+    position: Point,
+    scale: f64,
+    // locked images ignore the pointer, so clicks reach the shapes and the
+    // rubber band.
+    locked: bool,
+}
+
+impl Background {
+    pub fn new(picture: Picture) -> Self {
+        Background {
+            picture: picture,
+            position: Point::new(0.0, 0.0),
+            scale: 1.0,
+            locked: true,
+        }
+    }
+
+    pub fn from_data(data: BackgroundData) -> Result<Self, Error> {
+        Ok(Background {
+            picture: data::picture_from_data(&data.picture)?,
+            position: data::point_from_data(&data.position),
+            scale: data.scale,
+            locked: data.locked,
+        })
+    }
+
+    pub fn to_data(&self) -> BackgroundData {
+        BackgroundData {
+            picture: data::picture_to_data(&self.picture),
+            position: data::point_to_data(&self.position),
+            scale: self.scale,
+            locked: self.locked,
+        }
+    }
+
+    pub fn get_picture(&self) -> &Picture {
+        &self.picture
+    }
+
+    pub fn set_picture(&mut self, picture: Picture) {
+        self.picture = picture;
+    }
+
+    pub fn get_position(&self) -> &Point {
+        &self.position
+    }
+
+    pub fn set_position(&mut self, position: Point) {
+        self.position = position;
+    }
+
+    pub fn get_scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+
+    // the size of the image in the page after scaling it
+    pub fn size(&self) -> Vector {
+        Vector::new(
+            self.picture.get_width() as f64 * self.scale,
+            self.picture.get_height() as f64 * self.scale
+        )
+    }
+
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(self.position, self.position + self.size())
+    }
+
+    pub fn contains(&self, pos: &Point) -> bool {
+        let bounds = self.bounds();
+        pos.x >= bounds.mins().x && pos.x <= bounds.maxs().x &&
+            pos.y >= bounds.mins().y && pos.y <= bounds.maxs().y
+    }
+
+    // draw the image in the page coordinates of @cr
+    pub fn draw(&self, cr: &cairo::Context, alpha: f64) {
+        if self.scale <= 0.0 {
+            return;
+        }
+
+        cr.save();
+        cr.translate(self.position.x, self.position.y);
+        cr.scale(self.scale, self.scale);
+        cr.set_source_surface(self.picture.get_surface(), 0.0, 0.0);
+        cr.paint_with_alpha(alpha);
+        cr.restore();
+    }
+}
@@ -0,0 +1,679 @@
+//
+// image.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use cairo;
+use gdk::{self, EventMotion, EventButton};
+use gtk::prelude::*;
+use gtk::{self, SwitchExt, ContainerExt, WidgetExt, GridExt, NotebookExtManual,
+          EntryExt, RangeExt, ScaleExt, SpinButtonExt};
+
+use gettextrs::*;
+
+use ncollide::bounding_volume::BoundingVolume;
+use na;
+use alga::linear::Transformation;
+
+use core::context::Context;
+use core::data::{self, ShapeData, ImageData};
+use error::Error;
+use common::types::*;
+use common::image::Picture;
+use super::*;
+
+#[derive(Clone, PartialEq)]
+pub enum Actions {
+    MoveBody(Vector),
+    Resize,
+}
+
+pub struct Image {
+    children: Vec<Box<ShapeTrait>>,
+    // ID field
+    name: Rc<RefCell<String>>,
+    // control fields
+    lock: Rc<RefCell<bool>>,
+    selected: bool,
+    // this field for Event trait.
+    action: Option<Actions>,
+    // draw fields
+    visible: Rc<RefCell<bool>>,
+    step: Rc<RefCell<u32>>,
+    opacity: Rc<RefCell<f64>>,
+    picture: Picture,
+    // position is the center of the image
+    position: Point,
+    scale: f64,
+    // flips and rotations applied on the image
+    transform: Matrix,
+}
+
+impl Image {
+    pub fn new(picture: Picture, position: Point) -> Self {
+        Image {
+            children: vec![],
+            name: Rc::new(RefCell::new(gettext("Image"))),
+            lock: Rc::new(RefCell::new(false)),
+            selected: false,
+            action: None,
+            visible: Rc::new(RefCell::new(true)),
+            step: Rc::new(RefCell::new(0)),
+            opacity: Rc::new(RefCell::new(1.0)),
+            picture: picture,
+            position: position,
+            scale: 1.0,
+            transform: Matrix::identity(),
+        }
+    }
+
+    pub fn from_data(data: ImageData) -> Result<Self, Error> {
+        let picture = data::picture_from_data(&data.picture)?;
+        let mut image =
+            Image::new(picture, data::point_from_data(&data.position));
+        image.set_name(&data.name);
+        image.set_opacity(data.opacity);
+        image.set_scale(data.scale);
+        image.transform = data::matrix_from_data(&data.transform);
+        image.set_step(data.step);
+        if data.lock {
+            image.lock();
+        }
+        if !data.visible {
+            image.hide();
+        }
+        image.children = data::shapes_from_data(data.children)?;
+        Ok(image)
+    }
+
+    // This crazy method will be replaced with Template UI file when GTK-rs
+    // support that
+    pub fn connect_ui(&mut self, options_widget: &OptionsWidget) {
+        let create_label = |text: &str| {
+            let label = gtk::Label::new(text);
+            label.set_halign(gtk::Align::End);
+            label
+        };
+
+        let grid = gtk::Grid::new();
+        grid.set_property_margin(10);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(10);
+
+        let name_label = create_label(
+            gettext("Name:").as_str()
+        );
+        let name_entry = gtk::Entry::new();
+        name_entry.set_hexpand(true);
+        name_entry.set_text(self.name().as_str());
+        let name = self.name.clone();
+        let options = options_widget.clone();
+        name_entry.connect_property_text_notify(move |me| {
+            if let Some(text) = me.get_text() {
+                name.borrow_mut().clone_from(&text);
+                options.changed();
+            }
+        });
+        grid.attach(&name_label, 0, 0, 1, 1);
+        grid.attach(&name_entry, 1, 0, 1, 1);
+
+        let opacity_label = create_label(
+            gettext("Opacity:").as_str()
+        );
+        let opacity_scale = gtk::Scale::new_with_range(
+            gtk::Orientation::Horizontal, 0.0, 1.0, 0.05
+        );
+        opacity_scale.set_digits(2);
+        opacity_scale.set_value(self.get_opacity());
+        let opacity = self.opacity.clone();
+        let options = options_widget.clone();
+        opacity_scale.connect_value_changed(move |me| {
+            opacity.borrow_mut().clone_from(&me.get_value());
+            options.changed();
+        });
+        grid.attach(&opacity_label, 0, 1, 1, 1);
+        grid.attach(&opacity_scale, 1, 1, 1, 1);
+
+        let visible_label = create_label(gettext("Visible:").as_str());
+        let visible_switch = gtk::Switch::new();
+        visible_switch.set_halign(gtk::Align::Start);
+        visible_switch.set_active(self.is_visible());
+        let visible = self.visible.clone();
+        let options = options_widget.clone();
+        visible_switch.connect_property_active_notify(move |me| {
+            visible.borrow_mut().clone_from(&me.get_active());
+            options.changed();
+        });
+        grid.attach(&visible_label, 0, 2, 1, 1);
+        grid.attach(&visible_switch, 1, 2, 1, 1);
+
+        let step_label = create_label(gettext("Reveal Step:").as_str());
+        let step_spin = gtk::SpinButton::new_with_range(0.0, 99.0, 1.0);
+        step_spin.set_halign(gtk::Align::Start);
+        step_spin.set_value(self.get_step() as f64);
+        let step = self.step.clone();
+        let options = options_widget.clone();
+        step_spin.connect_property_value_notify(move |me| {
+            step.borrow_mut().clone_from(&(me.get_value_as_int() as u32));
+            options.changed();
+        });
+        grid.attach(&step_label, 0, 3, 1, 1);
+        grid.attach(&step_spin, 1, 3, 1, 1);
+
+        let lock_label = create_label(gettext("Lock:").as_str());
+        let lock_switch = gtk::Switch::new();
+        lock_switch.set_halign(gtk::Align::Start);
+        lock_switch.set_active(self.is_locked());
+        let lock = self.lock.clone();
+        let options = options_widget.clone();
+        lock_switch.connect_property_active_notify(move |me| {
+            lock.borrow_mut().clone_from(&me.get_active());
+            options.changed();
+        });
+        grid.attach(&lock_label, 0, 4, 1, 1);
+        grid.attach(&lock_switch, 1, 4, 1, 1);
+
+        grid.show_all();
+        let tab_label = gtk::Label::new(
+            gettext("Options").as_str()
+        );
+        options_widget.append_page(&grid, Some(&tab_label));
+    }
+
+    fn radius(&self) -> f64 {
+        6.0
+    }
+
+    fn fill_color(&self) -> RgbColor {
+        RgbColor::new(0.97, 0.97, 1.0) // #F8F8FF
+    }
+
+    fn stroke_color(&self) -> RgbColor {
+        RgbColor::new(0.47, 0.53, 0.60) // #778899
+    }
+
+    fn line_width(&self) -> f64 {
+        2.0
+    }
+
+    pub fn get_picture(&self) -> &Picture {
+        &self.picture
+    }
+
+    pub fn set_picture(&mut self, picture: Picture) {
+        self.picture = picture;
+    }
+
+    pub fn get_opacity(&self) -> f64 {
+        *self.opacity.borrow()
+    }
+
+    pub fn set_opacity(&mut self, opacity: f64) {
+        self.opacity.borrow_mut().clone_from(&opacity);
+    }
+
+    pub fn get_scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+    }
+
+    pub fn set_action(&mut self, action: Option<Actions>) {
+        self.action = action;
+    }
+
+    pub fn get_action(&self) -> Option<Actions> {
+        self.action.clone()
+    }
+
+    // half of the image diagonal before any scaling, used by resize action.
+    fn half_size(&self) -> Vector {
+        Vector::new(
+            self.picture.get_width() as f64 / 2.0,
+            self.picture.get_height() as f64 / 2.0
+        )
+    }
+
+    // the area covered by this image on the page
+    pub fn bounds(&self) -> Rectangle {
+        let half = self.half_size() * self.scale;
+        let corners = [
+            self.transform * Vector::new(-half.x, -half.y),
+            self.transform * Vector::new(half.x, -half.y),
+            self.transform * Vector::new(half.x, half.y),
+            self.transform * Vector::new(-half.x, half.y),
+        ];
+
+        let mut mins = self.position.clone();
+        let mut maxs = self.position.clone();
+        for corner in corners.iter() {
+            let point = self.position + corner;
+            mins.x = mins.x.min(point.x);
+            mins.y = mins.y.min(point.y);
+            maxs.x = maxs.x.max(point.x);
+            maxs.y = maxs.y.max(point.y);
+        }
+        Rectangle::new(mins, maxs)
+    }
+
+    fn resize_handle(&self) -> Point {
+        self.bounds().maxs().clone()
+    }
+
+    fn select_controller(
+        &self, pos: &Point, cr: &Context
+    ) -> Option<Actions> {
+        cr.new_path();
+
+        if self.is_selected() {
+            cr.save();
+            self.draw_resize_handle(cr, false);
+            let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+            cr.restore();
+            if hit {
+                return Some(Actions::Resize);
+            }
+        }
+
+        cr.new_path();
+        cr.rectangle(&self.bounds());
+        let hit = cr.in_fill(pos);
+        cr.new_path();
+        if hit {
+            return Some(Actions::MoveBody(pos.clone() - self.position.clone()));
+        }
+
+        None
+    }
+
+    fn move_body(&mut self, pos: &Point, cr: &Context) {
+        if let Some(Actions::MoveBody(offset)) = self.action {
+            self.position = cr.snap_to_grid(&(pos.clone() - offset));
+            if let Some(extents) = self.draw_extents(cr) {
+                self.position += cr.snap_extents(&extents);
+            }
+        }
+    }
+
+    fn resize(&mut self, pos: &Point) {
+        let diagonal = na::norm(&self.half_size());
+        if diagonal == 0.0 {
+            return;
+        }
+        let distance = na::distance(&self.position, pos);
+        // don't let the image vanish
+        self.scale = (distance / diagonal).max(0.05);
+    }
+
+    fn draw_picture(&self, cr: &Context) {
+        let half = self.half_size();
+        let transform = cairo::Matrix::new(
+            self.transform[(0, 0)], self.transform[(1, 0)],
+            self.transform[(0, 1)], self.transform[(1, 1)],
+            self.position.x, self.position.y
+        );
+
+        cr.save();
+        cr.transform(transform);
+        cr.scale(self.scale, self.scale);
+        cr.set_source_surface(self.picture.get_surface(), -half.x, -half.y);
+        cr.paint_with_alpha(self.get_opacity());
+        cr.restore();
+    }
+
+    fn draw_resize_handle(&self, cr: &Context, draw_it: bool) {
+        cr.new_path();
+
+        cr.set_line_width(self.line_width());
+        cr.circle(&self.resize_handle(), self.radius());
+
+        if draw_it {
+            cr.set_source_rgb(&self.fill_color());
+            cr.fill_preserve();
+            cr.set_source_rgb(&self.stroke_color());
+            cr.stroke();
+        }
+    }
+
+    fn draw_controllers(&self, cr: &Context) {
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.line_width());
+        cr.set_source_rgb(&self.stroke_color());
+        cr.set_dash(&[6.0], 0.0);
+        cr.rectangle(&self.bounds());
+        cr.stroke();
+        cr.restore();
+
+        cr.save();
+        self.draw_resize_handle(cr, true);
+        cr.restore();
+    }
+}
+
+impl Order for Image {}
+impl ShapeTrait for Image {}
+
+impl Draw for Image {
+    fn draw(&self, cr: &Context) {
+        if !self.is_visible() || !cr.is_revealed(self.get_step()) ||
+           self.get_opacity() == 0.0 {
+            return;
+        }
+
+        cr.save();
+
+        self.draw_picture(&cr);
+        if self.is_selected() && cr.get_show_controllers() {
+            self.draw_controllers(&cr);
+        }
+
+        // draw children if there are any.
+        for child in self.children.iter() {
+            child.draw(&cr);
+        }
+
+        cr.restore();
+    }
+
+    fn in_draw(&self, pos: &Point, cr: &Context) -> bool {
+        match self.select_controller(pos, cr) {
+            None => return false,
+            _ => return true,
+        };
+    }
+
+    fn draw_extents(&self, cr: &Context) -> Option<Rectangle> {
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.line_width());
+        cr.rectangle(&self.bounds());
+        let mut result = cr.user_to_device_rect(&cr.stroke_extents());
+        cr.restore();
+
+        cr.save();
+        self.draw_resize_handle(cr, false);
+        result.merge(&cr.user_to_device_rect(&cr.stroke_extents()));
+        cr.restore();
+
+        Some(result)
+    }
+}
+
+impl Snapshot for Image {
+    fn snapshot(&self) -> ShapeData {
+        ShapeData::Image(ImageData {
+            name: self.name(),
+            lock: self.is_locked(),
+            visible: self.is_visible(),
+            step: self.get_step(),
+            opacity: self.get_opacity(),
+            position: data::point_to_data(&self.position),
+            scale: self.scale,
+            transform: data::matrix_to_data(&self.transform),
+            picture: data::picture_to_data(&self.picture),
+            children: self.children.iter().map(|c| c.snapshot()).collect(),
+        })
+    }
+}
+
+impl Name for Image {
+    fn name(&self) -> String {
+        self.name.borrow().clone()
+    }
+
+    fn set_name(&mut self, name: &String) {
+        self.name.borrow_mut().clone_from(&name);
+    }
+}
+
+// images don't have a color, so we use the alpha channel as their opacity.
+impl Color for Image {
+    fn get_color(&self) -> RgbaColor {
+        RgbaColor::new(1.0, 1.0, 1.0, self.get_opacity())
+    }
+
+    fn set_color(&mut self, color: &RgbaColor) {
+        self.set_opacity(color.alpha);
+    }
+}
+
+impl Move for Image {
+    fn position(&self) -> Point {
+        self.position.clone()
+    }
+
+    fn move_to(&mut self, pos: &Point) {
+        self.position = pos.clone();
+    }
+
+    fn translate_by(&mut self, trans: &Translation) {
+        self.position = trans.transform_point(&self.position);
+    }
+
+    fn rotate_by(&mut self, rotate: &Rotation, origin: &Vector) {
+        let center = self.position() + origin;
+        let trans = Translation::new(-center.x, -center.y);
+        let mut pos = trans.transform_point(&self.position);
+        pos = rotate.transform_point(&pos);
+        self.position = trans.inverse_transform_point(&pos);
+        self.transform = rotate.matrix() * self.transform;
+    }
+
+    // the picture keep its aspect ratio, so the whole image (its center and
+    // its size) is scaled around @origin by the smaller factor
+    fn scale_by(&mut self, scale: &Vector, origin: &Point) {
+        let factor = scale.x.min(scale.y);
+        self.position = origin + (self.position - origin) * factor;
+        self.scale *= factor;
+    }
+}
+
+impl Select for Image {
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+
+    fn select(&mut self) {
+        self.selected = true;
+    }
+
+    fn unselect(&mut self) {
+        self.selected = false;
+    }
+
+    fn toggle_select(&mut self) -> bool {
+        self.selected = !self.selected;
+        self.selected
+    }
+}
+
+impl Lock for Image {
+    fn is_locked(&self) -> bool {
+        *self.lock.borrow()
+    }
+
+    fn lock(&mut self) {
+        if !self.is_locked() {
+            self.lock.borrow_mut().clone_from(&true);
+        }
+    }
+
+    fn unlock(&mut self) {
+        if self.is_locked() {
+            self.lock.borrow_mut().clone_from(&false);
+        }
+    }
+
+    fn toggle_lock(&mut self) -> bool {
+        self.lock.borrow_mut().clone_from(&!self.is_locked());
+        self.is_locked()
+    }
+}
+
+impl Visible for Image {
+    fn is_visible(&self) -> bool {
+        *self.visible.borrow()
+    }
+
+    fn show(&mut self) {
+        if !self.is_visible() {
+            self.visible.borrow_mut().clone_from(&true);
+        }
+    }
+
+    fn hide(&mut self) {
+        if self.is_visible() {
+            self.visible.borrow_mut().clone_from(&false);
+        }
+    }
+
+    fn toggle_visible(&mut self) -> bool {
+        self.visible.borrow_mut().clone_from(&!self.is_visible());
+        self.is_visible()
+    }
+}
+
+impl Reveal for Image {
+    fn get_step(&self) -> u32 {
+        *self.step.borrow()
+    }
+
+    fn set_step(&mut self, step: u32) {
+        self.step.borrow_mut().clone_from(&step);
+    }
+}
+
+impl Container for Image {
+    fn add(&mut self, child: Box<ShapeTrait>) {
+        self.children.push(child);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Box<ShapeTrait>> {
+        if index >= self.children.len() {
+            None
+        } else {
+            Some(self.children.remove(index))
+        }
+    }
+
+    fn get_children(&self) -> &Vec<Box<ShapeTrait>> {
+        &self.children
+    }
+
+    fn get_mut_children(&mut self) -> &mut Vec<Box<ShapeTrait>> {
+        &mut self.children
+    }
+
+    fn set_children(&mut self, children: Vec<Box<ShapeTrait>>) {
+        self.children = children;
+    }
+}
+
+impl Flip for Image {
+    fn flip_vertical(&mut self) {
+        let matrix = Matrix::new(1.0, 0.0, 0.0, -1.0);
+        self.transform = matrix * self.transform;
+    }
+
+    fn flip_horizontal(&mut self) {
+        let matrix = Matrix::new(-1.0, 0.0, 0.0, 1.0);
+        self.transform = matrix * self.transform;
+    }
+}
+
+impl Rotate for Image {
+    fn rotate_left(&mut self) {
+        let matrix = Matrix::new(0.0, 1.0, -1.0, 0.0);
+        self.transform = matrix * self.transform;
+    }
+
+    fn rotate_right(&mut self) {
+        let matrix = Matrix::new(0.0, -1.0, 1.0, 0.0);
+        self.transform = matrix * self.transform;
+    }
+}
+
+impl Event for Image {
+    fn motion_notify(
+        &mut self,
+        event: &EventMotion,
+        pos: &Point,
+        cr: &Context
+    ) -> bool {
+        if self.is_locked() || !self.is_visible() {
+            return false;
+        }
+
+        if event.get_state() == gdk::BUTTON1_MASK {
+            match self.action {
+                None => return false,
+                Some(Actions::MoveBody(..)) => {
+                    self.move_body(pos, cr);
+                },
+                Some(Actions::Resize) => {
+                    self.resize(pos);
+                },
+            };
+            return true;
+        }
+        false
+    }
+
+    fn button_press(
+        &mut self,
+        event: &EventButton,
+        pos: &Point,
+        cr: &Context,
+        options_widget: &OptionsWidget
+    ) -> bool {
+        if self.is_locked() || !self.is_visible() {
+            return false;
+        }
+
+        if event.get_button() == 1 {
+            let action = self.select_controller(pos, cr);
+            self.set_action(action);
+            if let None = self.get_action() {
+                self.unselect();
+                return false;
+            } else {
+                self.select();
+                self.connect_ui(options_widget);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl super::Mode for Image {
+    // images are created from an existing picture, so they are never in
+    // creating mode.
+    fn in_creating_mode(&self) -> bool {
+        false
+    }
+
+    fn in_editing_mode(&self) -> bool {
+        true
+    }
+}
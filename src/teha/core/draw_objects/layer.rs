@@ -17,14 +17,19 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 //
 
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
 use gdk::{EventMotion, EventButton};
-use gtk::{self, NotebookExtManual};
 
 use gettextrs::*;
 
 use ncollide::bounding_volume::BoundingVolume;
+use ncollide::partitioning::{BVT, BoundingVolumeInterferencesCollector};
 
 use core::context::Context;
+use core::data::{self, ShapeData, LayerData};
+use error::Error;
 use common::types::*;
 use super::*;
 
@@ -35,6 +40,66 @@ pub trait LayerTrait: Draw + Name + Lock + Visible + Container + Event + Order {
     fn flip_selected_children_vertically(&mut self);
     fn rotate_selected_children_to_left(&mut self);
     fn rotate_selected_children_to_right(&mut self);
+    fn snapshot_selected_children(&self) -> Vec<ShapeData>;
+    fn remove_selected_children(&mut self);
+    fn take_selected_children(&mut self) -> Vec<Box<ShapeTrait>>;
+    fn has_shapes_in_creating_mode(&self) -> bool;
+    fn select_children_in(&mut self, rect: &Rectangle, cr: &Context) -> bool;
+    fn idle_extents(&self, cr: &Context) -> Vec<Rectangle>;
+    fn invalidate_index(&self);
+    fn to_data(&self) -> LayerData;
+}
+
+// the tree is rebuilt once this many children changed since it was built,
+// until then they are checked one by one.
+const INDEX_REBUILD_AFTER: usize = 32;
+
+// the extents (in page coordinates) of the idle children and a bounding
+// volume tree over them. children are known by the address of their box, so
+// reordering them doesn't touch the index. selected shapes and shapes being
+// created are not in the index since they change with every event, they are
+// always checked.
+struct Index {
+    extents: HashMap<usize, Rectangle>,
+    tree: BVT<usize, Rectangle>,
+    // children whose entry in the tree is missing or out of date
+    outdated: HashSet<usize>,
+}
+
+impl Index {
+    fn new(extents: HashMap<usize, Rectangle>) -> Self {
+        let leaves = extents.iter().map(|(key, rect)| (*key, rect.clone())).collect();
+        Index {
+            extents: extents,
+            tree: BVT::new_balanced(leaves),
+            outdated: HashSet::new(),
+        }
+    }
+
+    fn forget(&mut self, key: usize) {
+        self.extents.remove(&key);
+        self.outdated.insert(key);
+    }
+
+    // return the keys of the indexed children that intersect @rect
+    fn query(&self, rect: &Rectangle) -> Vec<usize> {
+        let mut result = vec![];
+        {
+            let mut visitor =
+                BoundingVolumeInterferencesCollector::new(rect, &mut result);
+            self.tree.visit(&mut visitor);
+        }
+        result.retain(|key| !self.outdated.contains(key));
+        for key in self.outdated.iter() {
+            let intersects = self.extents
+                                 .get(key)
+                                 .map_or(false, |extents| extents.intersects(rect));
+            if intersects {
+                result.push(*key);
+            }
+        }
+        result
+    }
 }
 
 pub struct Layer {
@@ -42,6 +107,8 @@ pub struct Layer {
     name: String,
     lock: bool,
     visible: bool,
+    // built on the first event and kept up to date with the children
+    index: RefCell<Option<Index>>,
 }
 
 impl Layer {
@@ -52,8 +119,127 @@ impl Layer {
             name: gettext("Unnamed Layer"),
             lock: false,
             visible: true,
+            index: RefCell::new(None),
+        }
+    }
+
+    pub fn from_data(data: LayerData) -> Result<Self, Error> {
+        let mut layer = Layer::new();
+        layer.name = data.name;
+        layer.lock = data.lock;
+        layer.visible = data.visible;
+        layer.children = data::shapes_from_data(data.children)?;
+        Ok(layer)
+    }
+
+    fn is_indexed(child: &Box<ShapeTrait>) -> bool {
+        !child.is_selected() && !child.in_creating_mode()
+    }
+
+    // the address of the child's box, it doesn't change when the children
+    // are reordered.
+    fn key(child: &Box<ShapeTrait>) -> usize {
+        &**child as *const ShapeTrait as *const () as usize
+    }
+
+    fn user_extents(child: &Box<ShapeTrait>, cr: &Context) -> Option<Rectangle> {
+        child.draw_extents(cr).map(|extents| cr.device_to_user_rect(&extents))
+    }
+
+    // build the index, or bring it up to date: selected children leave it
+    // and children that became idle (or were added) are measured again. only
+    // the changed children are measured.
+    fn update_index(&self, cr: &Context) {
+        let mut index = self.index.borrow_mut();
+        if index.is_none() {
+            let extents = self.children
+                .iter()
+                .filter(|child| Layer::is_indexed(child))
+                .filter_map(|child| {
+                    Layer::user_extents(child, cr)
+                          .map(|extents| (Layer::key(child), extents))
+                })
+                .collect();
+            *index = Some(Index::new(extents));
+            return;
+        }
+
+        let index = index.as_mut().unwrap();
+        for child in self.children.iter() {
+            let key = Layer::key(child);
+            if !Layer::is_indexed(child) {
+                if index.extents.contains_key(&key) {
+                    index.forget(key);
+                }
+            } else if !index.extents.contains_key(&key) {
+                if let Some(extents) = Layer::user_extents(child, cr) {
+                    index.extents.insert(key, extents);
+                    index.outdated.insert(key);
+                }
+            }
+        }
+
+        if index.outdated.len() > INDEX_REBUILD_AFTER {
+            let extents = index.extents.drain().collect();
+            *index = Index::new(extents);
         }
     }
+
+    // drop the removed child from the index, an other child could get the
+    // same address later.
+    fn forget(&self, child: &Box<ShapeTrait>) {
+        if let Some(ref mut index) = *self.index.borrow_mut() {
+            index.forget(Layer::key(child));
+        }
+    }
+
+    // return the indices (in drawing order) of the children that may
+    // intersect @rect, the children that are not indexed are always
+    // included.
+    fn children_in(&self, rect: &Rectangle, cr: &Context) -> Vec<usize> {
+        self.update_index(cr);
+        let keys: HashSet<usize> = match *self.index.borrow() {
+            Some(ref index) => index.query(rect).into_iter().collect(),
+            None => HashSet::new(),
+        };
+        self.indices_of(&keys)
+    }
+
+    // like children_in() but without updating the index, so it can be used
+    // while drawing. return None if the index isn't built yet or if some
+    // idle children are missing from it, they were added or unselected
+    // since the last event.
+    fn query(&self, rect: &Rectangle) -> Option<Vec<usize>> {
+        let index = self.index.borrow();
+        let index = match *index {
+            Some(ref val) => val,
+            None => return None,
+        };
+
+        let stale = self.children.iter().any(|child| {
+            Layer::is_indexed(child) &&
+                !index.extents.contains_key(&Layer::key(child))
+        });
+        if stale {
+            return None;
+        }
+
+        let keys: HashSet<usize> = index.query(rect).into_iter().collect();
+        Some(self.indices_of(&keys))
+    }
+
+    // return the indices of the children that are in @keys or that are not
+    // indexed.
+    fn indices_of(&self, keys: &HashSet<usize>) -> Vec<usize> {
+        self.children
+            .iter()
+            .enumerate()
+            .filter(|&(_, child)| {
+                !Layer::is_indexed(child) || keys.contains(&Layer::key(child))
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
 }
 
 impl Default for Layer {
@@ -63,6 +249,7 @@ impl Default for Layer {
             name: gettext("Unnamed Layer"),
             lock: false,
             visible: true,
+            index: RefCell::new(None),
         }
     }
 }
@@ -77,7 +264,8 @@ impl LayerTrait for Layer {
         let mut i = self.children.len() - 1;
         loop {
             if self.children[i].in_creating_mode() {
-                self.children.remove(i);
+                let child = self.children.remove(i);
+                self.forget(&child);
             }
 
             if i == 0 {
@@ -93,7 +281,15 @@ impl LayerTrait for Layer {
             return;
         }
 
+        // the selected shapes could have been changed (e.g. from their
+        // options), they are measured again once they are idle.
+        let mut index = self.index.borrow_mut();
         for shape in self.children.iter_mut() {
+            if shape.is_selected() {
+                if let Some(ref mut index) = *index {
+                    index.forget(Layer::key(shape));
+                }
+            }
             shape.unselect();
         }
     }
@@ -145,6 +341,101 @@ impl LayerTrait for Layer {
             }
         }
     }
+
+    fn snapshot_selected_children(&self) -> Vec<ShapeData> {
+        self.children
+            .iter()
+            .filter(|shape| shape.is_selected())
+            .map(|shape| shape.snapshot())
+            .collect()
+    }
+
+    fn remove_selected_children(&mut self) {
+        self.take_selected_children();
+    }
+
+    fn take_selected_children(&mut self) -> Vec<Box<ShapeTrait>> {
+        let mut shapes = vec![];
+        let mut i = 0;
+        while i < self.children.len() {
+            if self.children[i].is_selected() {
+                let child = self.children.remove(i);
+                self.forget(&child);
+                shapes.push(child);
+            } else {
+                i += 1;
+            }
+        }
+        shapes
+    }
+
+    fn has_shapes_in_creating_mode(&self) -> bool {
+        self.children.iter().any(|shape| shape.in_creating_mode())
+    }
+
+    // select the children that intersect @rect (in page coordinates), return
+    // true if any got selected.
+    fn select_children_in(&mut self, rect: &Rectangle, cr: &Context) -> bool {
+        if self.lock || !self.visible {
+            return false;
+        }
+
+        let mut result = false;
+        for index in self.children_in(rect, cr) {
+            let child = &mut self.children[index];
+            if child.in_creating_mode() || child.is_locked() ||
+               !child.is_visible() {
+                continue;
+            }
+
+            let intersects = child.draw_extents(cr)
+                .map(|extents| cr.device_to_user_rect(&extents))
+                .map_or(false, |extents| extents.intersects(rect));
+            if intersects {
+                child.select();
+                result = true;
+            }
+        }
+        result
+    }
+
+    // return the extents (in page coordinates) of the visible idle
+    // children, they are taken from the index instead of measuring every
+    // child.
+    fn idle_extents(&self, cr: &Context) -> Vec<Rectangle> {
+        self.update_index(cr);
+        let index = self.index.borrow();
+        let index = match *index {
+            Some(ref val) => val,
+            None => return vec![],
+        };
+
+        self.children
+            .iter()
+            .filter(|child| Layer::is_indexed(child) && child.is_visible())
+            .filter_map(|child| index.extents.get(&Layer::key(child)).cloned())
+            .collect()
+    }
+
+    // drop the index, it's rebuilt on the next event. e.g. used when the
+    // zoom level change the size of the shapes controllers, or when the
+    // idle children are moved.
+    fn invalidate_index(&self) {
+        self.index.borrow_mut().take();
+    }
+
+    fn to_data(&self) -> LayerData {
+        LayerData {
+            name: self.name.clone(),
+            lock: self.lock,
+            visible: self.visible,
+            children: self.children
+                          .iter()
+                          .filter(|shape| !shape.in_creating_mode())
+                          .map(|shape| shape.snapshot())
+                          .collect(),
+        }
+    }
 }
 
 impl Draw for Layer {
@@ -153,7 +444,20 @@ impl Draw for Layer {
             return;
         }
 
-        for child in self.children.iter() {
+        // only draw the children inside the damaged area, if we have the
+        // index.
+        let (x1, y1, x2, y2) = cr.clip_extents();
+        let clip = Rectangle::new(Point::new(x1, y1), Point::new(x2, y2));
+        let indices = match self.query(&clip) {
+            Some(val) => val,
+            None => (0..self.children.len()).collect(),
+        };
+        for index in indices {
+            let child = &self.children[index];
+            // half created shapes are not part of the exported page
+            if !cr.get_show_controllers() && child.in_creating_mode() {
+                continue;
+            }
             child.draw(cr);
         }
     }
@@ -163,8 +467,9 @@ impl Draw for Layer {
             return false;
         }
 
-        for child in self.children.iter() {
-            if child.in_draw(pos, cr) {
+        let point = Rectangle::new(pos.clone(), pos.clone());
+        for index in self.children_in(&point, cr) {
+            if self.children[index].in_draw(pos, cr) {
                 return true;
             }
         }
@@ -246,15 +551,18 @@ impl Visible for Layer {
 }
 
 impl Container for Layer {
+    // the new child is measured on the next event
     fn add(&mut self, child: Box<ShapeTrait>) {
         self.children.push(child);
     }
 
     fn remove(&mut self, index: usize) -> Option<Box<ShapeTrait>> {
-        if index > self.children.len() {
+        if index >= self.children.len() {
             None
         } else {
-            Some(self.children.remove(index))
+            let child = self.children.remove(index);
+            self.forget(&child);
+            Some(child)
         }
     }
 
@@ -262,11 +570,16 @@ impl Container for Layer {
         &self.children
     }
 
+    // the children may be reordered through the returned vector, callers
+    // that move the idle children must call invalidate_index().
     fn get_mut_children(&mut self) -> &mut Vec<Box<ShapeTrait>> {
         &mut self.children
     }
 
     fn set_children(&mut self, children: Vec<Box<ShapeTrait>>) {
+        for child in self.children.iter() {
+            self.forget(child);
+        }
         self.children = children;
     }
 }
@@ -278,7 +591,17 @@ impl Event for Layer {
         pos: &Point,
         cr: &Context
     ) -> bool {
+        // locked or hidden layers don't accept any editing
+        if self.lock || !self.visible {
+            return false;
+        }
+
+        // only the selected shapes and the shapes being created follow the
+        // pointer.
         for child in self.children.iter_mut().rev() {
+            if Layer::is_indexed(child) {
+                continue;
+            }
             if child.motion_notify(event, pos, cr) {
                 return true;
             }
@@ -291,18 +614,26 @@ impl Event for Layer {
         event: &EventButton,
         pos: &Point,
         cr: &Context,
-        options_widget: &gtk::Notebook
+        options_widget: &OptionsWidget
     ) -> bool {
         let mut result = false;
 
-        // clean up options widget
-        let n_pages = options_widget.get_n_pages();
-        for _ in 0..n_pages {
-            options_widget.remove_page(Some(0));
+        // locked or hidden layers don't accept any editing
+        if self.lock || !self.visible {
+            self.unselect_all_shapes();
+            return false;
         }
 
-        // call children method
-        for child in self.children.iter_mut().rev() {
+        // idle children away from the pointer can't handle the event, so
+        // only the ones the index found get it.
+        let point = Rectangle::new(pos.clone(), pos.clone());
+        let candidates = self.children_in(&point, cr);
+
+        for (index, child) in self.children.iter_mut().enumerate().rev() {
+            if candidates.binary_search(&index).is_err() {
+                child.unselect();
+                continue;
+            }
             if child.button_press(event, pos, cr, options_widget) && !result {
                 child.select();
                 result = true;
@@ -310,7 +641,7 @@ impl Event for Layer {
                 child.unselect();
             }
         }
-        false
+        result
     }
 
     fn button_release(
@@ -319,7 +650,14 @@ impl Event for Layer {
         pos: &Point,
         cr: &Context
     ) -> bool {
+        if self.lock || !self.visible {
+            return false;
+        }
+
         for child in self.children.iter_mut().rev() {
+            if Layer::is_indexed(child) {
+                continue;
+            }
             if child.button_release(event, pos, cr) {
                 return true;
             }
@@ -327,3 +665,81 @@ impl Event for Layer {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cairo;
+
+    use super::*;
+    use super::super::line_arrow::{LineArrow, Mode};
+
+    fn arrow(x: f64) -> Box<ShapeTrait> {
+        let mut arrow = LineArrow::new(
+            Segment::new(Point::new(x, 10.0), Point::new(x + 20.0, 10.0))
+        );
+        arrow.set_mode(Mode::Editing);
+        Box::new(arrow)
+    }
+
+    #[test]
+    fn draw_through_a_clip() {
+        let mut layer = Layer::new();
+        layer.add(arrow(0.0));
+        layer.add(arrow(100.0));
+
+        let mut surface =
+            cairo::ImageSurface::create(cairo::Format::ARgb32, 200, 40)
+            .expect("Cairo: couldn't create surface");
+        let cr = cairo::Context::new(&surface);
+        let context = Context::new(&cr, 1.0, &Vector::new(0.0, 0.0));
+
+        // the index is stale until an event builds it
+        assert_eq!(layer.query(&Rectangle::new(Point::new(0.0, 0.0),
+                                               Point::new(40.0, 40.0))),
+                   None);
+        layer.children_in(&Rectangle::new(Point::new(0.0, 0.0),
+                                          Point::new(0.0, 0.0)),
+                          &context);
+
+        context.rectangle(&Rectangle::new(Point::new(90.0, 0.0),
+                                          Point::new(200.0, 40.0)));
+        context.clip();
+        let (x1, y1, x2, y2) = context.clip_extents();
+        let clip = Rectangle::new(Point::new(x1, y1), Point::new(x2, y2));
+        assert_eq!(layer.query(&clip), Some(vec![1]));
+
+        layer.draw(&context);
+        drop(context);
+        drop(cr);
+        surface.flush();
+
+        // only the second arrow is inside the clip
+        let stride = surface.get_stride() as usize;
+        let data = surface.get_data().unwrap();
+        let painted = |x: usize, y: usize| {
+            data[y * stride + x * 4..y * stride + x * 4 + 4]
+                .iter()
+                .any(|byte| *byte != 0)
+        };
+        assert!(!painted(10, 10));
+        assert!(painted(110, 10));
+    }
+
+    #[test]
+    fn query_is_stale_after_adding_a_child() {
+        let mut layer = Layer::new();
+        layer.add(arrow(0.0));
+
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
+            .expect("Cairo: couldn't create surface");
+        let cr = cairo::Context::new(&surface);
+        let context = Context::new(&cr, 1.0, &Vector::new(0.0, 0.0));
+        let everything = Rectangle::new(Point::new(-1000.0, -1000.0),
+                                        Point::new(1000.0, 1000.0));
+        layer.children_in(&everything, &context);
+        assert_eq!(layer.query(&everything), Some(vec![0]));
+
+        layer.add(arrow(50.0));
+        assert_eq!(layer.query(&everything), None);
+    }
+}
@@ -35,6 +35,8 @@ use na;
 use alga::linear::{Transformation, ProjectiveTransformation, Similarity};
 
 use core::context::Context;
+use core::data::{self, ShapeData, LineArrowData};
+use error::Error;
 use common::types::*;
 use super::*;
 
@@ -68,6 +70,7 @@ pub struct LineArrow {
     mode: Mode,
     // draw fields
     visible: Rc<RefCell<bool>>,
+    step: Rc<RefCell<u32>>,
     color: Rc<RefCell<RgbaColor>>,
     width: Rc<RefCell<f64>>,
     cap: Rc<RefCell<cairo::LineCap>>,
@@ -95,6 +98,7 @@ impl LineArrow {
             action: None,
             mode: Mode::CreatingLineArrow,
             visible: Rc::new(RefCell::new(true)),
+            step: Rc::new(RefCell::new(0)),
             color: Rc::new(RefCell::new(RgbaColor::new(0.0, 0.0, 0.0, 1.0))),
             width: Rc::new(RefCell::new(10.0)),
             cap: Rc::new(RefCell::new(cairo::LineCap::Round)),
@@ -109,9 +113,37 @@ impl LineArrow {
         }
     }
 
+    pub fn from_data(data: LineArrowData) -> Result<Self, Error> {
+        let mut line_arrow = LineArrow::new(Segment::new(
+            data::point_from_data(&data.start),
+            data::point_from_data(&data.end)
+        ));
+        line_arrow.set_mode(Mode::Editing);
+        line_arrow.set_name(&data.name);
+        line_arrow.set_color(&data::rgba_from_data(&data.color));
+        line_arrow.set_width(data.width);
+        line_arrow.set_cap(&data.cap.into());
+        line_arrow.set_join(&data.join.into());
+        line_arrow.set_dashes(&data.dashes);
+        line_arrow.set_offset(&data.offset);
+        line_arrow.set_curve_like(data.curve_like);
+        line_arrow.set_have_head(data.have_head);
+        line_arrow.set_step(data.step);
+        line_arrow.go_dir = data::vector_from_data(&data.go_dir);
+        line_arrow.arrive_dir = data::vector_from_data(&data.arrive_dir);
+        if data.lock {
+            line_arrow.lock();
+        }
+        if !data.visible {
+            line_arrow.hide();
+        }
+        line_arrow.children = data::shapes_from_data(data.children)?;
+        Ok(line_arrow)
+    }
+
     // This crazy method will be replaced with Template UI file when GTK-rs
     // support that
-    pub fn connect_ui(&mut self, options_widget: &gtk::Notebook) {
+    pub fn connect_ui(&mut self, options_widget: &OptionsWidget) {
         let create_label = |text: &str| {
             let label = gtk::Label::new(text);
             label.set_halign(gtk::Align::End);
@@ -130,9 +162,11 @@ impl LineArrow {
         name_entry.set_hexpand(true);
         name_entry.set_text(self.name().as_str());
         let name = self.name.clone();
+        let options = options_widget.clone();
         name_entry.connect_property_text_notify(move |me| {
             if let Some(text) = me.get_text() {
                 name.borrow_mut().clone_from(&text);
+                options.changed();
             }
         });
         grid.attach(&name_label, 0, 0, 1, 1);
@@ -154,6 +188,7 @@ impl LineArrow {
         );
         color_button.set_use_alpha(true);
         let color = self.color.clone();
+        let options = options_widget.clone();
         let color_chooser = color_button
             .clone()
             .dynamic_cast::<gtk::ColorChooser>()
@@ -165,6 +200,7 @@ impl LineArrow {
                 new_color.red, new_color.green, new_color.blue, new_color.alpha
             );
             color.borrow_mut().clone_from(&new_color);
+            options.changed();
         });
         grid.attach(&color_label, 0, 1, 1, 1);
         grid.attach(&color_button, 1, 1, 1, 1);
@@ -176,8 +212,10 @@ impl LineArrow {
         width_spin.set_digits(2);
         width_spin.set_value(self.get_width());
         let width = self.width.clone();
+        let options = options_widget.clone();
         width_spin.connect_property_value_notify(move |me| {
             width.borrow_mut().clone_from(&me.get_value());
+            options.changed();
         });
         grid.attach(&width_label, 0, 2, 1, 1);
         grid.attach(&width_spin, 1, 2, 1, 1);
@@ -273,6 +311,7 @@ impl LineArrow {
         let inner_cap_styles_square_icon = cap_styles_square_icon.clone();
         let inner_cap_styles_square_label = cap_styles_square_label.clone();
         let cap = self.cap.clone();
+        let options = options_widget.clone();
         cap_styles_listbox.connect_row_activated(move |_me, row| {
             inner_cap_styles_butt_icon.hide();
             inner_cap_styles_round_icon.hide();
@@ -305,7 +344,7 @@ impl LineArrow {
                 },
                 _ => unreachable!(),
             };
-
+            options.changed();
         });
 
         grid.attach(&cap_label, 0, 3, 1, 1);
@@ -406,6 +445,7 @@ impl LineArrow {
         let inner_join_styles_round_icon = join_styles_round_icon.clone();
         let inner_join_styles_round_label = join_styles_round_label.clone();
         let join = self.join.clone();
+        let options = options_widget.clone();
         join_styles_listbox.connect_row_activated(move |_me, row| {
             inner_join_styles_bevel_icon.hide();
             inner_join_styles_miter_icon.hide();
@@ -438,7 +478,7 @@ impl LineArrow {
                 },
                 _ => unreachable!(),
             };
-
+            options.changed();
         });
 
         grid.attach(&join_label, 0, 4, 1, 1);
@@ -471,29 +511,31 @@ impl LineArrow {
         let dashes = self.dashes.clone();
         let inner_dashes_on_spin = dashes_on_spin.clone();
         let inner_dashes_off_spin = dashes_off_spin.clone();
+        let options = options_widget.clone();
         dashes_on_spin.connect_property_value_notify(move |_me| {
             let on_value = inner_dashes_on_spin.get_value();
             let off_value = inner_dashes_off_spin.get_value();
             if on_value == 0.0 && off_value == 0.0 {
                 dashes.borrow_mut().clone_from(&vec![]);
-                return;
+            } else {
+                dashes.borrow_mut().clone_from(&vec![on_value, off_value]);
             }
-            let new_dashes = vec![on_value, off_value];
-            dashes.borrow_mut().clone_from(&new_dashes);
+            options.changed();
         });
 
         let dashes = self.dashes.clone();
         let inner_dashes_on_spin = dashes_on_spin.clone();
         let inner_dashes_off_spin = dashes_off_spin.clone();
+        let options = options_widget.clone();
         dashes_off_spin.connect_property_value_notify(move |_me| {
             let on_value = inner_dashes_on_spin.get_value();
             let off_value = inner_dashes_off_spin.get_value();
             if on_value == 0.0 && off_value == 0.0 {
                 dashes.borrow_mut().clone_from(&vec![]);
-                return;
+            } else {
+                dashes.borrow_mut().clone_from(&vec![on_value, off_value]);
             }
-            let new_dashes = vec![on_value, off_value];
-            dashes.borrow_mut().clone_from(&new_dashes);
+            options.changed();
         });
 
         grid.attach(&dashes_label, 0, 5, 1, 2);
@@ -505,8 +547,10 @@ impl LineArrow {
         curve_like_switch.set_halign(gtk::Align::Start);
         curve_like_switch.set_active(self.get_curve_like());
         let curve_like = self.curve_like.clone();
+        let options = options_widget.clone();
         curve_like_switch.connect_property_active_notify(move |me| {
             curve_like.borrow_mut().clone_from(&me.get_active());
+            options.changed();
         });
         grid.attach(&curve_like_label, 0, 7, 1, 1);
         grid.attach(&curve_like_switch, 1, 7, 1, 1);
@@ -516,8 +560,10 @@ impl LineArrow {
         have_head_switch.set_halign(gtk::Align::Start);
         have_head_switch.set_active(self.get_have_head());
         let have_head = self.have_head.clone();
+        let options = options_widget.clone();
         have_head_switch.connect_property_active_notify(move |me| {
             have_head.borrow_mut().clone_from(&me.get_active());
+            options.changed();
         });
         grid.attach(&have_head_label, 0, 8, 1, 1);
         grid.attach(&have_head_switch, 1, 8, 1, 1);
@@ -527,22 +573,39 @@ impl LineArrow {
         visible_switch.set_halign(gtk::Align::Start);
         visible_switch.set_active(self.is_visible());
         let visible = self.visible.clone();
+        let options = options_widget.clone();
         visible_switch.connect_property_active_notify(move |me| {
             visible.borrow_mut().clone_from(&me.get_active());
+            options.changed();
         });
         grid.attach(&visible_label, 0, 9, 1, 1);
         grid.attach(&visible_switch, 1, 9, 1, 1);
 
+        let step_label = create_label(gettext("Reveal Step:").as_str());
+        let step_spin = gtk::SpinButton::new_with_range(0.0, 99.0, 1.0);
+        step_spin.set_halign(gtk::Align::Start);
+        step_spin.set_value(self.get_step() as f64);
+        let step = self.step.clone();
+        let options = options_widget.clone();
+        step_spin.connect_property_value_notify(move |me| {
+            step.borrow_mut().clone_from(&(me.get_value_as_int() as u32));
+            options.changed();
+        });
+        grid.attach(&step_label, 0, 10, 1, 1);
+        grid.attach(&step_spin, 1, 10, 1, 1);
+
         let lock_label = create_label(gettext("Lock:").as_str());
         let lock_switch = gtk::Switch::new();
         lock_switch.set_halign(gtk::Align::Start);
         lock_switch.set_active(self.is_locked());
         let lock = self.lock.clone();
+        let options = options_widget.clone();
         lock_switch.connect_property_active_notify(move |me| {
             lock.borrow_mut().clone_from(&me.get_active());
+            options.changed();
         });
-        grid.attach(&lock_label, 0, 10, 1, 1);
-        grid.attach(&lock_switch, 1, 10, 1, 1);
+        grid.attach(&lock_label, 0, 11, 1, 1);
+        grid.attach(&lock_switch, 1, 11, 1, 1);
 
         grid.show_all();
         let tab_label = gtk::Label::new(
@@ -680,6 +743,8 @@ impl LineArrow {
         self.action.clone()
     }
 
+    // the context is restored before returning since it's shared by all the
+    // hit tests and measurements of the page.
     fn select_controller(
         &self, pos: &Point, cr: &Context
     ) -> Option<Actions> {
@@ -692,100 +757,99 @@ impl LineArrow {
             if self.get_curve_like() {
                 cr.save();
                 self.draw_go_direction(cr, false);
-                if cr.in_stroke(pos) || cr.in_fill(pos) {
+                let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+                cr.restore();
+                if hit {
                     return Some(Actions::MoveGoDirection);
                 }
-                cr.restore();
 
                 cr.save();
                 self.draw_arrive_direction(cr, false);
-                if cr.in_stroke(pos) || cr.in_fill(pos) {
+                let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+                cr.restore();
+                if hit {
                     return Some(Actions::MoveArriveDirection);
                 }
-                cr.restore();
             }
 
             cr.save();
             self.draw_start_point(cr, false);
-            if cr.in_stroke(pos) || cr.in_fill(pos) {
-                if self.is_selected() {
-                    return Some(Actions::MoveStartPoint);
-                } else {
-                    return Some(Actions::MoveBody(vec_a, vec_b));
-                }
-            }
+            let hit = cr.in_stroke(pos) || cr.in_fill(pos);
             cr.restore();
+            if hit {
+                return Some(Actions::MoveStartPoint);
+            }
 
             cr.save();
             self.draw_end_point(cr, false);
-            if cr.in_stroke(pos) || cr.in_fill(pos) {
-                if self.is_selected() {
-                    return Some(Actions::MoveEndPoint);
-                } else {
-                    return Some(Actions::MoveBody(vec_a, vec_b));
-                }
-            }
+            let hit = cr.in_stroke(pos) || cr.in_fill(pos);
             cr.restore();
+            if hit {
+                return Some(Actions::MoveEndPoint);
+            }
         }
 
         cr.save();
         self.draw_segment(cr, false);
-        if cr.in_stroke(pos) {
+        let hit = cr.in_stroke(pos);
+        cr.restore();
+        if hit {
             return Some(Actions::MoveBody(vec_a, vec_b));
         }
-        cr.restore();
 
         if self.get_have_head() {
             cr.save();
             self.draw_head(cr, false);
-            if cr.in_fill(pos) {
+            let hit = cr.in_fill(pos);
+            cr.restore();
+            if hit {
                 return Some(Actions::MoveBody(vec_a, vec_b));
             }
-            cr.restore();
         }
 
-        // cr.save();
-        // self.draw_tail(cr, false);
-        // if cr.in_fill(pos) {
-        //     return Some(Actions::MoveBody);
-        //     println!("draw_tail");
-        // }
-        // cr.restore();
-
         None
     }
 
-    fn move_segment(&mut self, pos: &Point) {
+    // the start point snap to the grid and the end point keep its distance
+    // from it, then the whole arrow get aligned with the other shapes.
+    fn move_segment(&mut self, pos: &Point, cr: &Context) {
         if let Some(Actions::MoveBody(vec_a, vec_b)) = self.action {
-            let a = pos.clone() - vec_a.clone();
-            let b = pos.clone() - vec_b.clone();
+            let a = cr.snap_to_grid(&(pos.clone() - vec_a.clone()));
+            let b = a.clone() + (vec_a - vec_b);
             self.segment = Segment::new(a, b);
+
+            if let Some(extents) = self.draw_extents(cr) {
+                let offset = cr.snap_extents(&extents);
+                self.segment = Segment::new(a + offset, b + offset);
+            }
         }
     }
 
-    fn move_go_dir(&mut self, pos: &Point) {
+    fn move_go_dir(&mut self, pos: &Point, cr: &Context) {
+        let pos = cr.snap(pos);
         self.go_dir = Vector::new(
             pos.x - self.segment.a().x,
             pos.y - self.segment.a().y
         );
     }
 
-    fn move_arrive_dir(&mut self, pos: &Point) {
+    fn move_arrive_dir(&mut self, pos: &Point, cr: &Context) {
+        let pos = cr.snap(pos);
         self.arrive_dir = Vector::new(
             pos.x - self.segment.b().x,
             pos.y - self.segment.b().y
         );
     }
 
-    fn move_start_point(&mut self, pos: &Point) {
+    fn move_start_point(&mut self, pos: &Point, cr: &Context) {
         let b = self.segment.b().clone();
-        let a = pos.clone();
+        let a = cr.snap(pos);
         self.segment = Segment::new(a, b);
     }
 
-    fn move_end_point(&mut self, pos: &Point) {
+    fn move_end_point(&mut self, pos: &Point, cr: &Context) {
         let a = self.segment.a().clone();
-        let b = pos.clone();
+        let b = cr.snap(pos);
         self.segment = Segment::new(a, b);
     }
 
@@ -996,14 +1060,15 @@ impl ShapeTrait for LineArrow {}
 
 impl Draw for LineArrow {
     fn draw(&self, cr: &Context) {
-        if !self.is_visible() || self.get_color().alpha == 0.0 {
+        if !self.is_visible() || !cr.is_revealed(self.get_step()) ||
+           self.get_color().alpha == 0.0 {
             return;
         }
 
         cr.save();
 
         self.draw_body(&cr, true);
-        if self.is_selected() {
+        if self.is_selected() && cr.get_show_controllers() {
             self.draw_helper_shapes(&cr);
             self.draw_controllers(&cr);
         }
@@ -1074,6 +1139,30 @@ impl Draw for LineArrow {
     }
 }
 
+impl Snapshot for LineArrow {
+    fn snapshot(&self) -> ShapeData {
+        ShapeData::LineArrow(LineArrowData {
+            name: self.name(),
+            lock: self.is_locked(),
+            visible: self.is_visible(),
+            step: self.get_step(),
+            color: data::rgba_to_data(&self.get_color()),
+            width: self.get_width(),
+            cap: self.get_cap().into(),
+            join: self.get_join().into(),
+            dashes: self.get_dashes(),
+            offset: self.get_offset(),
+            start: data::point_to_data(self.segment.a()),
+            end: data::point_to_data(self.segment.b()),
+            curve_like: self.get_curve_like(),
+            have_head: self.get_have_head(),
+            go_dir: data::vector_to_data(&self.go_dir),
+            arrive_dir: data::vector_to_data(&self.arrive_dir),
+            children: self.children.iter().map(|c| c.snapshot()).collect(),
+        })
+    }
+}
+
 impl Name for LineArrow {
     fn name(&self) -> String {
         self.name.borrow().clone()
@@ -1132,6 +1221,16 @@ impl Move for LineArrow {
 
         self.segment = Segment::new(a, b);
     }
+
+    // the ends and the curve directions are stretched, the line width and
+    // the arrow heads keep their size
+    fn scale_by(&mut self, scale: &Vector, origin: &Point) {
+        let a = origin + (self.segment.a() - origin).component_mul(scale);
+        let b = origin + (self.segment.b() - origin).component_mul(scale);
+        self.segment = Segment::new(a, b);
+        self.go_dir = self.go_dir.component_mul(scale);
+        self.arrive_dir = self.arrive_dir.component_mul(scale);
+    }
 }
 
 impl Select for LineArrow {
@@ -1199,13 +1298,23 @@ impl Visible for LineArrow {
     }
 }
 
+impl Reveal for LineArrow {
+    fn get_step(&self) -> u32 {
+        *self.step.borrow()
+    }
+
+    fn set_step(&mut self, step: u32) {
+        self.step.borrow_mut().clone_from(&step);
+    }
+}
+
 impl Container for LineArrow {
     fn add(&mut self, child: Box<ShapeTrait>) {
         self.children.push(child);
     }
 
     fn remove(&mut self, index: usize) -> Option<Box<ShapeTrait>> {
-        if index > self.children.len() {
+        if index >= self.children.len() {
             None
         } else {
             Some(self.children.remove(index))
@@ -1335,7 +1444,7 @@ impl Event for LineArrow {
         &mut self,
         event: &EventMotion,
         pos: &Point,
-        _cr: &Context
+        cr: &Context
     ) -> bool {
         if self.is_locked() || !self.is_visible() {
             return false;
@@ -1347,19 +1456,19 @@ impl Event for LineArrow {
                     match self.action {
                         None => return false,
                         Some(Actions::MoveGoDirection) => {
-                            self.move_go_dir(pos);
+                            self.move_go_dir(pos, cr);
                         },
                         Some(Actions::MoveArriveDirection) => {
-                            self.move_arrive_dir(pos);
+                            self.move_arrive_dir(pos, cr);
                         },
                         Some(Actions::MoveStartPoint) => {
-                            self.move_start_point(pos);
+                            self.move_start_point(pos, cr);
                         },
                         Some(Actions::MoveEndPoint) => {
-                            self.move_end_point(pos);
+                            self.move_end_point(pos, cr);
                         },
                         Some(Actions::MoveBody(..)) => {
-                            self.move_segment(pos);
+                            self.move_segment(pos, cr);
                         },
                     };
                     return true;
@@ -1368,16 +1477,16 @@ impl Event for LineArrow {
             _ => {
                 match self.action {
                     Some(Actions::MoveStartPoint) => {
-                        self.move_start_point(&pos);
+                        self.move_start_point(pos, cr);
                     },
                     Some(Actions::MoveEndPoint) => {
-                        self.move_end_point(&pos);
+                        self.move_end_point(pos, cr);
                     },
                     Some(Actions::MoveGoDirection) => {
-                        self.move_go_dir(&pos);
+                        self.move_go_dir(pos, cr);
                     },
                     Some(Actions::MoveArriveDirection) => {
-                        self.move_arrive_dir(&pos);
+                        self.move_arrive_dir(pos, cr);
                     },
                     _ => unreachable!(),
                 };
@@ -1392,7 +1501,7 @@ impl Event for LineArrow {
         event: &EventButton,
         pos: &Point,
         cr: &Context,
-        options_widget: &gtk::Notebook
+        options_widget: &OptionsWidget
     ) -> bool {
         if self.is_locked() {
             return false;
@@ -1405,8 +1514,8 @@ impl Event for LineArrow {
                     match self.get_action() {
                         Some(Actions::MoveStartPoint) => {
                             self.action = Some(Actions::MoveEndPoint);
-                            self.move_start_point(pos);
-                            self.move_end_point(pos);
+                            self.move_start_point(pos, cr);
+                            self.move_end_point(pos, cr);
                             self.show();
                         },
                         Some(Actions::MoveEndPoint) => {
@@ -1417,16 +1526,16 @@ impl Event for LineArrow {
                                 self.action = None;
                                 self.mode = Mode::Editing;
                             }
-                           self.move_end_point(pos);
+                           self.move_end_point(pos, cr);
                         },
                         Some(Actions::MoveGoDirection) => {
                             self.action = Some(Actions::MoveArriveDirection);
-                            self.move_go_dir(pos);
+                            self.move_go_dir(pos, cr);
                         },
                         Some(Actions::MoveArriveDirection) => {
                             self.action = None;
                             self.mode = Mode::Editing;
-                            self.move_arrive_dir(pos);
+                            self.move_arrive_dir(pos, cr);
                         },
                         _ => unreachable!(),
                     };
@@ -0,0 +1,1148 @@
+//
+// magnifier.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+use cairo;
+use gdk::{self, EventMotion, EventButton};
+use gtk::prelude::*;
+use gtk::{self, SwitchExt, ContainerExt, WidgetExt, GridExt, NotebookExtManual,
+          EntryExt, ColorButtonExt, ColorChooserExt, Cast, SpinButtonExt,
+          ComboBoxExt, ComboBoxTextExt};
+
+use gettextrs::*;
+
+use ncollide::bounding_volume::BoundingVolume;
+use na;
+use alga::linear::Transformation;
+
+use core::context::Context;
+use core::data::{self, ShapeData, MagnifierData};
+use error::Error;
+use common::types::*;
+use super::*;
+
+// the zoom of new magnifiers
+const DEFAULT_ZOOM: f64 = 2.0;
+// the space between the source region and the lens of new magnifiers
+const LENS_GAP: f64 = 40.0;
+// the smallest source region, so the lens never vanish
+const MIN_SOURCE_SIZE: f64 = 4.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum LensShape {
+    Circle,
+    Rectangle,
+}
+
+// how the source region is tied to the lens
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Connector {
+    Nothing,
+    Line,
+    Cone,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Mode {
+    Editing,
+    Creating,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Actions {
+    // the corner where the user started dragging the source region
+    PickSource(Point),
+    MoveSource(Vector),
+    ResizeSource,
+    MoveLens(Vector),
+    Zoom,
+}
+
+// a callout that redraw a region of the page enlarged inside a lens, the
+// lens show the page color and the background image (e.g. a screenshot) so
+// it follows any change of the background.
+pub struct Magnifier {
+    children: Vec<Box<ShapeTrait>>,
+    // ID field
+    name: Rc<RefCell<String>>,
+    // control fields
+    lock: Rc<RefCell<bool>>,
+    selected: bool,
+    // this field for Event trait.
+    action: Option<Actions>,
+    mode: Mode,
+    // draw fields
+    visible: Rc<RefCell<bool>>,
+    step: Rc<RefCell<u32>>,
+    lens_shape: Rc<RefCell<LensShape>>,
+    connector: Rc<RefCell<Connector>>,
+    border_color: Rc<RefCell<RgbaColor>>,
+    border_width: Rc<RefCell<f64>>,
+    // the region of the page shown in the lens
+    source: Rectangle,
+    lens_center: Point,
+    // the lens is the source region scaled by the zoom
+    zoom: Rc<RefCell<f64>>,
+}
+
+impl Magnifier {
+    pub fn new() -> Self {
+        let origin = Point::new(0.0, 0.0);
+        Magnifier {
+            children: vec![],
+            name: Rc::new(RefCell::new(gettext("Magnifier"))),
+            lock: Rc::new(RefCell::new(false)),
+            selected: false,
+            action: None,
+            mode: Mode::Creating,
+            visible: Rc::new(RefCell::new(true)),
+            step: Rc::new(RefCell::new(0)),
+            lens_shape: Rc::new(RefCell::new(LensShape::Circle)),
+            connector: Rc::new(RefCell::new(Connector::Cone)),
+            // #3584E4
+            border_color:
+                Rc::new(RefCell::new(RgbaColor::new(0.21, 0.52, 0.89, 1.0))),
+            border_width: Rc::new(RefCell::new(3.0)),
+            source: Rectangle::new(origin, origin),
+            lens_center: origin,
+            zoom: Rc::new(RefCell::new(DEFAULT_ZOOM)),
+        }
+    }
+
+    pub fn from_data(data: MagnifierData) -> Result<Self, Error> {
+        let mut magnifier = Magnifier::new();
+        magnifier.mode = Mode::Editing;
+        magnifier.set_name(&data.name);
+        magnifier.source = Rectangle::new(
+            data::point_from_data(&data.source_mins),
+            data::point_from_data(&data.source_maxs)
+        );
+        magnifier.lens_center = data::point_from_data(&data.lens_center);
+        magnifier.set_zoom(data.zoom);
+        magnifier.set_lens_shape(data.lens_shape);
+        magnifier.set_connector(data.connector);
+        magnifier.set_color(&data::rgba_from_data(&data.border_color));
+        magnifier.set_border_width(data.border_width);
+        magnifier.set_step(data.step);
+        if data.lock {
+            magnifier.lock();
+        }
+        if !data.visible {
+            magnifier.hide();
+        }
+        magnifier.children = data::shapes_from_data(data.children)?;
+        Ok(magnifier)
+    }
+
+    // This crazy method will be replaced with Template UI file when GTK-rs
+    // support that
+    pub fn connect_ui(&mut self, options_widget: &OptionsWidget) {
+        let create_label = |text: &str| {
+            let label = gtk::Label::new(text);
+            label.set_halign(gtk::Align::End);
+            label
+        };
+
+        let grid = gtk::Grid::new();
+        grid.set_property_margin(10);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(10);
+
+        let name_label = create_label(
+            gettext("Name:").as_str()
+        );
+        let name_entry = gtk::Entry::new();
+        name_entry.set_hexpand(true);
+        name_entry.set_text(self.name().as_str());
+        let name = self.name.clone();
+        let options = options_widget.clone();
+        name_entry.connect_property_text_notify(move |me| {
+            if let Some(text) = me.get_text() {
+                name.borrow_mut().clone_from(&text);
+                options.changed();
+            }
+        });
+        grid.attach(&name_label, 0, 0, 1, 1);
+        grid.attach(&name_entry, 1, 0, 1, 1);
+
+        let shape_label = create_label(gettext("Lens:").as_str());
+        let shape_combo = gtk::ComboBoxText::new();
+        shape_combo.append_text(gettext("Circle").as_str());
+        shape_combo.append_text(gettext("Rectangle").as_str());
+        shape_combo.set_active(match self.get_lens_shape() {
+            LensShape::Circle => 0,
+            LensShape::Rectangle => 1,
+        });
+        let lens_shape = self.lens_shape.clone();
+        let options = options_widget.clone();
+        shape_combo.connect_changed(move |me| {
+            let shape = match me.get_active() {
+                1 => LensShape::Rectangle,
+                _ => LensShape::Circle,
+            };
+            lens_shape.borrow_mut().clone_from(&shape);
+            options.changed();
+        });
+        grid.attach(&shape_label, 0, 1, 1, 1);
+        grid.attach(&shape_combo, 1, 1, 1, 1);
+
+        let zoom_label = create_label(gettext("Zoom:").as_str());
+        let zoom_spin = gtk::SpinButton::new_with_range(1.0, 20.0, 0.5);
+        zoom_spin.set_digits(1);
+        zoom_spin.set_value(self.get_zoom());
+        let zoom = self.zoom.clone();
+        let options = options_widget.clone();
+        zoom_spin.connect_property_value_notify(move |me| {
+            zoom.borrow_mut().clone_from(&me.get_value());
+            options.changed();
+        });
+        grid.attach(&zoom_label, 0, 2, 1, 1);
+        grid.attach(&zoom_spin, 1, 2, 1, 1);
+
+        let connector_label = create_label(gettext("Connector:").as_str());
+        let connector_combo = gtk::ComboBoxText::new();
+        connector_combo.append_text(gettext("None").as_str());
+        connector_combo.append_text(gettext("Line").as_str());
+        connector_combo.append_text(gettext("Cone").as_str());
+        connector_combo.set_active(match self.get_connector() {
+            Connector::Nothing => 0,
+            Connector::Line => 1,
+            Connector::Cone => 2,
+        });
+        let connector = self.connector.clone();
+        let options = options_widget.clone();
+        connector_combo.connect_changed(move |me| {
+            let new_connector = match me.get_active() {
+                0 => Connector::Nothing,
+                1 => Connector::Line,
+                _ => Connector::Cone,
+            };
+            connector.borrow_mut().clone_from(&new_connector);
+            options.changed();
+        });
+        grid.attach(&connector_label, 0, 3, 1, 1);
+        grid.attach(&connector_combo, 1, 3, 1, 1);
+
+        let color_label = create_label(
+            gettext("Border Color:").as_str()
+        );
+        let color_init_value = self.get_color();
+        let color_init_value = gdk::RGBA {
+            red: color_init_value.color.red,
+            green: color_init_value.color.green,
+            blue: color_init_value.color.blue,
+            alpha: color_init_value.alpha
+        };
+        let color_button = gtk::ColorButton::new_with_rgba(&color_init_value);
+        color_button.set_title(
+            gettext("Border Color").as_str()
+        );
+        color_button.set_use_alpha(true);
+        let color = self.border_color.clone();
+        let options = options_widget.clone();
+        let color_chooser = color_button
+            .clone()
+            .dynamic_cast::<gtk::ColorChooser>()
+            .expect("GtkButtonChooser does implmente GtkColorChooser \
+                     interface");
+        color_chooser.connect_property_rgba_notify(move |me| {
+            let new_color = me.get_rgba();
+            let new_color = RgbaColor::new(
+                new_color.red, new_color.green, new_color.blue, new_color.alpha
+            );
+            color.borrow_mut().clone_from(&new_color);
+            options.changed();
+        });
+        grid.attach(&color_label, 0, 4, 1, 1);
+        grid.attach(&color_button, 1, 4, 1, 1);
+
+        let width_label = create_label(
+            gettext("Border Width:").as_str()
+        );
+        let width_spin = gtk::SpinButton::new_with_range(0.0, 50.0, 1.0);
+        width_spin.set_digits(1);
+        width_spin.set_value(self.get_border_width());
+        let width = self.border_width.clone();
+        let options = options_widget.clone();
+        width_spin.connect_property_value_notify(move |me| {
+            width.borrow_mut().clone_from(&me.get_value());
+            options.changed();
+        });
+        grid.attach(&width_label, 0, 5, 1, 1);
+        grid.attach(&width_spin, 1, 5, 1, 1);
+
+        let visible_label = create_label(gettext("Visible:").as_str());
+        let visible_switch = gtk::Switch::new();
+        visible_switch.set_halign(gtk::Align::Start);
+        visible_switch.set_active(self.is_visible());
+        let visible = self.visible.clone();
+        let options = options_widget.clone();
+        visible_switch.connect_property_active_notify(move |me| {
+            visible.borrow_mut().clone_from(&me.get_active());
+            options.changed();
+        });
+        grid.attach(&visible_label, 0, 6, 1, 1);
+        grid.attach(&visible_switch, 1, 6, 1, 1);
+
+        let step_label = create_label(gettext("Reveal Step:").as_str());
+        let step_spin = gtk::SpinButton::new_with_range(0.0, 99.0, 1.0);
+        step_spin.set_halign(gtk::Align::Start);
+        step_spin.set_value(self.get_step() as f64);
+        let step = self.step.clone();
+        let options = options_widget.clone();
+        step_spin.connect_property_value_notify(move |me| {
+            step.borrow_mut().clone_from(&(me.get_value_as_int() as u32));
+            options.changed();
+        });
+        grid.attach(&step_label, 0, 7, 1, 1);
+        grid.attach(&step_spin, 1, 7, 1, 1);
+
+        let lock_label = create_label(gettext("Lock:").as_str());
+        let lock_switch = gtk::Switch::new();
+        lock_switch.set_halign(gtk::Align::Start);
+        lock_switch.set_active(self.is_locked());
+        let lock = self.lock.clone();
+        let options = options_widget.clone();
+        lock_switch.connect_property_active_notify(move |me| {
+            lock.borrow_mut().clone_from(&me.get_active());
+            options.changed();
+        });
+        grid.attach(&lock_label, 0, 8, 1, 1);
+        grid.attach(&lock_switch, 1, 8, 1, 1);
+
+        grid.show_all();
+        let tab_label = gtk::Label::new(
+            gettext("Options").as_str()
+        );
+        options_widget.append_page(&grid, Some(&tab_label));
+    }
+
+    fn radius(&self) -> f64 {
+        6.0
+    }
+
+    fn fill_color(&self) -> RgbColor {
+        RgbColor::new(0.97, 0.97, 1.0) // #F8F8FF
+    }
+
+    fn stroke_color(&self) -> RgbColor {
+        RgbColor::new(0.47, 0.53, 0.60) // #778899
+    }
+
+    fn line_width(&self) -> f64 {
+        2.0
+    }
+
+    pub fn get_lens_shape(&self) -> LensShape {
+        *self.lens_shape.borrow()
+    }
+
+    pub fn set_lens_shape(&mut self, shape: LensShape) {
+        self.lens_shape.borrow_mut().clone_from(&shape);
+    }
+
+    pub fn get_connector(&self) -> Connector {
+        *self.connector.borrow()
+    }
+
+    pub fn set_connector(&mut self, connector: Connector) {
+        self.connector.borrow_mut().clone_from(&connector);
+    }
+
+    pub fn get_border_width(&self) -> f64 {
+        *self.border_width.borrow()
+    }
+
+    pub fn set_border_width(&mut self, width: f64) {
+        self.border_width.borrow_mut().clone_from(&width);
+    }
+
+    pub fn get_zoom(&self) -> f64 {
+        *self.zoom.borrow()
+    }
+
+    pub fn set_zoom(&mut self, zoom: f64) {
+        self.zoom.borrow_mut().clone_from(&zoom.max(1.0));
+    }
+
+    pub fn get_source(&self) -> &Rectangle {
+        &self.source
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        self.mode.clone()
+    }
+
+    pub fn set_action(&mut self, action: Option<Actions>) {
+        self.action = action;
+    }
+
+    pub fn get_action(&self) -> Option<Actions> {
+        self.action.clone()
+    }
+
+    fn source_center(&self) -> Point {
+        na::center(self.source.mins(), self.source.maxs())
+    }
+
+    // half the size of the source region
+    fn source_half(&self) -> Vector {
+        (self.source.maxs() - self.source.mins()) / 2.0
+    }
+
+    pub fn lens(&self) -> Rectangle {
+        let half = self.source_half() * self.get_zoom();
+        Rectangle::new(self.lens_center - half, self.lens_center + half)
+    }
+
+    fn source_handle(&self) -> Point {
+        self.source.maxs().clone()
+    }
+
+    fn zoom_handle(&self) -> Point {
+        self.lens().maxs().clone()
+    }
+
+    // add the outline of the lens shape filling @rect to the path
+    fn region(&self, cr: &Context, rect: &Rectangle) {
+        let center = na::center(rect.mins(), rect.maxs());
+        let half = (rect.maxs() - rect.mins()) / 2.0;
+        if self.get_lens_shape() == LensShape::Rectangle ||
+           half.x <= 0.0 || half.y <= 0.0 {
+            cr.rectangle(rect);
+            return;
+        }
+
+        cr.save();
+        cr.translate(center.x, center.y);
+        cr.scale(half.x, half.y);
+        cr.new_sub_path();
+        cr.arc(0.0, 0.0, 1.0, 0.0, 2.0 * PI);
+        cr.restore();
+    }
+
+    // the point of the source region outline that is the farthest along
+    // @dir, relative to the source center. the same point scaled by the zoom
+    // is on the lens outline.
+    fn support_point(&self, dir: &Vector) -> Vector {
+        let half = self.source_half();
+        match self.get_lens_shape() {
+            LensShape::Rectangle => Vector::new(
+                half.x * dir.x.signum(), half.y * dir.y.signum()
+            ),
+            LensShape::Circle => {
+                let length = (half.x * dir.x).hypot(half.y * dir.y);
+                if length == 0.0 {
+                    return Vector::new(0.0, 0.0);
+                }
+                Vector::new(
+                    half.x * half.x * dir.x / length,
+                    half.y * half.y * dir.y / length
+                )
+            },
+        }
+    }
+
+    // the point where the line from the source center along @dir leave the
+    // source region, relative to the source center.
+    fn edge_point(&self, dir: &Vector) -> Vector {
+        let half = self.source_half();
+        let scale = match self.get_lens_shape() {
+            LensShape::Rectangle => {
+                let x = if dir.x == 0.0 { ::std::f64::INFINITY }
+                        else { half.x / dir.x.abs() };
+                let y = if dir.y == 0.0 { ::std::f64::INFINITY }
+                        else { half.y / dir.y.abs() };
+                x.min(y)
+            },
+            LensShape::Circle => {
+                if half.x == 0.0 || half.y == 0.0 {
+                    0.0
+                } else {
+                    1.0 / (dir.x / half.x).hypot(dir.y / half.y)
+                }
+            },
+        };
+        dir * scale
+    }
+
+    // add the connector path to @cr, return false if there is nothing to
+    // connect, e.g. the lens cover the source region.
+    fn connector_path(&self, cr: &Context) -> bool {
+        let source = self.source_center();
+        let offset = self.lens_center - source;
+        let distance = na::norm(&offset);
+        if distance == 0.0 {
+            return false;
+        }
+        let dir = offset / distance;
+        let zoom = self.get_zoom();
+
+        match self.get_connector() {
+            Connector::Nothing => false,
+            Connector::Line => {
+                let edge = self.edge_point(&dir);
+                if na::norm(&edge) * (1.0 + zoom) >= distance {
+                    return false;
+                }
+                cr.move_to(&(source + edge));
+                cr.line_to(&(self.lens_center - edge * zoom));
+                true
+            },
+            Connector::Cone => {
+                let normal = Vector::new(-dir.y, dir.x);
+                let side = self.support_point(&normal);
+                let other_side = self.support_point(&-normal);
+                cr.move_to(&(source + side));
+                cr.line_to(&(self.lens_center + side * zoom));
+                cr.line_to(&(self.lens_center + other_side * zoom));
+                cr.line_to(&(source + other_side));
+                cr.close_path();
+                true
+            },
+        }
+    }
+
+    // the context is restored before returning since it's shared by all the
+    // hit tests and measurements of the page.
+    fn select_controller(
+        &self, pos: &Point, cr: &Context
+    ) -> Option<Actions> {
+        cr.new_path();
+
+        if self.is_selected() {
+            cr.save();
+            self.draw_handle(cr, &self.zoom_handle(), false);
+            let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+            cr.restore();
+            if hit {
+                return Some(Actions::Zoom);
+            }
+
+            cr.save();
+            self.draw_handle(cr, &self.source_handle(), false);
+            let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+            cr.restore();
+            if hit {
+                return Some(Actions::ResizeSource);
+            }
+        }
+
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.get_border_width());
+        self.region(cr, &self.lens());
+        let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+        cr.restore();
+        if hit {
+            return Some(Actions::MoveLens(pos.clone() - self.lens_center));
+        }
+
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.get_border_width());
+        self.region(cr, &self.source);
+        let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+        cr.restore();
+        if hit {
+            return Some(Actions::MoveSource(
+                pos.clone() - self.source.mins().clone()
+            ));
+        }
+
+        None
+    }
+
+    // set the source region to the rectangle between @start and @pos, circle
+    // lenses get a square region.
+    fn pick_source(&mut self, start: &Point, pos: &Point, cr: &Context) {
+        let pos = cr.snap(pos);
+        let mut size = pos - start;
+        if self.get_lens_shape() == LensShape::Circle {
+            let side = size.x.abs().max(size.y.abs());
+            size = Vector::new(side * size.x.signum(), side * size.y.signum());
+        }
+        let end = start + size;
+        self.source = Rectangle::new(
+            Point::new(start.x.min(end.x), start.y.min(end.y)),
+            Point::new(start.x.max(end.x), start.y.max(end.y))
+        );
+    }
+
+    // leave the creating mode with the lens placed at the right of the source
+    // region.
+    fn place_lens(&mut self) {
+        let mut size = self.source.maxs() - self.source.mins();
+        if size.x < MIN_SOURCE_SIZE || size.y < MIN_SOURCE_SIZE {
+            // a click without dragging pick a default region
+            size = Vector::new(
+                size.x.max(MIN_SOURCE_SIZE * 20.0),
+                size.y.max(MIN_SOURCE_SIZE * 20.0)
+            );
+            self.source = Rectangle::new(
+                self.source.mins().clone(), self.source.mins() + size
+            );
+        }
+
+        let zoom = self.get_zoom();
+        self.lens_center = self.source_center() +
+            Vector::new(size.x * (1.0 + zoom) / 2.0 + LENS_GAP, 0.0);
+        self.mode = Mode::Editing;
+        self.action = None;
+    }
+
+    fn move_source(&mut self, pos: &Point, cr: &Context) {
+        if let Some(Actions::MoveSource(offset)) = self.action {
+            let size = self.source.maxs() - self.source.mins();
+            let mins = cr.snap_to_grid(&(pos.clone() - offset));
+            self.source = Rectangle::new(mins, mins + size);
+        }
+    }
+
+    fn resize_source(&mut self, pos: &Point, cr: &Context) {
+        let pos = cr.snap(pos);
+        let mins = self.source.mins().clone();
+        let mut size = Vector::new(
+            (pos.x - mins.x).max(MIN_SOURCE_SIZE),
+            (pos.y - mins.y).max(MIN_SOURCE_SIZE)
+        );
+        if self.get_lens_shape() == LensShape::Circle {
+            let side = size.x.max(size.y);
+            size = Vector::new(side, side);
+        }
+        self.source = Rectangle::new(mins, mins + size);
+    }
+
+    fn move_lens(&mut self, pos: &Point, cr: &Context) {
+        if let Some(Actions::MoveLens(offset)) = self.action {
+            self.lens_center = cr.snap_to_grid(&(pos.clone() - offset));
+            if let Some(extents) = self.lens_extents(cr) {
+                self.lens_center += cr.snap_extents(&extents);
+            }
+        }
+    }
+
+    // the lens corner follow the pointer
+    fn zoom_to(&mut self, pos: &Point) {
+        let diagonal = na::norm(&self.source_half());
+        if diagonal == 0.0 {
+            return;
+        }
+        let zoom = na::distance(&self.lens_center, pos) / diagonal;
+        self.set_zoom(zoom);
+    }
+
+    fn lens_extents(&self, cr: &Context) -> Option<Rectangle> {
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.get_border_width());
+        self.region(cr, &self.lens());
+        let result = cr.user_to_device_rect(&cr.stroke_extents());
+        cr.restore();
+        Some(result)
+    }
+
+    fn draw_connector(&self, cr: &Context) {
+        cr.save();
+        cr.new_path();
+
+        // the connector don't cover the source region
+        let (x1, y1, x2, y2) = cr.clip_extents();
+        cr.rectangle(&Rectangle::new(Point::new(x1, y1), Point::new(x2, y2)));
+        self.region(cr, &self.source);
+        cr.set_fill_rule(cairo::FillRule::EvenOdd);
+        cr.clip();
+
+        cr.new_path();
+        if self.connector_path(cr) {
+            let color = self.get_color();
+            cr.set_line_width(self.get_border_width() / 2.0);
+            cr.set_line_join(cairo::LineJoin::Round);
+            if self.get_connector() == Connector::Cone {
+                cr.set_source_rgba(&RgbaColor::new(
+                    color.color.red, color.color.green, color.color.blue,
+                    color.alpha * 0.2
+                ));
+                cr.fill_preserve();
+            }
+            cr.set_source_rgba(&color);
+            cr.stroke();
+        }
+        cr.restore();
+    }
+
+    // draw the page inside the lens, the source region is scaled by the zoom
+    // and moved to the lens center. the lens shows the backdrop and the
+    // shapes below the magnifier, it's transparent where the page is.
+    fn draw_lens(&self, cr: &Context) {
+        let source = self.source_center();
+        let zoom = self.get_zoom();
+
+        cr.save();
+        cr.new_path();
+        self.region(cr, &self.lens());
+        cr.clip();
+        cr.translate(self.lens_center.x, self.lens_center.y);
+        cr.scale(zoom, zoom);
+        cr.translate(-source.x, -source.y);
+        cr.draw_backdrop();
+        cr.draw_shapes_below(self as *const Magnifier as *const () as usize);
+        cr.restore();
+    }
+
+    fn draw_borders(&self, cr: &Context) {
+        if self.get_border_width() <= 0.0 {
+            return;
+        }
+
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.get_border_width());
+        cr.set_source_rgba(&self.get_color());
+        self.region(cr, &self.source);
+        cr.stroke();
+        self.region(cr, &self.lens());
+        cr.stroke();
+        cr.restore();
+    }
+
+    fn draw_handle(&self, cr: &Context, pos: &Point, draw_it: bool) {
+        cr.new_path();
+
+        cr.set_line_width(self.line_width());
+        cr.circle(pos, self.radius());
+
+        if draw_it {
+            cr.set_source_rgb(&self.fill_color());
+            cr.fill_preserve();
+            cr.set_source_rgb(&self.stroke_color());
+            cr.stroke();
+        }
+    }
+
+    fn draw_controllers(&self, cr: &Context) {
+        cr.save();
+        self.draw_handle(cr, &self.source_handle(), true);
+        self.draw_handle(cr, &self.zoom_handle(), true);
+        cr.restore();
+    }
+}
+
+impl Order for Magnifier {}
+impl ShapeTrait for Magnifier {}
+
+impl Draw for Magnifier {
+    fn draw(&self, cr: &Context) {
+        if !self.is_visible() || !cr.is_revealed(self.get_step()) {
+            return;
+        }
+
+        cr.save();
+
+        // only the source region is shown while it's being picked
+        if self.get_mode() == Mode::Creating {
+            if let Some(Actions::PickSource(_)) = self.action {
+                cr.new_path();
+                cr.set_line_width(self.get_border_width());
+                cr.set_source_rgba(&self.get_color());
+                cr.set_dash(&[6.0], 0.0);
+                self.region(cr, &self.source);
+                cr.stroke();
+            }
+            cr.restore();
+            return;
+        }
+
+        self.draw_connector(cr);
+        self.draw_lens(cr);
+        self.draw_borders(cr);
+        if self.is_selected() && cr.get_show_controllers() {
+            self.draw_controllers(cr);
+        }
+
+        // draw children if there are any.
+        for child in self.children.iter() {
+            child.draw(&cr);
+        }
+
+        cr.restore();
+    }
+
+    fn in_draw(&self, pos: &Point, cr: &Context) -> bool {
+        match self.select_controller(pos, cr) {
+            None => return false,
+            _ => return true,
+        };
+    }
+
+    fn draw_extents(&self, cr: &Context) -> Option<Rectangle> {
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.get_border_width().max(1.0));
+        self.region(cr, &self.source);
+        let mut result = cr.user_to_device_rect(&cr.stroke_extents());
+        cr.restore();
+
+        if self.get_mode() == Mode::Creating {
+            return Some(result);
+        }
+
+        if let Some(extents) = self.lens_extents(cr) {
+            result.merge(&extents);
+        }
+
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.get_border_width() / 2.0);
+        if self.connector_path(cr) {
+            result.merge(&cr.user_to_device_rect(&cr.stroke_extents()));
+        }
+        cr.restore();
+
+        for handle in [self.source_handle(), self.zoom_handle()].iter() {
+            cr.save();
+            self.draw_handle(cr, handle, false);
+            result.merge(&cr.user_to_device_rect(&cr.stroke_extents()));
+            cr.restore();
+        }
+
+        Some(result)
+    }
+}
+
+impl Snapshot for Magnifier {
+    fn snapshot(&self) -> ShapeData {
+        ShapeData::Magnifier(MagnifierData {
+            name: self.name(),
+            lock: self.is_locked(),
+            visible: self.is_visible(),
+            step: self.get_step(),
+            source_mins: data::point_to_data(self.source.mins()),
+            source_maxs: data::point_to_data(self.source.maxs()),
+            lens_center: data::point_to_data(&self.lens_center),
+            zoom: self.get_zoom(),
+            lens_shape: self.get_lens_shape(),
+            connector: self.get_connector(),
+            border_color: data::rgba_to_data(&self.get_color()),
+            border_width: self.get_border_width(),
+            children: self.children.iter().map(|c| c.snapshot()).collect(),
+        })
+    }
+}
+
+impl Name for Magnifier {
+    fn name(&self) -> String {
+        self.name.borrow().clone()
+    }
+
+    fn set_name(&mut self, name: &String) {
+        self.name.borrow_mut().clone_from(&name);
+    }
+}
+
+impl Color for Magnifier {
+    fn get_color(&self) -> RgbaColor {
+        self.border_color.borrow().clone()
+    }
+
+    fn set_color(&mut self, color: &RgbaColor) {
+        self.border_color.borrow_mut().clone_from(color);
+    }
+}
+
+// the position is the center of the source region, the lens follow it.
+impl Move for Magnifier {
+    fn position(&self) -> Point {
+        self.source_center()
+    }
+
+    fn move_to(&mut self, pos: &Point) {
+        let offset = pos - self.position();
+        self.translate_by(&Translation::from_vector(offset));
+    }
+
+    fn translate_by(&mut self, trans: &Translation) {
+        self.source = Rectangle::new(
+            trans.transform_point(self.source.mins()),
+            trans.transform_point(self.source.maxs())
+        );
+        self.lens_center = trans.transform_point(&self.lens_center);
+    }
+
+    // the regions stay aligned with the page, only their centers rotate
+    fn rotate_by(&mut self, rotate: &Rotation, origin: &Vector) {
+        let center = self.position() + origin;
+        let trans = Translation::new(-center.x, -center.y);
+        let rotate_point = |point: &Point| {
+            let pos = rotate.transform_point(&trans.transform_point(point));
+            trans.inverse_transform_point(&pos)
+        };
+
+        let source = self.source_center();
+        let offset = rotate_point(&source) - source;
+        self.source = Rectangle::new(
+            self.source.mins() + offset, self.source.maxs() + offset
+        );
+        self.lens_center = rotate_point(&self.lens_center);
+    }
+
+    // the source region follow the page content, the zoom is kept
+    fn scale_by(&mut self, scale: &Vector, origin: &Point) {
+        self.source = Rectangle::new(
+            origin + (self.source.mins() - origin).component_mul(scale),
+            origin + (self.source.maxs() - origin).component_mul(scale)
+        );
+        self.lens_center =
+            origin + (self.lens_center - origin).component_mul(scale);
+    }
+}
+
+impl Select for Magnifier {
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+
+    fn select(&mut self) {
+        self.selected = true;
+    }
+
+    fn unselect(&mut self) {
+        self.selected = false;
+    }
+
+    fn toggle_select(&mut self) -> bool {
+        self.selected = !self.selected;
+        self.selected
+    }
+}
+
+impl Lock for Magnifier {
+    fn is_locked(&self) -> bool {
+        *self.lock.borrow()
+    }
+
+    fn lock(&mut self) {
+        if !self.is_locked() {
+            self.lock.borrow_mut().clone_from(&true);
+        }
+    }
+
+    fn unlock(&mut self) {
+        if self.is_locked() {
+            self.lock.borrow_mut().clone_from(&false);
+        }
+    }
+
+    fn toggle_lock(&mut self) -> bool {
+        self.lock.borrow_mut().clone_from(&!self.is_locked());
+        self.is_locked()
+    }
+}
+
+impl Visible for Magnifier {
+    fn is_visible(&self) -> bool {
+        *self.visible.borrow()
+    }
+
+    fn show(&mut self) {
+        if !self.is_visible() {
+            self.visible.borrow_mut().clone_from(&true);
+        }
+    }
+
+    fn hide(&mut self) {
+        if self.is_visible() {
+            self.visible.borrow_mut().clone_from(&false);
+        }
+    }
+
+    fn toggle_visible(&mut self) -> bool {
+        self.visible.borrow_mut().clone_from(&!self.is_visible());
+        self.is_visible()
+    }
+}
+
+impl Reveal for Magnifier {
+    fn get_step(&self) -> u32 {
+        *self.step.borrow()
+    }
+
+    fn set_step(&mut self, step: u32) {
+        self.step.borrow_mut().clone_from(&step);
+    }
+}
+
+impl Container for Magnifier {
+    fn add(&mut self, child: Box<ShapeTrait>) {
+        self.children.push(child);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Box<ShapeTrait>> {
+        if index >= self.children.len() {
+            None
+        } else {
+            Some(self.children.remove(index))
+        }
+    }
+
+    fn get_children(&self) -> &Vec<Box<ShapeTrait>> {
+        &self.children
+    }
+
+    fn get_mut_children(&mut self) -> &mut Vec<Box<ShapeTrait>> {
+        &mut self.children
+    }
+
+    fn set_children(&mut self, children: Vec<Box<ShapeTrait>>) {
+        self.children = children;
+    }
+}
+
+// flipping and rotating move the lens around the source region
+impl Flip for Magnifier {
+    fn flip_vertical(&mut self) {
+        let source = self.source_center();
+        self.lens_center.y = 2.0 * source.y - self.lens_center.y;
+    }
+
+    fn flip_horizontal(&mut self) {
+        let source = self.source_center();
+        self.lens_center.x = 2.0 * source.x - self.lens_center.x;
+    }
+}
+
+impl Rotate for Magnifier {
+    fn rotate_left(&mut self) {
+        let source = self.source_center();
+        let offset = self.lens_center - source;
+        self.lens_center = source + Vector::new(offset.y, -offset.x);
+    }
+
+    fn rotate_right(&mut self) {
+        let source = self.source_center();
+        let offset = self.lens_center - source;
+        self.lens_center = source + Vector::new(-offset.y, offset.x);
+    }
+}
+
+impl Event for Magnifier {
+    fn motion_notify(
+        &mut self,
+        event: &EventMotion,
+        pos: &Point,
+        cr: &Context
+    ) -> bool {
+        if self.is_locked() || !self.is_visible() {
+            return false;
+        }
+
+        if self.get_mode() == Mode::Creating {
+            if let Some(Actions::PickSource(start)) = self.action {
+                self.pick_source(&start, pos, cr);
+                return true;
+            }
+            return false;
+        }
+
+        if event.get_state() == gdk::BUTTON1_MASK {
+            match self.action {
+                None | Some(Actions::PickSource(..)) => return false,
+                Some(Actions::MoveSource(..)) => {
+                    self.move_source(pos, cr);
+                },
+                Some(Actions::ResizeSource) => {
+                    self.resize_source(pos, cr);
+                },
+                Some(Actions::MoveLens(..)) => {
+                    self.move_lens(pos, cr);
+                },
+                Some(Actions::Zoom) => {
+                    self.zoom_to(pos);
+                },
+            };
+            return true;
+        }
+        false
+    }
+
+    fn button_press(
+        &mut self,
+        event: &EventButton,
+        pos: &Point,
+        cr: &Context,
+        options_widget: &OptionsWidget
+    ) -> bool {
+        if self.is_locked() || !self.is_visible() {
+            return false;
+        }
+
+        if event.get_button() == 1 {
+            if self.get_mode() == Mode::Creating {
+                let start = cr.snap(pos);
+                self.source = Rectangle::new(start, start);
+                self.set_action(Some(Actions::PickSource(start)));
+                self.select();
+                self.connect_ui(options_widget);
+                return true;
+            }
+
+            let action = self.select_controller(pos, cr);
+            self.set_action(action);
+            if let None = self.get_action() {
+                self.unselect();
+                return false;
+            } else {
+                self.select();
+                self.connect_ui(options_widget);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn button_release(
+        &mut self,
+        event: &EventButton,
+        _pos: &Point,
+        _cr: &Context
+    ) -> bool {
+        if event.get_button() != 1 || self.get_mode() != Mode::Creating {
+            return false;
+        }
+
+        if let Some(Actions::PickSource(_)) = self.action {
+            self.place_lens();
+            return true;
+        }
+        false
+    }
+}
+
+impl super::Mode for Magnifier {
+    fn in_creating_mode(&self) -> bool {
+        self.get_mode() == Mode::Creating
+    }
+
+    fn in_editing_mode(&self) -> bool {
+        self.get_mode() == Mode::Editing
+    }
+}
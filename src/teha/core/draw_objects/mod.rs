@@ -20,17 +20,59 @@
 pub mod page;
 pub mod layer;
 pub mod line_arrow;
+pub mod image;
+pub mod background;
+pub mod magnifier;
+pub mod spotlight;
 
 pub use self::page::Page;
 pub use self::layer::Layer;
 pub use self::line_arrow::LineArrow;
+pub use self::image::Image;
+pub use self::background::Background;
+pub use self::magnifier::Magnifier;
+pub use self::spotlight::Spotlight;
+
+use std::rc::Rc;
+use std::ops::Deref;
 
 use gdk::{EventMotion, EventButton, EventKey};
 use gtk::{self};
 
 use core::context::Context;
+use core::data::ShapeData;
 use common::types::*;
 
+// the notebook that hold the options of the selected shape. shapes changed
+// from their options don't know the document they belong to, so they call
+// changed() and the owner of the notebook take care of the rest.
+#[derive(Clone)]
+pub struct OptionsWidget {
+    notebook: gtk::Notebook,
+    on_change: Rc<Fn()>,
+}
+
+impl OptionsWidget {
+    pub fn new<F: Fn() + 'static>(notebook: gtk::Notebook, on_change: F) -> Self {
+        OptionsWidget {
+            notebook: notebook,
+            on_change: Rc::new(on_change),
+        }
+    }
+
+    pub fn changed(&self) {
+        (self.on_change)();
+    }
+}
+
+impl Deref for OptionsWidget {
+    type Target = gtk::Notebook;
+
+    fn deref(&self) -> &gtk::Notebook {
+        &self.notebook
+    }
+}
+
 pub trait Draw {
     fn draw(&self, cr: &Context);
     fn in_draw(&self, pos: &Point, cr: &Context) -> bool;
@@ -199,6 +241,8 @@ pub trait Move {
     fn move_to(&mut self, &Point);
     fn translate_by(&mut self, &Translation);
     fn rotate_by(&mut self, &Rotation, &Vector);
+    // stretch the shape away from @origin by the x and y of @scale
+    fn scale_by(&mut self, &Vector, &Point);
 }
 
 pub trait Select {
@@ -222,6 +266,13 @@ pub trait Visible {
     fn toggle_visible(&mut self) -> bool;
 }
 
+// the step in which a shape is revealed when the page is played step by step,
+// shapes with step 0 are shown from the start.
+pub trait Reveal {
+    fn get_step(&self) -> u32;
+    fn set_step(&mut self, step: u32);
+}
+
 pub trait Container {
     fn add(&mut self, child: Box<ShapeTrait>);
     fn remove(&mut self, index: usize) -> Option<Box<ShapeTrait>>;
@@ -246,7 +297,7 @@ pub trait Event {
         event: &EventButton,
         pos: &Point,
         cr: &Context,
-        options_widget: &gtk::Notebook
+        options_widget: &OptionsWidget
     ) -> bool { false }
     #[allow(unused_variables)]
     fn button_release(
@@ -274,6 +325,12 @@ pub trait Mode {
     fn in_editing_mode(&self) -> bool;
 }
 
+pub trait Snapshot {
+    // return a serializable copy of this shape and its children.
+    fn snapshot(&self) -> ShapeData;
+}
+
 pub trait ShapeTrait: Draw + Name + Color + Move + Select + Lock + Visible +
-                      Container + Event + Mode + Order + Flip + Rotate {}
+                      Reveal + Container + Event + Mode + Order + Flip +
+                      Rotate + Snapshot {}
 
@@ -17,28 +17,90 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 //
 
+use std::cell::RefCell;
+
 use cairo;
-use gdk::{EventMotion, EventButton, EventKey};
-use gtk::{self};
+use gdk::{self, EventMotion, EventButton, EventKey};
+use gtk::NotebookExtManual;
 
 use gettextrs::*;
 
 use ncollide::bounding_volume::BoundingVolume;
 
-use core::context::Context;
+use core::context::{Context, RenderOptions};
+use core::data::{self, ShapeData, PageData};
+use core::snap::{SnapTargets, Guide};
+use core::notes::{self, Span};
+use error::Error;
 use common::types::*;
-use super::{Name, Layer};
+use common::image::Picture;
+use super::{Name, Layer, ShapeTrait, Container, Move, Select, Visible, Mode,
+            Reveal, Background, OptionsWidget};
 use super::layer::LayerTrait;
 
+pub const GRID_SPACING: f64 = 50.0;
+pub const GRID_SUBDIVISIONS: u32 = 5;
+// grid lines closer than this (in pixels) to each other are not drawn
+const GRID_MIN_GAP: f64 = 6.0;
+// how close (in pixels) a dragged shape should be to other shapes to snap
+const SNAP_TOLERANCE: f64 = 8.0;
+// how close (in pixels) the pointer should be to a guide line to pick it
+const GUIDE_TOLERANCE: f64 = 4.0;
+// the opacity of the replaced background image shown over the new one
+const PREVIOUS_BACKGROUND_ALPHA: f64 = 0.4;
+// the caption strip drawn below the exported page
+const CAPTION_FONT_SIZE: f64 = 16.0;
+const CAPTION_LINE_HEIGHT: f64 = 22.0;
+const CAPTION_PADDING: f64 = 12.0;
 
 pub struct Page {
     size: Size<i32>,
     layers: Vec<Box<LayerTrait>>,
     active_layer_index: usize,
     color: Option<RgbColor>,
+    // an image drawn above the color and below all the layers
+    background: Option<Background>,
+    // the distance from the pointer to the background position while the
+    // user is dragging the background
+    background_drag: Option<Vector>,
+    // the image replaced by the last replace_background(), it's shown faded
+    // in the editor to help realigning the shapes and it's never saved.
+    previous_background: Option<Background>,
     border: Option<RgbColor>,
     grid: Option<RgbColor>,
+    grid_spacing: f64,
+    grid_subdivisions: u32,
+    snap_to_grid: bool,
+    // the guides shown while dragging a shape aligned with other shapes
+    smart_guides: Vec<Guide>,
+    // the corners (in page coordinates) of the rubber band while the user
+    // is dragging it to select shapes
+    rubber_band: Option<(Point, Point)>,
+    // while true dragging with the first button draw the crop rectangle
+    // instead of moving the shapes
+    cropping: bool,
+    // the corners (in page coordinates) of the crop rectangle being dragged
+    crop_band: Option<(Point, Point)>,
+    // guide lines placed by the user, they are only shown in the editor
+    guides: Vec<Guide>,
+    // the areas (in the drawing area coordinates) that changed by the
+    // events and need to be redrawn
+    damage: Vec<Rectangle>,
+    // what changing_extents() returned after the last event, the options of
+    // the selected shapes can change them between the events.
+    last_extents: Vec<Rectangle>,
+    // set by the events that changed the shapes or the page, not the ones
+    // that only selected something.
+    changed: bool,
+    // a context translated and scaled like the page, used to hit-test and
+    // measure the shapes. it's dropped when the zoom level or the translate
+    // change.
+    measure_cr: RefCell<Option<cairo::Context>>,
     name: String,
+    // text that explain the page, used as its caption by the exports
+    notes: String,
+    // draw the notes in a strip below the page when it's exported
+    show_caption: bool,
     translate: Vector,
     zoom_level: f64,
 }
@@ -50,9 +112,26 @@ impl Page {
             layers: vec![Box::new(Layer::new())],
             active_layer_index: 0,
             color: Some(RgbColor::new(1.0, 1.0, 1.0)),
+            background: None,
+            background_drag: None,
+            previous_background: None,
             border: Some(RgbColor::new(0.47, 0.47, 0.47)), // #797979
             grid: None,
+            grid_spacing: GRID_SPACING,
+            grid_subdivisions: GRID_SUBDIVISIONS,
+            snap_to_grid: false,
+            smart_guides: vec![],
+            rubber_band: None,
+            cropping: false,
+            crop_band: None,
+            guides: vec![],
+            damage: vec![],
+            last_extents: vec![],
+            changed: false,
+            measure_cr: RefCell::new(None),
             name: gettext("Unnamed Page"),
+            notes: String::new(),
+            show_caption: false,
             translate: Vector::new(0.0, 0.0),
             zoom_level: 1.0,
         }
@@ -98,6 +177,73 @@ impl Page {
         self.active_layer_index = index;
     }
 
+    // add a new layer above the active layer and make it the active one.
+    pub fn add_layer(&mut self) -> usize {
+        let index = if self.layers.is_empty() {
+            0
+        } else {
+            self.active_layer_index + 1
+        };
+        self.layers.insert(index, Box::new(Layer::new()));
+        self.active_layer_index = index;
+        index
+    }
+
+    // remove the layer at @index, the page always keep one layer at least.
+    pub fn remove_layer(&mut self, index: usize) -> bool {
+        if self.layers.len() < 2 || index >= self.layers.len() {
+            return false;
+        }
+
+        self.layers.remove(index);
+        if self.active_layer_index >= self.layers.len() ||
+           self.active_layer_index > index {
+            self.active_layer_index -= 1;
+        }
+        true
+    }
+
+    // move the layer at @index one step up (toward the top of the page).
+    pub fn move_layer_up(&mut self, index: usize) -> bool {
+        if index + 1 >= self.layers.len() {
+            return false;
+        }
+
+        self.layers.swap(index, index + 1);
+        if self.active_layer_index == index {
+            self.active_layer_index = index + 1;
+        } else if self.active_layer_index == index + 1 {
+            self.active_layer_index = index;
+        }
+        true
+    }
+
+    // move the layer at @index one step down (toward the page background).
+    pub fn move_layer_down(&mut self, index: usize) -> bool {
+        if index == 0 || index >= self.layers.len() {
+            return false;
+        }
+
+        self.move_layer_up(index - 1)
+    }
+
+    // move the selected shapes from all layers to the layer at @index
+    pub fn move_selected_shapes_to_layer(&mut self, index: usize) -> bool {
+        if index >= self.layers.len() {
+            return false;
+        }
+
+        let mut shapes = vec![];
+        for layer in self.layers.iter_mut() {
+            shapes.append(&mut layer.take_selected_children());
+        }
+
+        for shape in shapes.into_iter() {
+            self.layers[index].add(shape);
+        }
+        true
+    }
+
     pub fn remove_shapes_in_creating_mode(&mut self) {
         for layer in self.layers.iter_mut() {
             layer.remove_shapes_in_creating_mode();
@@ -110,6 +256,132 @@ impl Page {
         }
     }
 
+    // return a copy of the selected shapes from all layers, ordered from
+    // the bottom layer to the top one.
+    pub fn snapshot_selected_shapes(&self) -> Vec<ShapeData> {
+        let mut shapes = vec![];
+        for layer in self.layers.iter() {
+            shapes.append(&mut layer.snapshot_selected_children());
+        }
+        shapes
+    }
+
+    pub fn remove_selected_shapes(&mut self) {
+        for layer in self.layers.iter_mut() {
+            layer.remove_selected_children();
+        }
+        self.changed = true;
+    }
+
+    pub fn add_shape(&mut self, shape: Box<ShapeTrait>) {
+        self.get_mut_active_layer().add(shape);
+        self.changed = true;
+    }
+
+    // add the given shapes to the active layer, translated by @offset, and
+    // make them the only selected shapes in this page.
+    pub fn paste_shapes(
+        &mut self, shapes: Vec<ShapeData>, offset: &Vector
+    ) -> Result<(), Error> {
+        let shapes = data::shapes_from_data(shapes)?;
+        self.remove_shapes_in_creating_mode();
+        self.unselect_all_shapes();
+
+        let translation = Translation::from_vector(offset.clone());
+        for mut shape in shapes.into_iter() {
+            shape.translate_by(&translation);
+            shape.select();
+            self.add_shape(shape);
+        }
+        Ok(())
+    }
+
+    // return a copy of this page with copies of all its layers and shapes.
+    pub fn duplicate(&self) -> Result<Page, Error> {
+        Page::from_data(self.to_data())
+    }
+
+    pub fn from_data(data: PageData) -> Result<Self, Error> {
+        let mut layers: Vec<Box<LayerTrait>> = vec![];
+        for layer in data.layers.into_iter() {
+            layers.push(Box::new(Layer::from_data(layer)?));
+        }
+        if layers.is_empty() {
+            layers.push(Box::new(Layer::new()));
+        }
+
+        let active_layer_index = if data.active_layer_index < layers.len() {
+            data.active_layer_index
+        } else {
+            layers.len() - 1
+        };
+
+        let background = match data.background {
+            Some(val) => Some(Background::from_data(val)?),
+            None => None,
+        };
+
+        Ok(Page {
+            size: Size::new(data.size[0], data.size[1]),
+            layers: layers,
+            active_layer_index: active_layer_index,
+            color: data.color.as_ref().map(data::rgb_from_data),
+            background: background,
+            background_drag: None,
+            previous_background: None,
+            border: data.border.as_ref().map(data::rgb_from_data),
+            grid: data.grid.as_ref().map(data::rgb_from_data),
+            grid_spacing: data.grid_spacing,
+            grid_subdivisions: data.grid_subdivisions,
+            snap_to_grid: data.snap_to_grid,
+            smart_guides: vec![],
+            rubber_band: None,
+            cropping: false,
+            crop_band: None,
+            guides: data.guides.iter().map(data::guide_from_data).collect(),
+            damage: vec![],
+            last_extents: vec![],
+            changed: false,
+            measure_cr: RefCell::new(None),
+            name: data.name,
+            notes: data.notes,
+            show_caption: data.show_caption,
+            translate: Vector::new(0.0, 0.0),
+            zoom_level: data.zoom_level,
+        })
+    }
+
+    pub fn to_data(&self) -> PageData {
+        PageData {
+            name: self.name.clone(),
+            notes: self.notes.clone(),
+            show_caption: self.show_caption,
+            size: [self.size.width, self.size.height],
+            color: self.color.as_ref().map(data::rgb_to_data),
+            border: self.border.as_ref().map(data::rgb_to_data),
+            grid: self.grid.as_ref().map(data::rgb_to_data),
+            grid_spacing: self.grid_spacing,
+            grid_subdivisions: self.grid_subdivisions,
+            snap_to_grid: self.snap_to_grid,
+            guides: self.guides.iter().map(data::guide_to_data).collect(),
+            background: self.background.as_ref().map(|val| val.to_data()),
+            zoom_level: self.zoom_level,
+            active_layer_index: self.active_layer_index,
+            layers: self.layers.iter().map(|layer| layer.to_data()).collect(),
+        }
+    }
+
+    // the last reveal step used by the visible shapes, 0 if all the shapes
+    // are shown from the start.
+    pub fn last_step(&self) -> u32 {
+        self.layers
+            .iter()
+            .filter(|layer| layer.is_visible())
+            .map(|layer| last_step_of(layer.get_children()))
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn get_color(&self) -> &Option<RgbColor> {
         &self.color
     }
@@ -122,6 +394,173 @@ impl Page {
         self.color = color;
     }
 
+    pub fn get_background(&self) -> &Option<Background> {
+        &self.background
+    }
+
+    pub fn get_mut_background(&mut self) -> &mut Option<Background> {
+        &mut self.background
+    }
+
+    pub fn set_background(&mut self, background: Option<Background>) {
+        self.background_drag = None;
+        self.previous_background = None;
+        self.background = background;
+    }
+
+    // swap the background image keeping the position and scale of the old
+    // one. the shapes stay where they are, or when @rescale is true they
+    // move and stretch with the image as if the old image was resized to
+    // the new one. a page that fit the old image is resized to fit the new
+    // one too. the old image is kept as a faded overlay until
+    // clear_previous_background() is called.
+    pub fn replace_background(&mut self, picture: Picture, rescale: bool) {
+        let mut background = match self.background.take() {
+            Some(val) => val,
+            None => {
+                self.set_background(Some(Background::new(picture)));
+                return;
+            },
+        };
+
+        let old_bounds = background.bounds();
+        let fitted = *old_bounds.mins() == Point::new(0.0, 0.0) &&
+            self.size == Size::new(
+                old_bounds.maxs().x.round() as i32,
+                old_bounds.maxs().y.round() as i32
+            );
+
+        let mut previous = Background::new(background.get_picture().clone());
+        previous.set_position(*background.get_position());
+        previous.set_scale(background.get_scale());
+
+        let old_size = background.size();
+        background.set_picture(picture);
+        let new_size = background.size();
+
+        if rescale && old_size.x > 0.0 && old_size.y > 0.0 {
+            let scale = Vector::new(
+                new_size.x / old_size.x, new_size.y / old_size.y
+            );
+            for layer in self.layers.iter_mut() {
+                for child in layer.get_mut_children().iter_mut() {
+                    child.scale_by(&scale, old_bounds.mins());
+                }
+                layer.invalidate_index();
+            }
+        }
+
+        self.background_drag = None;
+        self.previous_background = Some(previous);
+        self.background = Some(background);
+        if fitted {
+            self.fit_to_background();
+        }
+    }
+
+    pub fn get_previous_background(&self) -> &Option<Background> {
+        &self.previous_background
+    }
+
+    pub fn clear_previous_background(&mut self) {
+        self.previous_background = None;
+    }
+
+    // resize the page to the size of the background image and move the
+    // image to the top left corner of the page.
+    pub fn fit_to_background(&mut self) -> bool {
+        let size = match self.background {
+            Some(ref mut background) => {
+                background.set_position(Point::new(0.0, 0.0));
+                background.size()
+            },
+            None => return false,
+        };
+        if size.x < 1.0 || size.y < 1.0 {
+            return false;
+        }
+
+        self.size = Size::new(size.x.round() as i32, size.y.round() as i32);
+        true
+    }
+
+    pub fn is_cropping(&self) -> bool {
+        self.cropping
+    }
+
+    pub fn set_cropping(&mut self, cropping: bool) {
+        self.cropping = cropping;
+        self.crop_band = None;
+    }
+
+    // crop the page to @rect (in the page coordinates), the shapes, the
+    // background and the guides move with the page content.
+    pub fn crop(&mut self, rect: &Rectangle) -> bool {
+        let mins = Point::new(rect.mins().x.round(), rect.mins().y.round());
+        let width = (rect.maxs().x - mins.x).round() as i32;
+        let height = (rect.maxs().y - mins.y).round() as i32;
+        if width < 1 || height < 1 {
+            return false;
+        }
+
+        let offset = Vector::new(-mins.x, -mins.y);
+        let translation = Translation::from_vector(offset);
+        for layer in self.layers.iter_mut() {
+            for child in layer.get_mut_children().iter_mut() {
+                child.translate_by(&translation);
+            }
+            layer.invalidate_index();
+        }
+
+        for background in self.background
+                              .iter_mut()
+                              .chain(self.previous_background.iter_mut()) {
+            let position = *background.get_position() + offset;
+            background.set_position(position);
+        }
+
+        for guide in self.guides.iter_mut() {
+            *guide = match *guide {
+                Guide::Vertical(x) => Guide::Vertical(x + offset.x),
+                Guide::Horizontal(y) => Guide::Horizontal(y + offset.y),
+            };
+        }
+
+        self.size = Size::new(width, height);
+        true
+    }
+
+    // the extents of the shapes in the visible layers in the page
+    // coordinates, measured like draw_extents() but without the page itself.
+    pub fn content_extents(&self) -> Option<Rectangle> {
+        let cr = self.measure_cr();
+        // without the page bounds, shapes covering the page (e.g. spotlights)
+        // measure only their own parts.
+        let cr = Context::from_transformed(&cr);
+        self.layers
+            .iter()
+            .filter(|layer| layer.is_visible())
+            .filter_map(|layer| layer.draw_extents(&cr))
+            .map(|extents| cr.device_to_user_rect(&extents))
+            .fold(None, |acc: Option<Rectangle>, extents| match acc {
+                Some(val) => Some(val.merged(&extents)),
+                None => Some(extents),
+            })
+    }
+
+    // crop the page tightly around its shapes leaving @margin around them
+    pub fn trim_to_content(&mut self, margin: f64) -> bool {
+        let extents = match self.content_extents() {
+            Some(val) => val,
+            None => return false,
+        };
+
+        let margin = Vector::new(margin, margin);
+        self.crop(&Rectangle::new(
+            *extents.mins() - margin, *extents.maxs() + margin
+        ))
+    }
+
     pub fn get_border(&self) -> &Option<RgbColor> {
         &self.border
     }
@@ -142,6 +581,92 @@ impl Page {
         self.grid = grid;
     }
 
+    // the distance between the main grid lines
+    pub fn get_grid_spacing(&self) -> f64 {
+        self.grid_spacing
+    }
+
+    pub fn set_grid_spacing(&mut self, spacing: f64) {
+        self.grid_spacing = spacing;
+    }
+
+    // the number of cells between two main grid lines
+    pub fn get_grid_subdivisions(&self) -> u32 {
+        self.grid_subdivisions
+    }
+
+    pub fn set_grid_subdivisions(&mut self, subdivisions: u32) {
+        self.grid_subdivisions = subdivisions;
+    }
+
+    pub fn get_snap_to_grid(&self) -> bool {
+        self.snap_to_grid
+    }
+
+    pub fn set_snap_to_grid(&mut self, snap_to_grid: bool) {
+        self.snap_to_grid = snap_to_grid;
+    }
+
+    pub fn get_guides(&self) -> &Vec<Guide> {
+        &self.guides
+    }
+
+    pub fn add_guide(&mut self, guide: Guide) -> usize {
+        self.guides.push(guide);
+        self.guides.len() - 1
+    }
+
+    pub fn set_guide(&mut self, index: usize, guide: Guide) {
+        if let Some(val) = self.guides.get_mut(index) {
+            *val = guide;
+        }
+    }
+
+    pub fn remove_guide(&mut self, index: usize) -> Option<Guide> {
+        if index < self.guides.len() {
+            Some(self.guides.remove(index))
+        } else {
+            None
+        }
+    }
+
+    // return the index of the guide under @pos, @pos is in the drawing area
+    // coordinates.
+    pub fn guide_at(&self, pos: &Point) -> Option<usize> {
+        let pos = self.device_to_page(pos);
+        let tolerance = GUIDE_TOLERANCE / self.zoom_level;
+        self.guides.iter().position(|guide| {
+            match *guide {
+                Guide::Vertical(x) => (x - pos.x).abs() <= tolerance,
+                Guide::Horizontal(y) => (y - pos.y).abs() <= tolerance,
+            }
+        })
+    }
+
+    // convert @pos from the drawing area coordinates to the page coordinates
+    pub fn device_to_page(&self, pos: &Point) -> Point {
+        Point::new(
+            (pos.x - self.translate.x) / self.zoom_level,
+            (pos.y - self.translate.y) / self.zoom_level
+        )
+    }
+
+    // convert @pos from the page coordinates to the drawing area coordinates
+    pub fn page_to_device(&self, pos: &Point) -> Point {
+        Point::new(
+            pos.x * self.zoom_level + self.translate.x,
+            pos.y * self.zoom_level + self.translate.y
+        )
+    }
+
+    // the distance between the grid lines that shapes snap to
+    fn snap_step(&self) -> Option<f64> {
+        if !self.snap_to_grid || self.grid_spacing <= 0.0 {
+            return None;
+        }
+        Some(self.grid_spacing / self.grid_subdivisions.max(1) as f64)
+    }
+
     pub fn get_name(&self) -> &String {
         &self.name
     }
@@ -154,16 +679,125 @@ impl Page {
         self.name = name;
     }
 
-    pub fn get_translate(&self) -> &Vector {
-        &self.translate
+    pub fn get_notes(&self) -> &String {
+        &self.notes
+    }
+
+    pub fn set_notes(&mut self, notes: String) {
+        self.notes = notes;
+    }
+
+    pub fn get_show_caption(&self) -> bool {
+        self.show_caption
+    }
+
+    pub fn set_show_caption(&mut self, show_caption: bool) {
+        self.show_caption = show_caption;
+    }
+
+    // split the notes into the lines of the caption strip, so they fit the
+    // page width. a word wider than the page is broken between its letters
+    fn caption_lines(&self) -> Vec<Vec<Span>> {
+        if !self.show_caption || self.notes.trim().is_empty() {
+            return vec![];
+        }
+
+        let cr = self.measure_cr();
+        cr.save();
+        let max_width = self.size.width as f64 - 2.0 * CAPTION_PADDING;
+        set_caption_font(&cr, false, false);
+        let space_width = cr.text_extents(" ").x_advance;
+
+        let mut lines = vec![];
+        for paragraph in self.notes.trim().lines() {
+            let mut line: Vec<Span> = vec![];
+            let mut line_width = 0.0;
+            for word in caption_words(&notes::parse(paragraph)) {
+                let word_width = spans_width(&cr, &word);
+                if !line.is_empty() {
+                    if line_width + space_width + word_width <= max_width {
+                        line.push(Span::new(" ", false, false));
+                        line.extend(word);
+                        line_width += space_width + word_width;
+                        continue;
+                    }
+                    lines.push(line);
+                }
+
+                if word_width <= max_width {
+                    line = word;
+                    line_width = word_width;
+                } else {
+                    let mut pieces = break_word(&cr, word, max_width);
+                    line = pieces.pop().unwrap_or(vec![]);
+                    line_width = spans_width(&cr, &line);
+                    lines.extend(pieces);
+                }
+            }
+            lines.push(line);
+        }
+        cr.restore();
+        lines
+    }
+
+    fn caption_height(&self, lines: &Vec<Vec<Span>>) -> f64 {
+        if lines.is_empty() {
+            0.0
+        } else {
+            lines.len() as f64 * CAPTION_LINE_HEIGHT + 2.0 * CAPTION_PADDING
+        }
+    }
+
+    // the size of the exported page, it include the caption strip
+    pub fn render_size(&self) -> Size<i32> {
+        let lines = self.caption_lines();
+        Size::new(
+            self.size.width,
+            self.size.height + self.caption_height(&lines).ceil() as i32
+        )
+    }
+
+    fn draw_caption(&self, cr: &Context) {
+        let lines = self.caption_lines();
+        if lines.is_empty() {
+            return;
+        }
+
+        let top = self.size.height as f64;
+        cr.save();
+        cr.rectangle(&Rectangle::new(
+            Point::new(0.0, top),
+            Point::new(self.size.width as f64, top + self.caption_height(&lines))
+        ));
+        cr.set_source_rgb(&RgbColor::new(0.96, 0.96, 0.96)); // #F6F5F4
+        cr.fill();
+
+        cr.set_source_rgb(&RgbColor::new(0.18, 0.2, 0.21)); // #2E3436
+        for (index, line) in lines.iter().enumerate() {
+            cr.move_to(&Point::new(
+                CAPTION_PADDING,
+                top + CAPTION_PADDING + CAPTION_FONT_SIZE +
+                    index as f64 * CAPTION_LINE_HEIGHT
+            ));
+            // show_text() moves the current point to the end of the text, so
+            // the spans follow each other
+            for span in line.iter() {
+                set_caption_font(cr, span.bold, span.italic);
+                cr.show_text(span.text.as_str());
+            }
+        }
+        cr.restore();
     }
 
-    pub fn get_mut_translate(&mut self) -> &mut Vector {
-        &mut self.translate
+    pub fn get_translate(&self) -> &Vector {
+        &self.translate
     }
 
     pub fn set_translate(&mut self, translate: Vector) {
-        self.translate = translate;
+        if translate != self.translate {
+            self.translate = translate;
+            self.measure_cr.borrow_mut().take();
+        }
     }
 
     pub fn get_zoom_level(&self) -> f64 {
@@ -171,7 +805,55 @@ impl Page {
     }
 
     pub fn set_zoom_level(&mut self, zoom_level: f64) {
-        self.zoom_level = zoom_level;
+        if zoom_level != self.zoom_level {
+            self.zoom_level = zoom_level;
+            self.measure_cr.borrow_mut().take();
+            for layer in self.layers.iter() {
+                layer.invalidate_index();
+            }
+        }
+    }
+
+    // return the cached measuring context, it's created on the first use
+    // after the zoom level or the translate change. it's shared by all the
+    // hit tests and measurements, so every save() on it must be restored
+    // before returning, even on the early returns of hit tests.
+    fn measure_cr(&self) -> cairo::Context {
+        if let Some(ref cr) = *self.measure_cr.borrow() {
+            cr.new_path();
+            return cr.clone();
+        }
+
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
+            .expect("Cairo: couldn't create surface");
+        let cr = cairo::Context::new(&surface);
+        cr.translate(self.translate.x, self.translate.y);
+        cr.scale(self.zoom_level, self.zoom_level);
+        *self.measure_cr.borrow_mut() = Some(cr.clone());
+        cr
+    }
+
+    // wrap the measuring context @cr, shapes that cover the whole page (e.g.
+    // spotlights) measure themselves with the page bounds.
+    fn measure_context<'a>(&self, cr: &'a cairo::Context) -> Context<'a> {
+        let mut context = Context::from_transformed(cr);
+        context.set_render_options(RenderOptions {
+            page_bounds: Some(self.page_bound()),
+            .. RenderOptions::default()
+        });
+        context
+    }
+
+    // the options the page is drawn with, the page color is left out if
+    // @transparent.
+    fn render_options(&self, transparent: bool) -> RenderOptions {
+        RenderOptions {
+            page_color: if transparent { None } else { self.color },
+            background: self.background.as_ref(),
+            layers: Some(&self.layers),
+            page_bounds: Some(self.page_bound()),
+            .. RenderOptions::default()
+        }
     }
 
     fn page_bound(&self) -> Rectangle {
@@ -185,12 +867,279 @@ impl Page {
         5.0
     }
 
+    // draw the grid lines inside the page, subdivision lines are drawn
+    // lighter than the main lines and they are skipped when the zoom level
+    // make them too close to each other.
+    fn draw_grid(&self, cr: &Context) {
+        let color = match self.grid {
+            Some(val) => val,
+            None => return,
+        };
+        if self.grid_spacing * self.zoom_level < GRID_MIN_GAP {
+            return;
+        }
+
+        let subdivisions = self.grid_subdivisions.max(1);
+        let mut step = self.grid_spacing / subdivisions as f64;
+        let mut lines_per_main = subdivisions;
+        if step * self.zoom_level < GRID_MIN_GAP {
+            step = self.grid_spacing;
+            lines_per_main = 1;
+        }
+
+        let width = self.size.width as f64;
+        let height = self.size.height as f64;
+
+        cr.save();
+        // one pixel wide lines at any zoom level
+        cr.set_line_width(1.0 / self.zoom_level);
+        let draw_lines = |main: bool| {
+            cr.new_path();
+            let n_columns = (width / step).floor() as u32;
+            for index in 0..n_columns + 1 {
+                if (index % lines_per_main == 0) == main {
+                    let x = step * index as f64;
+                    cr.move_to(&Point::new(x, 0.0));
+                    cr.line_to(&Point::new(x, height));
+                }
+            }
+
+            let n_rows = (height / step).floor() as u32;
+            for index in 0..n_rows + 1 {
+                if (index % lines_per_main == 0) == main {
+                    let y = step * index as f64;
+                    cr.move_to(&Point::new(0.0, y));
+                    cr.line_to(&Point::new(width, y));
+                }
+            }
+
+            let alpha = if main { 1.0 } else { 0.4 };
+            cr.set_source_rgba(
+                &RgbaColor::new(color.red, color.green, color.blue, alpha)
+            );
+            cr.stroke();
+        };
+        draw_lines(false);
+        draw_lines(true);
+        cr.restore();
+    }
+
+    // collect the edges and centers of the shapes that are not selected, the
+    // page center and the guide lines, dragged shapes snap to them.
+    fn snap_targets(&self, cr: &Context) -> SnapTargets {
+        let mut targets = SnapTargets::new(SNAP_TOLERANCE / self.zoom_level);
+        targets.add_x(self.size.width as f64 / 2.0);
+        targets.add_y(self.size.height as f64 / 2.0);
+        for guide in self.guides.iter() {
+            match *guide {
+                Guide::Vertical(x) => targets.add_x(x),
+                Guide::Horizontal(y) => targets.add_y(y),
+            }
+        }
+
+        for layer in self.layers.iter().filter(|layer| layer.is_visible()) {
+            for extents in layer.idle_extents(cr) {
+                targets.add_rectangle(&extents);
+            }
+        }
+        targets
+    }
+
+    // return the extents of the selected shapes in the page coordinates
+    pub fn selected_extents(&self) -> Option<Rectangle> {
+        let cr = self.measure_cr();
+        let cr = self.measure_context(&cr);
+
+        let mut result: Option<Rectangle> = None;
+        for layer in self.layers.iter().filter(|layer| layer.is_visible()) {
+            for child in layer.get_children().iter() {
+                if !child.is_selected() || !child.is_visible() {
+                    continue;
+                }
+                if let Some(extents) = child.draw_extents(&cr) {
+                    let extents = cr.device_to_user_rect(&extents);
+                    result = match result {
+                        Some(val) => Some(val.merged(&extents)),
+                        None => Some(extents),
+                    };
+                }
+            }
+        }
+        result
+    }
+
+    // return the extents (in the drawing area coordinates) of what the
+    // events can change: the selected shapes, the shapes being created and
+    // the smart guides.
+    fn changing_extents(&self, cr: &Context) -> Vec<Rectangle> {
+        let mut result = vec![];
+        for layer in self.layers.iter() {
+            for child in layer.get_children().iter() {
+                if !child.is_selected() && !child.in_creating_mode() {
+                    continue;
+                }
+                if let Some(extents) = child.draw_extents(cr) {
+                    result.push(extents);
+                }
+            }
+        }
+
+        let width = self.size.width as f64;
+        let height = self.size.height as f64;
+        for guide in self.smart_guides.iter() {
+            let line = match *guide {
+                Guide::Vertical(x) => Rectangle::new(
+                    Point::new(x, 0.0), Point::new(x, height)
+                ),
+                Guide::Horizontal(y) => Rectangle::new(
+                    Point::new(0.0, y), Point::new(width, y)
+                ),
+            };
+            result.push(cr.user_to_device_rect(&line));
+        }
+
+        if let Some(band) = self.rubber_band_rect() {
+            result.push(cr.user_to_device_rect(&band));
+        }
+
+        // the area outside the crop rectangle is dimmed
+        if self.crop_band.is_some() {
+            result.push(cr.user_to_device_rect(&self.page_bound()));
+        }
+
+        // magnifiers anywhere on the page show the dragged background
+        if let (&Some(ref background), Some(_)) =
+            (&self.background, self.background_drag) {
+            result.push(cr.user_to_device_rect(&background.bounds()));
+            result.push(cr.user_to_device_rect(&self.page_bound()));
+        }
+        result
+    }
+
+    fn rubber_band_rect(&self) -> Option<Rectangle> {
+        self.rubber_band.map(|(a, b)| Rectangle::new(
+            Point::new(a.x.min(b.x), a.y.min(b.y)),
+            Point::new(a.x.max(b.x), a.y.max(b.y))
+        ))
+    }
+
+    fn crop_band_rect(&self) -> Option<Rectangle> {
+        self.crop_band.map(|(a, b)| Rectangle::new(
+            Point::new(a.x.min(b.x), a.y.min(b.y)),
+            Point::new(a.x.max(b.x), a.y.max(b.y))
+        ))
+    }
+
+    // dim the page outside the crop rectangle
+    fn draw_crop_band(&self, cr: &Context) {
+        let band = match self.crop_band_rect() {
+            Some(val) => val,
+            None => return,
+        };
+
+        cr.save();
+        cr.new_path();
+        cr.rectangle(&self.page_bound());
+        cr.rectangle(&band);
+        cr.set_fill_rule(cairo::FillRule::EvenOdd);
+        cr.set_source_rgba(&RgbaColor::new(0.0, 0.0, 0.0, 0.5));
+        cr.fill();
+        cr.rectangle(&band);
+        cr.set_source_rgb(&RgbColor::new(1.0, 1.0, 1.0));
+        cr.set_line_width(1.0 / self.zoom_level);
+        cr.stroke();
+        cr.restore();
+    }
+
+    fn draw_rubber_band(&self, cr: &Context) {
+        let band = match self.rubber_band_rect() {
+            Some(val) => val,
+            None => return,
+        };
+
+        cr.save();
+        cr.new_path();
+        cr.rectangle(&band);
+        // #3584E4
+        cr.set_source_rgba(&RgbaColor::new(0.21, 0.52, 0.89, 0.2));
+        cr.fill_preserve();
+        cr.set_source_rgb(&RgbColor::new(0.21, 0.52, 0.89));
+        cr.set_line_width(1.0 / self.zoom_level);
+        cr.stroke();
+        cr.restore();
+    }
+
+    // @before is what changing_extents() returned before handling an event,
+    // the event damaged it and the new extents if anything changed.
+    fn add_damage(
+        &mut self, before: Vec<Rectangle>, cr: &Context, handled: bool
+    ) {
+        let after = self.changing_extents(cr);
+        self.last_extents = after.clone();
+        if !handled && before == after {
+            return;
+        }
+        self.damage.extend(before);
+        self.damage.extend(after);
+    }
+
+    // the options of the selected shapes changed, damage their extents
+    // before and after the change.
+    pub fn options_changed(&mut self) {
+        let cr = self.measure_cr();
+        let cr = self.measure_context(&cr);
+        let before = self.last_extents.clone();
+        self.add_damage(before, &cr, true);
+        self.changed = true;
+    }
+
+    // return the areas that need to be redrawn since the last call
+    pub fn take_damage(&mut self) -> Vec<Rectangle> {
+        self.damage.drain(..).collect()
+    }
+
+    // return true if the events changed the page since the last call
+    pub fn take_changed(&mut self) -> bool {
+        let changed = self.changed;
+        self.changed = false;
+        changed
+    }
+
+    fn draw_smart_guides(&self, cr: &Context) {
+        if self.smart_guides.is_empty() {
+            return;
+        }
+
+        let width = self.size.width as f64;
+        let height = self.size.height as f64;
+
+        cr.save();
+        cr.new_path();
+        for guide in self.smart_guides.iter() {
+            match *guide {
+                Guide::Vertical(x) => {
+                    cr.move_to(&Point::new(x, 0.0));
+                    cr.line_to(&Point::new(x, height));
+                },
+                Guide::Horizontal(y) => {
+                    cr.move_to(&Point::new(0.0, y));
+                    cr.line_to(&Point::new(width, y));
+                },
+            }
+        }
+        cr.set_source_rgb(&RgbColor::new(1.0, 0.0, 1.0)); // #FF00FF
+        cr.set_line_width(1.0 / self.zoom_level);
+        cr.stroke();
+        cr.restore();
+    }
+
     pub fn draw(&self, cr: &cairo::Context) {
         cr.save();
         let matrix = cr.get_matrix();
 
-        let context =
+        let mut context =
             Context::new(cr, self.zoom_level, &self.translate);
+        context.set_render_options(self.render_options(false));
 
         context.save();
         context.set_line_width(self.line_width());
@@ -204,22 +1153,126 @@ impl Page {
             context.fill();
         }
         context.new_path();
+        if let Some(ref background) = self.background {
+            context.rectangle(&self.page_bound());
+            context.clip();
+            background.draw(&context, 1.0);
+        }
+        if let Some(ref previous) = self.previous_background {
+            previous.draw(&context, PREVIOUS_BACKGROUND_ALPHA);
+        }
         context.restore();
 
+        self.draw_grid(&context);
+
         for layer in self.layers.iter() {
             layer.draw(&context);
         }
 
+        self.draw_smart_guides(&context);
+        self.draw_rubber_band(&context);
+        self.draw_crop_band(&context);
+
+        cr.set_matrix(matrix);
+        cr.restore();
+    }
+
+    // draw the page as it's exported, in the current user space of @cr and
+    // clipped to the page size: no border, grid, guides or controllers, and
+    // no background if @transparent. if @step is given only the shapes
+    // revealed up to that step are drawn. the caption strip, if any, is
+    // drawn below the page.
+    pub fn render(
+        &self, cr: &cairo::Context, transparent: bool, step: Option<u32>
+    ) {
+        self.render_page(cr, transparent, step, true);
+    }
+
+    // like render() but without the caption strip, for the exports that
+    // write the notes as text next to the page image.
+    pub fn render_without_caption(
+        &self, cr: &cairo::Context, transparent: bool, step: Option<u32>
+    ) {
+        self.render_page(cr, transparent, step, false);
+    }
+
+    fn render_page(
+        &self,
+        cr: &cairo::Context,
+        transparent: bool,
+        step: Option<u32>,
+        caption: bool
+    ) {
+        cr.save();
+        let matrix = cr.get_matrix();
+
+        let mut context = Context::new(cr, 1.0, &Vector::new(0.0, 0.0));
+        context.set_render_options(RenderOptions {
+            show_controllers: false,
+            reveal_step: step,
+            .. self.render_options(transparent)
+        });
+
+        context.save();
+        context.rectangle(&self.page_bound());
+        context.clip();
+        if let (Some(color), false) = (self.color, transparent) {
+            context.set_source_rgb(&color);
+            context.paint();
+        }
+        if let Some(ref background) = self.background {
+            background.draw(&context, 1.0);
+        }
+
+        for layer in self.layers.iter() {
+            layer.draw(&context);
+        }
+        context.restore();
+
+        if caption {
+            self.draw_caption(&context);
+        }
+
+        cr.set_matrix(matrix);
+        cr.restore();
+    }
+
+    // draw the guide lines placed by the user across the visible area, they
+    // are drawn by the editor on top of the page and never exported.
+    pub fn draw_guides(&self, cr: &cairo::Context) {
+        if self.guides.is_empty() {
+            return;
+        }
+
+        cr.save();
+        let matrix = cr.get_matrix();
+        let context = Context::new(cr, self.zoom_level, &self.translate);
+        let (x1, y1, x2, y2) = context.clip_extents();
+
+        context.new_path();
+        for guide in self.guides.iter() {
+            match *guide {
+                Guide::Vertical(x) => {
+                    context.move_to(&Point::new(x, y1));
+                    context.line_to(&Point::new(x, y2));
+                },
+                Guide::Horizontal(y) => {
+                    context.move_to(&Point::new(x1, y));
+                    context.line_to(&Point::new(x2, y));
+                },
+            }
+        }
+        context.set_source_rgb(&RgbColor::new(0.21, 0.52, 0.89)); // #3584E4
+        context.set_line_width(1.0 / self.zoom_level);
+        context.stroke();
+
         cr.set_matrix(matrix);
         cr.restore();
     }
 
     pub fn in_draw(&self, pos: &Point) -> bool {
-        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
-            .expect("Cairo: couldn't create surface");
-        let cr = cairo::Context::new(&surface);
-        let cr =
-            Context::new(&cr, self.zoom_level, &self.translate);
+        let cr = self.measure_cr();
+        let cr = self.measure_context(&cr);
         for layer in self.layers.iter() {
             if layer.in_draw(&pos, &cr) {
                 return true;
@@ -229,11 +1282,8 @@ impl Page {
     }
 
     pub fn draw_extents(&self) -> Option<Rectangle> {
-        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
-            .expect("Cairo: couldn't create surface");
-        let cr = cairo::Context::new(&surface);
-        let cr =
-            Context::new(&cr, self.zoom_level, &Vector::new(0.0, 0.0));
+        let cr = self.measure_cr();
+        let cr = self.measure_context(&cr);
 
         cr.save();
         cr.set_line_width(self.line_width());
@@ -241,99 +1291,229 @@ impl Page {
 
         let fill_extents = cr.user_to_device_rect(&cr.fill_extents());
         let stroke_extents = cr.user_to_device_rect(&cr.stroke_extents());
-        let page_bound = fill_extents.merged(&stroke_extents);
+        let mut result = fill_extents.merged(&stroke_extents);
         cr.restore();
-        if self.layers.len() == 0 {
-            return Some(page_bound);
+
+        for extents in self.layers.iter().filter_map(|s| s.draw_extents(&cr)) {
+            result.merge(&extents);
         }
 
-        let mut iter =
-            self.layers
-                .iter()
-                .filter_map(|s| s.draw_extents(&cr));
-        let init = match iter.next() {
-            None => return Some(page_bound),
-            Some(val) => val,
-        };
-        let mut result = iter.fold(init, |acc, ref x| acc.merged(x));
-        result.merge(&page_bound);
-        Some(result)
+        // the extents are measured without the translate, since it's computed
+        // from them.
+        Some(Rectangle::new(
+            *result.mins() - self.translate,
+            *result.maxs() - self.translate
+        ))
     }
 
     pub fn motion_notify(&mut self, event: &EventMotion) -> bool {
-        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
-            .expect("Cairo: couldn't create surface");
-        let cr = cairo::Context::new(&surface);
-        let cr = Context::new(&cr, self.zoom_level, &self.translate);
+        let cr = self.measure_cr();
+        let mut cr = self.measure_context(&cr);
+        cr.set_snap_step(self.snap_step());
         let (x, y) = event.get_position();
         let pos = cr.device_to_user(&Point::new(x, y));
 
+        // shapes are dragged with the first button, and shapes being created
+        // follow the pointer.
+        if event.get_state().contains(gdk::BUTTON1_MASK) ||
+           self.get_active_layer().has_shapes_in_creating_mode() {
+            let targets = self.snap_targets(&cr);
+            cr.set_snap_targets(Some(targets));
+        }
+
+        let before = self.changing_extents(&cr);
+        if let Some((start, _)) = self.crop_band {
+            self.crop_band = Some((start, pos));
+            self.add_damage(before, &cr, true);
+            return true;
+        }
+
+        if let Some((start, _)) = self.rubber_band {
+            self.rubber_band = Some((start, pos));
+            self.add_damage(before, &cr, true);
+            return true;
+        }
+
+        if let Some(offset) = self.background_drag {
+            if let Some(ref mut background) = self.background {
+                background.set_position(pos - offset);
+            }
+            self.changed = true;
+            self.add_damage(before, &cr, true);
+            return true;
+        }
+
+        let mut result = false;
         for layer in self.layers.iter_mut().rev() {
             if layer.motion_notify(event, &pos, &cr) {
-                return true;
+                result = true;
+                break;
             }
         }
-        false
+        // the selected shapes are dragged
+        if result && event.get_state().contains(gdk::BUTTON1_MASK) {
+            self.changed = true;
+        }
+        self.smart_guides = cr.take_guides();
+        self.add_damage(before, &cr, result);
+        result
     }
 
     pub fn button_press(
-        &mut self, event: &EventButton, options_widget: &gtk::Notebook
+        &mut self, event: &EventButton, options_widget: &OptionsWidget
     ) -> bool {
-        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
-            .expect("Cairo: couldn't create surface");
-        let cr = cairo::Context::new(&surface);
-        let cr = Context::new(&cr, self.zoom_level, &self.translate);
+        let cr = self.measure_cr();
+        let mut cr = self.measure_context(&cr);
+        cr.set_snap_step(self.snap_step());
         let (x, y) = event.get_position();
         let pos = cr.device_to_user(&Point::new(x, y));
 
-        for layer in self.layers.iter_mut().rev() {
-            if layer.button_press(event, &pos, &cr, options_widget) {
+        // clean up options widget
+        let n_pages = options_widget.get_n_pages();
+        for _ in 0..n_pages {
+            options_widget.remove_page(Some(0));
+        }
+
+        let before = self.changing_extents(&cr);
+        let mut result = false;
+
+        // while cropping the first button start the crop rectangle
+        if self.cropping {
+            if event.get_button() == 1 &&
+               event.get_event_type() == gdk::EventType::ButtonPress {
+                self.crop_band = Some((pos, pos));
+                self.add_damage(before, &cr, true);
                 return true;
             }
+            return false;
         }
-        false
+
+        // shapes that are being created belong to the active layer, so it
+        // get the event before any other layer.
+        let active_layer_index = self.active_layer_index;
+        if self.layers[active_layer_index].has_shapes_in_creating_mode() {
+            for (index, layer) in self.layers.iter_mut().enumerate() {
+                if index != active_layer_index {
+                    layer.unselect_all_shapes();
+                }
+            }
+
+            // the clicked points snap like they did while following the
+            // pointer.
+            let targets = self.snap_targets(&cr);
+            cr.set_snap_targets(Some(targets));
+            result = self.layers[active_layer_index]
+                         .button_press(event, &pos, &cr, options_widget);
+            // the clicks place the points of the created shapes
+            self.changed = self.changed || result;
+        } else {
+            // only one layer can handle the event, shapes in the other
+            // layers get unselected.
+            for layer in self.layers.iter_mut().rev() {
+                if result {
+                    layer.unselect_all_shapes();
+                } else if layer.button_press(event, &pos, &cr, options_widget) {
+                    result = true;
+                }
+            }
+
+            // pressing away from the shapes drag the background if it's
+            // unlocked, otherwise it start selecting with a rubber band.
+            if !result && event.get_button() == 1 &&
+               event.get_event_type() == gdk::EventType::ButtonPress {
+                let drag = match self.background {
+                    Some(ref val) if !val.is_locked() && val.contains(&pos) =>
+                        Some(pos - *val.get_position()),
+                    _ => None,
+                };
+                if drag.is_some() {
+                    self.background_drag = drag;
+                    result = true;
+                } else {
+                    self.rubber_band = Some((pos, pos));
+                }
+            }
+        }
+        self.add_damage(before, &cr, result);
+        result
     }
 
     pub fn button_release(&mut self, event: &EventButton) -> bool {
-        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
-            .expect("Cairo: couldn't create surface");
-        let cr = cairo::Context::new(&surface);
-        let cr = Context::new(&cr, self.zoom_level, &self.translate);
+        let cr = self.measure_cr();
+        let mut cr = self.measure_context(&cr);
+        cr.set_snap_step(self.snap_step());
         let (x, y) = event.get_position();
         let pos = cr.device_to_user(&Point::new(x, y));
 
+        let before = self.changing_extents(&cr);
+        self.smart_guides.clear();
+        if let Some(band) = self.crop_band_rect() {
+            // the page size changes, so the whole page is redrawn
+            self.set_cropping(false);
+            self.crop(&band);
+            self.changed = true;
+            self.add_damage(before, &cr, true);
+            return true;
+        }
+        if let Some(band) = self.rubber_band_rect() {
+            self.rubber_band = None;
+            let active_layer_index = self.active_layer_index;
+            self.layers[active_layer_index].select_children_in(&band, &cr);
+            self.add_damage(before, &cr, true);
+            return true;
+        }
+
+        if self.background_drag.is_some() {
+            // the damage include the background at its final position
+            self.add_damage(before, &cr, true);
+            self.background_drag = None;
+            return true;
+        }
+
+        // releasing the button finish the shapes being created, the drags
+        // that moved the other shapes are marked by motion_notify().
+        let creating = self.get_active_layer().has_shapes_in_creating_mode();
+        let mut result = false;
         for layer in self.layers.iter_mut().rev() {
             if layer.button_release(event, &pos, &cr) {
-                return true;
+                result = true;
+                break;
             }
         }
-        false
+        self.changed = self.changed || (creating && result);
+        self.add_damage(before, &cr, result);
+        result
     }
 
     pub fn key_press(&mut self, event: &EventKey) -> bool {
-        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
-            .expect("Cairo: couldn't create surface");
-        let cr = cairo::Context::new(&surface);
-        let cr = Context::new(&cr, self.zoom_level, &self.translate);
+        let cr = self.measure_cr();
+        let cr = self.measure_context(&cr);
+        let before = self.changing_extents(&cr);
+        let mut result = false;
         for layer in self.layers.iter_mut().rev() {
             if layer.key_press(event, &cr) {
-                return true;
+                result = true;
+                break;
             }
         }
-        false
+        self.changed = self.changed || result;
+        self.add_damage(before, &cr, result);
+        result
     }
 
     pub fn key_release(&mut self, event: &EventKey) -> bool {
-        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
-            .expect("Cairo: couldn't create surface");
-        let cr = cairo::Context::new(&surface);
-        let cr = Context::new(&cr, self.zoom_level, &self.translate);
+        let cr = self.measure_cr();
+        let cr = self.measure_context(&cr);
+        let before = self.changing_extents(&cr);
+        let mut result = false;
         for layer in self.layers.iter_mut().rev() {
             if layer.key_release(event, &cr) {
-                return true;
+                result = true;
+                break;
             }
         }
-        false
+        self.add_damage(before, &cr, result);
+        result
     }
 }
 
@@ -346,3 +1526,177 @@ impl Name for Page {
         self.name.clone_from(name);
     }
 }
+
+fn last_step_of(shapes: &Vec<Box<ShapeTrait>>) -> u32 {
+    shapes.iter()
+          .filter(|shape| shape.is_visible())
+          .map(|shape| shape.get_step().max(last_step_of(shape.get_children())))
+          .max()
+          .unwrap_or(0)
+}
+
+fn set_caption_font(cr: &cairo::Context, bold: bool, italic: bool) {
+    let slant = if italic {
+        cairo::FontSlant::Italic
+    } else {
+        cairo::FontSlant::Normal
+    };
+    let weight = if bold {
+        cairo::FontWeight::Bold
+    } else {
+        cairo::FontWeight::Normal
+    };
+    cr.select_font_face("Sans", slant, weight);
+    cr.set_font_size(CAPTION_FONT_SIZE);
+}
+
+fn spans_width(cr: &cairo::Context, spans: &Vec<Span>) -> f64 {
+    spans.iter()
+         .map(|span| {
+             set_caption_font(cr, span.bold, span.italic);
+             cr.text_extents(span.text.as_str()).x_advance
+         })
+         .sum()
+}
+
+// split @spans at the white spaces, a word can be made of more than one
+// span when its style change in the middle
+fn caption_words(spans: &Vec<Span>) -> Vec<Vec<Span>> {
+    let mut words = vec![];
+    let mut word = vec![];
+    for span in spans.iter() {
+        let mut text = String::new();
+        for c in span.text.chars() {
+            if !c.is_whitespace() {
+                text.push(c);
+                continue;
+            }
+            if !text.is_empty() {
+                word.push(Span::new(text.as_str(), span.bold, span.italic));
+                text.clear();
+            }
+            if !word.is_empty() {
+                words.push(word);
+                word = vec![];
+            }
+        }
+        if !text.is_empty() {
+            word.push(Span::new(text.as_str(), span.bold, span.italic));
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+// break @word into pieces that are not wider than @max_width, every piece
+// has one letter at least
+fn break_word(
+    cr: &cairo::Context, word: Vec<Span>, max_width: f64
+) -> Vec<Vec<Span>> {
+    let mut pieces = vec![];
+    let mut piece = vec![];
+    let mut width = 0.0;
+    for span in word {
+        set_caption_font(cr, span.bold, span.italic);
+        let mut text = String::new();
+        for c in span.text.chars() {
+            let c_width = cr.text_extents(c.to_string().as_str()).x_advance;
+            if width > 0.0 && width + c_width > max_width {
+                if !text.is_empty() {
+                    piece.push(Span::new(text.as_str(), span.bold, span.italic));
+                    text.clear();
+                }
+                pieces.push(piece);
+                piece = vec![];
+                width = 0.0;
+            }
+            text.push(c);
+            width += c_width;
+        }
+        if !text.is_empty() {
+            piece.push(Span::new(text.as_str(), span.bold, span.italic));
+        }
+    }
+    if !piece.is_empty() {
+        pieces.push(piece);
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::line_arrow::{self, LineArrow};
+
+    // a page with an arrow from (100, 50) to (200, 150)
+    fn page_with_arrow() -> Page {
+        let mut page = Page::new();
+        page.set_size(Size::new(400, 300));
+        let mut arrow = LineArrow::new(
+            Segment::new(Point::new(100.0, 50.0), Point::new(200.0, 150.0))
+        );
+        arrow.set_mode(line_arrow::Mode::Editing);
+        page.add_shape(Box::new(arrow));
+        page
+    }
+
+    #[test]
+    fn adding_and_removing_shapes_change_the_page() {
+        let mut page = page_with_arrow();
+        assert!(page.take_changed());
+        assert!(!page.take_changed());
+
+        page.unselect_all_shapes();
+        assert!(!page.take_changed());
+        page.remove_selected_shapes();
+        assert!(page.take_changed());
+    }
+
+    #[test]
+    fn crop_moves_the_content() {
+        let mut page = page_with_arrow();
+        page.add_guide(Guide::Vertical(120.0));
+        page.add_guide(Guide::Horizontal(60.0));
+
+        // the corners are rounded to whole pixels
+        assert!(page.crop(&Rectangle::new(Point::new(49.6, 20.2),
+                                          Point::new(300.0, 220.0))));
+        assert_eq!(page.get_size(), &Size::new(250, 200));
+        let arrow = &page.get_layers()[0].get_children()[0];
+        assert_eq!(arrow.position(), Point::new(100.0, 80.0));
+        assert!(page.get_guides()[0] == Guide::Vertical(70.0));
+        assert!(page.get_guides()[1] == Guide::Horizontal(40.0));
+    }
+
+    #[test]
+    fn crop_rejects_empty_rectangles() {
+        let mut page = page_with_arrow();
+        assert!(!page.crop(&Rectangle::new(Point::new(10.0, 10.0),
+                                           Point::new(10.3, 50.0))));
+        assert_eq!(page.get_size(), &Size::new(400, 300));
+    }
+
+    #[test]
+    fn trim_to_content() {
+        let mut page = page_with_arrow();
+        let before = page.content_extents().unwrap();
+        assert!(page.trim_to_content(10.0));
+
+        // the content is moved to the margin and keeps its size
+        let after = page.content_extents().unwrap();
+        assert!((after.mins().x - 10.0).abs() <= 0.5);
+        assert!((after.mins().y - 10.0).abs() <= 0.5);
+        let width = before.maxs().x - before.mins().x;
+        assert!((page.get_size().width as f64 - width - 20.0).abs() <= 1.0);
+    }
+
+    #[test]
+    fn trim_empty_page() {
+        let mut page = Page::new();
+        page.set_size(Size::new(400, 300));
+        assert!(!page.trim_to_content(10.0));
+        assert_eq!(page.get_size(), &Size::new(400, 300));
+    }
+}
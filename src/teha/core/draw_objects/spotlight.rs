@@ -0,0 +1,1023 @@
+//
+// spotlight.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+use cairo;
+use gdk::{self, EventMotion, EventButton};
+use gtk::prelude::*;
+use gtk::{self, SwitchExt, ContainerExt, WidgetExt, GridExt, NotebookExtManual,
+          EntryExt, ColorButtonExt, ColorChooserExt, Cast, SpinButtonExt,
+          ComboBoxExt, ComboBoxTextExt, ButtonExt, ToggleButtonExt};
+
+use gettextrs::*;
+
+use ncollide::bounding_volume::BoundingVolume;
+use na;
+use alga::linear::Transformation;
+
+use core::context::Context;
+use core::data::{self, ShapeData, SpotlightData, HoleData};
+use error::Error;
+use common::types::*;
+use super::*;
+
+// the smallest hole, smaller holes made by a click get the default size
+const MIN_HOLE_SIZE: f64 = 4.0;
+const DEFAULT_HOLE_SIZE: (f64, f64) = (160.0, 100.0);
+// the feathered edge is drawn as this many outlines at most
+const MAX_FEATHER_STEPS: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum HoleShape {
+    Rectangle,
+    Ellipse,
+}
+
+#[derive(Clone)]
+pub struct Hole {
+    pub shape: HoleShape,
+    pub rect: Rectangle,
+}
+
+impl Hole {
+    fn center(&self) -> Point {
+        na::center(self.rect.mins(), self.rect.maxs())
+    }
+
+    fn translated(&self, offset: &Vector) -> Hole {
+        Hole {
+            shape: self.shape,
+            rect: Rectangle::new(
+                self.rect.mins() + offset, self.rect.maxs() + offset
+            ),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Mode {
+    Editing,
+    Creating,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Actions {
+    // the corner where the user started dragging the active hole
+    PickHole(Point),
+    MoveHole(Vector),
+    ResizeHole,
+}
+
+// dim the whole page except its holes, it's drawn in the layers order so
+// the shapes above it stay bright.
+pub struct Spotlight {
+    children: Vec<Box<ShapeTrait>>,
+    // ID field
+    name: Rc<RefCell<String>>,
+    // control fields
+    lock: Rc<RefCell<bool>>,
+    selected: bool,
+    // this field for Event trait.
+    action: Option<Actions>,
+    mode: Mode,
+    // the next drag on the page add a hole, set from the options widget
+    adding_hole: Rc<RefCell<bool>>,
+    // the hole that was clicked last, the options widget edit it
+    active_hole: Rc<RefCell<usize>>,
+    // draw fields
+    visible: Rc<RefCell<bool>>,
+    step: Rc<RefCell<u32>>,
+    dim_color: Rc<RefCell<RgbaColor>>,
+    // the width of the soft edge around the holes
+    feather: Rc<RefCell<f64>>,
+    holes: Rc<RefCell<Vec<Hole>>>,
+}
+
+impl Spotlight {
+    pub fn new() -> Self {
+        Spotlight {
+            children: vec![],
+            name: Rc::new(RefCell::new(gettext("Spotlight"))),
+            lock: Rc::new(RefCell::new(false)),
+            selected: false,
+            action: None,
+            mode: Mode::Creating,
+            adding_hole: Rc::new(RefCell::new(false)),
+            active_hole: Rc::new(RefCell::new(0)),
+            visible: Rc::new(RefCell::new(true)),
+            step: Rc::new(RefCell::new(0)),
+            dim_color: Rc::new(RefCell::new(RgbaColor::new(0.0, 0.0, 0.0, 0.6))),
+            feather: Rc::new(RefCell::new(12.0)),
+            holes: Rc::new(RefCell::new(vec![])),
+        }
+    }
+
+    pub fn from_data(data: SpotlightData) -> Result<Self, Error> {
+        let mut spotlight = Spotlight::new();
+        spotlight.mode = Mode::Editing;
+        spotlight.set_name(&data.name);
+        spotlight.set_color(&data::rgba_from_data(&data.dim_color));
+        spotlight.set_feather(data.feather);
+        spotlight.holes.borrow_mut().extend(data.holes.iter().map(|hole| {
+            Hole {
+                shape: hole.shape,
+                rect: Rectangle::new(
+                    data::point_from_data(&hole.mins),
+                    data::point_from_data(&hole.maxs)
+                ),
+            }
+        }));
+        spotlight.set_step(data.step);
+        if data.lock {
+            spotlight.lock();
+        }
+        if !data.visible {
+            spotlight.hide();
+        }
+        spotlight.children = data::shapes_from_data(data.children)?;
+        Ok(spotlight)
+    }
+
+    // This crazy method will be replaced with Template UI file when GTK-rs
+    // support that
+    pub fn connect_ui(&mut self, options_widget: &OptionsWidget) {
+        let create_label = |text: &str| {
+            let label = gtk::Label::new(text);
+            label.set_halign(gtk::Align::End);
+            label
+        };
+
+        let grid = gtk::Grid::new();
+        grid.set_property_margin(10);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(10);
+
+        let name_label = create_label(
+            gettext("Name:").as_str()
+        );
+        let name_entry = gtk::Entry::new();
+        name_entry.set_hexpand(true);
+        name_entry.set_text(self.name().as_str());
+        let name = self.name.clone();
+        let options = options_widget.clone();
+        name_entry.connect_property_text_notify(move |me| {
+            if let Some(text) = me.get_text() {
+                name.borrow_mut().clone_from(&text);
+                options.changed();
+            }
+        });
+        grid.attach(&name_label, 0, 0, 1, 1);
+        grid.attach(&name_entry, 1, 0, 1, 1);
+
+        let color_label = create_label(
+            gettext("Dim Color:").as_str()
+        );
+        let color_init_value = self.get_color();
+        let color_init_value = gdk::RGBA {
+            red: color_init_value.color.red,
+            green: color_init_value.color.green,
+            blue: color_init_value.color.blue,
+            alpha: color_init_value.alpha
+        };
+        let color_button = gtk::ColorButton::new_with_rgba(&color_init_value);
+        color_button.set_title(
+            gettext("Dim Color").as_str()
+        );
+        color_button.set_use_alpha(true);
+        color_button.set_tooltip_text(
+            gettext("The alpha of the color is the dim opacity").as_str()
+        );
+        let color = self.dim_color.clone();
+        let options = options_widget.clone();
+        let color_chooser = color_button
+            .clone()
+            .dynamic_cast::<gtk::ColorChooser>()
+            .expect("GtkButtonChooser does implmente GtkColorChooser \
+                     interface");
+        color_chooser.connect_property_rgba_notify(move |me| {
+            let new_color = me.get_rgba();
+            let new_color = RgbaColor::new(
+                new_color.red, new_color.green, new_color.blue, new_color.alpha
+            );
+            color.borrow_mut().clone_from(&new_color);
+            options.changed();
+        });
+        grid.attach(&color_label, 0, 1, 1, 1);
+        grid.attach(&color_button, 1, 1, 1, 1);
+
+        let feather_label = create_label(
+            gettext("Feather:").as_str()
+        );
+        let feather_spin = gtk::SpinButton::new_with_range(0.0, 200.0, 1.0);
+        feather_spin.set_value(self.get_feather());
+        let feather = self.feather.clone();
+        let options = options_widget.clone();
+        feather_spin.connect_property_value_notify(move |me| {
+            feather.borrow_mut().clone_from(&me.get_value());
+            options.changed();
+        });
+        grid.attach(&feather_label, 0, 2, 1, 1);
+        grid.attach(&feather_spin, 1, 2, 1, 1);
+
+        let shape_label = create_label(gettext("Hole:").as_str());
+        let shape_combo = gtk::ComboBoxText::new();
+        shape_combo.append_text(gettext("Rectangle").as_str());
+        shape_combo.append_text(gettext("Ellipse").as_str());
+        shape_combo.set_tooltip_text(
+            gettext("The shape of the last clicked hole").as_str()
+        );
+        let active_shape = self.holes
+                               .borrow()
+                               .get(*self.active_hole.borrow())
+                               .map(|hole| hole.shape);
+        match active_shape {
+            Some(HoleShape::Rectangle) => shape_combo.set_active(0),
+            Some(HoleShape::Ellipse) => shape_combo.set_active(1),
+            None => shape_combo.set_sensitive(false),
+        }
+        let holes = self.holes.clone();
+        let active_hole = self.active_hole.clone();
+        let options = options_widget.clone();
+        shape_combo.connect_changed(move |me| {
+            let shape = match me.get_active() {
+                1 => HoleShape::Ellipse,
+                _ => HoleShape::Rectangle,
+            };
+            if let Some(hole) =
+                   holes.borrow_mut().get_mut(*active_hole.borrow()) {
+                hole.shape = shape;
+            }
+            options.changed();
+        });
+        grid.attach(&shape_label, 0, 3, 1, 1);
+        grid.attach(&shape_combo, 1, 3, 1, 1);
+
+        let holes_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        holes_box.get_style_context()
+                 .map(|style| style.add_class("linked"));
+        let add_button = gtk::ToggleButton::new_with_label(
+            gettext("Add Hole").as_str()
+        );
+        add_button.set_tooltip_text(
+            gettext("Drag on the page to cut another hole").as_str()
+        );
+        add_button.set_active(*self.adding_hole.borrow());
+        let adding_hole = self.adding_hole.clone();
+        add_button.connect_toggled(move |me| {
+            adding_hole.borrow_mut().clone_from(&me.get_active());
+        });
+        let remove_button = gtk::Button::new_with_label(
+            gettext("Remove Hole").as_str()
+        );
+        remove_button.set_tooltip_text(
+            gettext("Remove the last clicked hole").as_str()
+        );
+        remove_button.set_sensitive(active_shape.is_some());
+        let holes = self.holes.clone();
+        let active_hole = self.active_hole.clone();
+        let options = options_widget.clone();
+        let inner_shape_combo = shape_combo.clone();
+        remove_button.connect_clicked(move |me| {
+            let index = *active_hole.borrow();
+            if index < holes.borrow().len() {
+                holes.borrow_mut().remove(index);
+            }
+            me.set_sensitive(false);
+            inner_shape_combo.set_sensitive(false);
+            options.changed();
+        });
+        holes_box.pack_start(&add_button, true, true, 0);
+        holes_box.pack_start(&remove_button, true, true, 0);
+        grid.attach(&holes_box, 0, 4, 2, 1);
+
+        let visible_label = create_label(gettext("Visible:").as_str());
+        let visible_switch = gtk::Switch::new();
+        visible_switch.set_halign(gtk::Align::Start);
+        visible_switch.set_active(self.is_visible());
+        let visible = self.visible.clone();
+        let options = options_widget.clone();
+        visible_switch.connect_property_active_notify(move |me| {
+            visible.borrow_mut().clone_from(&me.get_active());
+            options.changed();
+        });
+        grid.attach(&visible_label, 0, 5, 1, 1);
+        grid.attach(&visible_switch, 1, 5, 1, 1);
+
+        let step_label = create_label(gettext("Reveal Step:").as_str());
+        let step_spin = gtk::SpinButton::new_with_range(0.0, 99.0, 1.0);
+        step_spin.set_halign(gtk::Align::Start);
+        step_spin.set_value(self.get_step() as f64);
+        let step = self.step.clone();
+        let options = options_widget.clone();
+        step_spin.connect_property_value_notify(move |me| {
+            step.borrow_mut().clone_from(&(me.get_value_as_int() as u32));
+            options.changed();
+        });
+        grid.attach(&step_label, 0, 6, 1, 1);
+        grid.attach(&step_spin, 1, 6, 1, 1);
+
+        let lock_label = create_label(gettext("Lock:").as_str());
+        let lock_switch = gtk::Switch::new();
+        lock_switch.set_halign(gtk::Align::Start);
+        lock_switch.set_active(self.is_locked());
+        let lock = self.lock.clone();
+        let options = options_widget.clone();
+        lock_switch.connect_property_active_notify(move |me| {
+            lock.borrow_mut().clone_from(&me.get_active());
+            options.changed();
+        });
+        grid.attach(&lock_label, 0, 7, 1, 1);
+        grid.attach(&lock_switch, 1, 7, 1, 1);
+
+        grid.show_all();
+        let tab_label = gtk::Label::new(
+            gettext("Options").as_str()
+        );
+        options_widget.append_page(&grid, Some(&tab_label));
+    }
+
+    fn radius(&self) -> f64 {
+        6.0
+    }
+
+    fn fill_color(&self) -> RgbColor {
+        RgbColor::new(0.97, 0.97, 1.0) // #F8F8FF
+    }
+
+    fn stroke_color(&self) -> RgbColor {
+        RgbColor::new(0.47, 0.53, 0.60) // #778899
+    }
+
+    fn line_width(&self) -> f64 {
+        2.0
+    }
+
+    pub fn get_feather(&self) -> f64 {
+        *self.feather.borrow()
+    }
+
+    pub fn set_feather(&mut self, feather: f64) {
+        self.feather.borrow_mut().clone_from(&feather.max(0.0));
+    }
+
+    pub fn get_holes(&self) -> Vec<Hole> {
+        self.holes.borrow().clone()
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        self.mode.clone()
+    }
+
+    pub fn set_action(&mut self, action: Option<Actions>) {
+        self.action = action;
+    }
+
+    pub fn get_action(&self) -> Option<Actions> {
+        self.action.clone()
+    }
+
+    // the area covered by the holes, None if there are no holes
+    fn holes_extents(&self) -> Option<Rectangle> {
+        let holes = self.holes.borrow();
+        let mut iter = holes.iter();
+        let mut result = match iter.next() {
+            Some(hole) => hole.rect.clone(),
+            None => return None,
+        };
+        for hole in iter {
+            result.merge(&hole.rect);
+        }
+        Some(result)
+    }
+
+    // the dimmed area, it's the page unless @cr doesn't know the page.
+    fn dim_area(&self, cr: &Context) -> Rectangle {
+        if let Some(bounds) = cr.get_page_bounds() {
+            return bounds;
+        }
+
+        let (x1, y1, x2, y2) = cr.clip_extents();
+        Rectangle::new(Point::new(x1, y1), Point::new(x2, y2))
+    }
+
+    // add the outline of @hole to the path, grown by @grow on each side
+    // (shrunk if it's negative).
+    fn hole_path(&self, cr: &Context, hole: &Hole, grow: f64) {
+        let center = hole.center();
+        let half = (hole.rect.maxs() - hole.rect.mins()) / 2.0 +
+                   Vector::new(grow, grow);
+        if half.x <= 0.0 || half.y <= 0.0 {
+            return;
+        }
+
+        match hole.shape {
+            HoleShape::Rectangle => {
+                cr.rectangle(&Rectangle::new(center - half, center + half));
+            },
+            HoleShape::Ellipse => {
+                cr.save();
+                cr.translate(center.x, center.y);
+                cr.scale(half.x, half.y);
+                cr.new_sub_path();
+                cr.arc(0.0, 0.0, 1.0, 0.0, 2.0 * PI);
+                cr.restore();
+            },
+        }
+    }
+
+    fn resize_handle(hole: &Hole) -> Point {
+        hole.rect.maxs().clone()
+    }
+
+    // return the index of the hole under @pos and the action it start. the
+    // context is restored before returning since it's shared by all the hit
+    // tests and measurements of the page.
+    fn select_controller(
+        &self, pos: &Point, cr: &Context
+    ) -> Option<(usize, Actions)> {
+        let holes = self.holes.borrow();
+        cr.new_path();
+
+        if self.is_selected() {
+            for (index, hole) in holes.iter().enumerate().rev() {
+                cr.save();
+                self.draw_handle(cr, &Spotlight::resize_handle(hole), false);
+                let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+                cr.restore();
+                if hit {
+                    return Some((index, Actions::ResizeHole));
+                }
+            }
+        }
+
+        for (index, hole) in holes.iter().enumerate().rev() {
+            cr.save();
+            cr.new_path();
+            cr.set_line_width(self.line_width());
+            self.hole_path(cr, hole, 0.0);
+            let hit = cr.in_stroke(pos) || cr.in_fill(pos);
+            cr.restore();
+            if hit {
+                let offset = pos.clone() - hole.rect.mins().clone();
+                return Some((index, Actions::MoveHole(offset)));
+            }
+        }
+
+        None
+    }
+
+    // start dragging a new hole from @pos
+    fn start_hole(&mut self, pos: &Point, cr: &Context) {
+        let start = cr.snap(pos);
+        let mut holes = self.holes.borrow_mut();
+        holes.push(Hole {
+            shape: HoleShape::Rectangle,
+            rect: Rectangle::new(start, start),
+        });
+        self.active_hole.borrow_mut().clone_from(&(holes.len() - 1));
+        self.action = Some(Actions::PickHole(start));
+    }
+
+    fn pick_hole(&mut self, start: &Point, pos: &Point, cr: &Context) {
+        let end = cr.snap(pos);
+        let index = *self.active_hole.borrow();
+        if let Some(hole) = self.holes.borrow_mut().get_mut(index) {
+            hole.rect = Rectangle::new(
+                Point::new(start.x.min(end.x), start.y.min(end.y)),
+                Point::new(start.x.max(end.x), start.y.max(end.y))
+            );
+        }
+    }
+
+    // a hole made by a click without dragging get the default size
+    fn end_pick_hole(&mut self) {
+        let index = *self.active_hole.borrow();
+        if let Some(hole) = self.holes.borrow_mut().get_mut(index) {
+            let size = hole.rect.maxs() - hole.rect.mins();
+            if size.x < MIN_HOLE_SIZE || size.y < MIN_HOLE_SIZE {
+                let mins = hole.rect.mins().clone();
+                hole.rect = Rectangle::new(
+                    mins,
+                    mins + Vector::new(DEFAULT_HOLE_SIZE.0, DEFAULT_HOLE_SIZE.1)
+                );
+            }
+        }
+        self.mode = Mode::Editing;
+        self.action = None;
+    }
+
+    fn move_hole(&mut self, pos: &Point, cr: &Context) {
+        if let Some(Actions::MoveHole(offset)) = self.action {
+            let index = *self.active_hole.borrow();
+            if let Some(hole) = self.holes.borrow_mut().get_mut(index) {
+                let size = hole.rect.maxs() - hole.rect.mins();
+                let mins = cr.snap_to_grid(&(pos.clone() - offset));
+                hole.rect = Rectangle::new(mins, mins + size);
+            }
+        }
+    }
+
+    fn resize_hole(&mut self, pos: &Point, cr: &Context) {
+        let pos = cr.snap(pos);
+        let index = *self.active_hole.borrow();
+        if let Some(hole) = self.holes.borrow_mut().get_mut(index) {
+            let mins = hole.rect.mins().clone();
+            let maxs = Point::new(
+                pos.x.max(mins.x + MIN_HOLE_SIZE),
+                pos.y.max(mins.y + MIN_HOLE_SIZE)
+            );
+            hole.rect = Rectangle::new(mins, maxs);
+        }
+    }
+
+    // add the holes to the current group, the alpha of the group is how
+    // much of the dim is removed. the feathered edge is made of outlines
+    // that grow from inside the hole to outside it, so the removed alpha
+    // drop from 1 to 0 across the edge.
+    fn draw_holes_mask(&self, cr: &Context) {
+        let feather = self.get_feather();
+        let steps = if feather <= 0.0 {
+            1
+        } else {
+            (feather.ceil() as usize).max(2).min(MAX_FEATHER_STEPS)
+        };
+
+        cr.set_operator(cairo::Operator::Add);
+        cr.set_source_rgba(&RgbaColor::new(0.0, 0.0, 0.0, 1.0 / steps as f64));
+        for hole in self.holes.borrow().iter() {
+            for index in 0..steps {
+                let grow = if steps == 1 {
+                    0.0
+                } else {
+                    feather / 2.0 - feather * index as f64 / (steps - 1) as f64
+                };
+                cr.new_path();
+                self.hole_path(cr, hole, grow);
+                cr.fill();
+            }
+        }
+    }
+
+    fn draw_dim(&self, cr: &Context) {
+        let color = self.get_color();
+
+        cr.save();
+        cr.new_path();
+        cr.rectangle(&self.dim_area(cr));
+        cr.clip();
+
+        cr.push_group();
+        cr.set_source_rgb(&color.color);
+        cr.paint();
+
+        cr.push_group();
+        self.draw_holes_mask(cr);
+        let mask = cr.pop_group();
+        cr.set_operator(cairo::Operator::DestOut);
+        cr.mask(&mask);
+
+        cr.pop_group_to_source();
+        cr.paint_with_alpha(color.alpha);
+        cr.restore();
+    }
+
+    fn draw_handle(&self, cr: &Context, pos: &Point, draw_it: bool) {
+        cr.new_path();
+
+        cr.set_line_width(self.line_width());
+        cr.circle(pos, self.radius());
+
+        if draw_it {
+            cr.set_source_rgb(&self.fill_color());
+            cr.fill_preserve();
+            cr.set_source_rgb(&self.stroke_color());
+            cr.stroke();
+        }
+    }
+
+    fn draw_controllers(&self, cr: &Context) {
+        cr.save();
+        cr.new_path();
+        cr.set_line_width(self.line_width());
+        cr.set_source_rgb(&self.stroke_color());
+        cr.set_dash(&[6.0], 0.0);
+        for hole in self.holes.borrow().iter() {
+            self.hole_path(cr, hole, 0.0);
+        }
+        cr.stroke();
+        cr.restore();
+
+        cr.save();
+        for hole in self.holes.borrow().iter() {
+            self.draw_handle(cr, &Spotlight::resize_handle(hole), true);
+        }
+        cr.restore();
+    }
+}
+
+impl Order for Spotlight {}
+impl ShapeTrait for Spotlight {}
+
+impl Draw for Spotlight {
+    fn draw(&self, cr: &Context) {
+        if !self.is_visible() || !cr.is_revealed(self.get_step()) ||
+           self.get_color().alpha == 0.0 {
+            return;
+        }
+
+        cr.save();
+
+        self.draw_dim(cr);
+        if (self.is_selected() || self.get_mode() == Mode::Creating) &&
+           cr.get_show_controllers() {
+            self.draw_controllers(cr);
+        }
+
+        // draw children if there are any.
+        for child in self.children.iter() {
+            child.draw(&cr);
+        }
+
+        cr.restore();
+    }
+
+    fn in_draw(&self, pos: &Point, cr: &Context) -> bool {
+        match self.select_controller(pos, cr) {
+            None => return false,
+            _ => return true,
+        };
+    }
+
+    // the dim cover the page, and the holes with their handles can go out
+    // of it. contexts that don't know the page get only the holes.
+    fn draw_extents(&self, cr: &Context) -> Option<Rectangle> {
+        let mut result =
+            cr.get_page_bounds().map(|bounds| cr.user_to_device_rect(&bounds));
+
+        if let Some(holes) = self.holes_extents() {
+            let margin = self.get_feather() / 2.0 + self.radius() +
+                         self.line_width();
+            let margin = Vector::new(margin, margin);
+            let holes = cr.user_to_device_rect(&Rectangle::new(
+                holes.mins() - margin, holes.maxs() + margin
+            ));
+            result = match result {
+                Some(val) => Some(val.merged(&holes)),
+                None => Some(holes),
+            };
+        }
+
+        result
+    }
+}
+
+impl Snapshot for Spotlight {
+    fn snapshot(&self) -> ShapeData {
+        ShapeData::Spotlight(SpotlightData {
+            name: self.name(),
+            lock: self.is_locked(),
+            visible: self.is_visible(),
+            step: self.get_step(),
+            dim_color: data::rgba_to_data(&self.get_color()),
+            feather: self.get_feather(),
+            holes: self.holes.borrow().iter().map(|hole| HoleData {
+                shape: hole.shape,
+                mins: data::point_to_data(hole.rect.mins()),
+                maxs: data::point_to_data(hole.rect.maxs()),
+            }).collect(),
+            children: self.children.iter().map(|c| c.snapshot()).collect(),
+        })
+    }
+}
+
+impl Name for Spotlight {
+    fn name(&self) -> String {
+        self.name.borrow().clone()
+    }
+
+    fn set_name(&mut self, name: &String) {
+        self.name.borrow_mut().clone_from(&name);
+    }
+}
+
+impl Color for Spotlight {
+    fn get_color(&self) -> RgbaColor {
+        self.dim_color.borrow().clone()
+    }
+
+    fn set_color(&mut self, color: &RgbaColor) {
+        self.dim_color.borrow_mut().clone_from(color);
+    }
+}
+
+// the position is the center of the holes, moving a spotlight move its
+// holes while the dim keep covering the page.
+impl Move for Spotlight {
+    fn position(&self) -> Point {
+        self.holes_extents()
+            .map(|holes| na::center(holes.mins(), holes.maxs()))
+            .unwrap_or(Point::new(0.0, 0.0))
+    }
+
+    fn move_to(&mut self, pos: &Point) {
+        let offset = pos - self.position();
+        self.translate_by(&Translation::from_vector(offset));
+    }
+
+    fn translate_by(&mut self, trans: &Translation) {
+        for hole in self.holes.borrow_mut().iter_mut() {
+            hole.rect = Rectangle::new(
+                trans.transform_point(hole.rect.mins()),
+                trans.transform_point(hole.rect.maxs())
+            );
+        }
+    }
+
+    // the holes stay aligned with the page, only their centers rotate
+    fn rotate_by(&mut self, rotate: &Rotation, origin: &Vector) {
+        let center = self.position() + origin;
+        let trans = Translation::new(-center.x, -center.y);
+        for hole in self.holes.borrow_mut().iter_mut() {
+            let old_center = hole.center();
+            let pos = rotate.transform_point(&trans.transform_point(&old_center));
+            let offset = trans.inverse_transform_point(&pos) - old_center;
+            *hole = hole.translated(&offset);
+        }
+    }
+
+    fn scale_by(&mut self, scale: &Vector, origin: &Point) {
+        for hole in self.holes.borrow_mut().iter_mut() {
+            hole.rect = Rectangle::new(
+                origin + (hole.rect.mins() - origin).component_mul(scale),
+                origin + (hole.rect.maxs() - origin).component_mul(scale)
+            );
+        }
+    }
+}
+
+impl Select for Spotlight {
+    fn is_selected(&self) -> bool {
+        self.selected
+    }
+
+    fn select(&mut self) {
+        self.selected = true;
+    }
+
+    fn unselect(&mut self) {
+        self.selected = false;
+    }
+
+    fn toggle_select(&mut self) -> bool {
+        self.selected = !self.selected;
+        self.selected
+    }
+}
+
+impl Lock for Spotlight {
+    fn is_locked(&self) -> bool {
+        *self.lock.borrow()
+    }
+
+    fn lock(&mut self) {
+        if !self.is_locked() {
+            self.lock.borrow_mut().clone_from(&true);
+        }
+    }
+
+    fn unlock(&mut self) {
+        if self.is_locked() {
+            self.lock.borrow_mut().clone_from(&false);
+        }
+    }
+
+    fn toggle_lock(&mut self) -> bool {
+        self.lock.borrow_mut().clone_from(&!self.is_locked());
+        self.is_locked()
+    }
+}
+
+impl Visible for Spotlight {
+    fn is_visible(&self) -> bool {
+        *self.visible.borrow()
+    }
+
+    fn show(&mut self) {
+        if !self.is_visible() {
+            self.visible.borrow_mut().clone_from(&true);
+        }
+    }
+
+    fn hide(&mut self) {
+        if self.is_visible() {
+            self.visible.borrow_mut().clone_from(&false);
+        }
+    }
+
+    fn toggle_visible(&mut self) -> bool {
+        self.visible.borrow_mut().clone_from(&!self.is_visible());
+        self.is_visible()
+    }
+}
+
+impl Reveal for Spotlight {
+    fn get_step(&self) -> u32 {
+        *self.step.borrow()
+    }
+
+    fn set_step(&mut self, step: u32) {
+        self.step.borrow_mut().clone_from(&step);
+    }
+}
+
+impl Container for Spotlight {
+    fn add(&mut self, child: Box<ShapeTrait>) {
+        self.children.push(child);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Box<ShapeTrait>> {
+        if index >= self.children.len() {
+            None
+        } else {
+            Some(self.children.remove(index))
+        }
+    }
+
+    fn get_children(&self) -> &Vec<Box<ShapeTrait>> {
+        &self.children
+    }
+
+    fn get_mut_children(&mut self) -> &mut Vec<Box<ShapeTrait>> {
+        &mut self.children
+    }
+
+    fn set_children(&mut self, children: Vec<Box<ShapeTrait>>) {
+        self.children = children;
+    }
+}
+
+// flipping and rotating move the holes around their center
+impl Flip for Spotlight {
+    fn flip_vertical(&mut self) {
+        let center = self.position();
+        for hole in self.holes.borrow_mut().iter_mut() {
+            let offset = Vector::new(0.0, 2.0 * (center.y - hole.center().y));
+            *hole = hole.translated(&offset);
+        }
+    }
+
+    fn flip_horizontal(&mut self) {
+        let center = self.position();
+        for hole in self.holes.borrow_mut().iter_mut() {
+            let offset = Vector::new(2.0 * (center.x - hole.center().x), 0.0);
+            *hole = hole.translated(&offset);
+        }
+    }
+}
+
+impl Rotate for Spotlight {
+    fn rotate_left(&mut self) {
+        let center = self.position();
+        for hole in self.holes.borrow_mut().iter_mut() {
+            let offset = hole.center() - center;
+            let half = (hole.rect.maxs() - hole.rect.mins()) / 2.0;
+            let new_center = center + Vector::new(offset.y, -offset.x);
+            let new_half = Vector::new(half.y, half.x);
+            hole.rect =
+                Rectangle::new(new_center - new_half, new_center + new_half);
+        }
+    }
+
+    fn rotate_right(&mut self) {
+        let center = self.position();
+        for hole in self.holes.borrow_mut().iter_mut() {
+            let offset = hole.center() - center;
+            let half = (hole.rect.maxs() - hole.rect.mins()) / 2.0;
+            let new_center = center + Vector::new(-offset.y, offset.x);
+            let new_half = Vector::new(half.y, half.x);
+            hole.rect =
+                Rectangle::new(new_center - new_half, new_center + new_half);
+        }
+    }
+}
+
+impl Event for Spotlight {
+    fn motion_notify(
+        &mut self,
+        event: &EventMotion,
+        pos: &Point,
+        cr: &Context
+    ) -> bool {
+        if self.is_locked() || !self.is_visible() {
+            return false;
+        }
+
+        if let Some(Actions::PickHole(start)) = self.action {
+            self.pick_hole(&start, pos, cr);
+            return true;
+        }
+
+        if event.get_state() == gdk::BUTTON1_MASK {
+            match self.action {
+                None | Some(Actions::PickHole(..)) => return false,
+                Some(Actions::MoveHole(..)) => {
+                    self.move_hole(pos, cr);
+                },
+                Some(Actions::ResizeHole) => {
+                    self.resize_hole(pos, cr);
+                },
+            };
+            return true;
+        }
+        false
+    }
+
+    fn button_press(
+        &mut self,
+        event: &EventButton,
+        pos: &Point,
+        cr: &Context,
+        options_widget: &OptionsWidget
+    ) -> bool {
+        if self.is_locked() || !self.is_visible() {
+            return false;
+        }
+
+        if event.get_button() == 1 {
+            // a new hole is dragged while creating the spotlight, or after
+            // pressing "Add Hole".
+            let adding_hole = *self.adding_hole.borrow();
+            if self.get_mode() == Mode::Creating ||
+               (adding_hole && self.is_selected()) {
+                self.adding_hole.borrow_mut().clone_from(&false);
+                self.start_hole(pos, cr);
+                self.select();
+                self.connect_ui(options_widget);
+                return true;
+            }
+
+            match self.select_controller(pos, cr) {
+                Some((index, action)) => {
+                    self.active_hole.borrow_mut().clone_from(&index);
+                    self.set_action(Some(action));
+                    self.select();
+                    self.connect_ui(options_widget);
+                    return true;
+                },
+                None => {
+                    self.set_action(None);
+                    self.adding_hole.borrow_mut().clone_from(&false);
+                    self.unselect();
+                    return false;
+                },
+            }
+        }
+        false
+    }
+
+    fn button_release(
+        &mut self,
+        event: &EventButton,
+        _pos: &Point,
+        _cr: &Context
+    ) -> bool {
+        if event.get_button() != 1 {
+            return false;
+        }
+
+        if let Some(Actions::PickHole(_)) = self.action {
+            self.end_pick_hole();
+            return true;
+        }
+        false
+    }
+}
+
+impl super::Mode for Spotlight {
+    fn in_creating_mode(&self) -> bool {
+        self.get_mode() == Mode::Creating
+    }
+
+    fn in_editing_mode(&self) -> bool {
+        self.get_mode() == Mode::Editing
+    }
+}
@@ -0,0 +1,935 @@
+//
+// export.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+// This module writes the pages of a document to other formats, pages are
+// always drawn through Page::render() so the exported images look exactly
+// like the preview.
+
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+
+use cairo;
+use gif::{self, SetParameter};
+use base64;
+
+use error::Error;
+use common::types::*;
+use super::Document;
+use super::notes;
+
+// the time (in milliseconds) between the frames of a cross-fade
+const FADE_FRAME_INTERVAL: u32 = 50;
+const PNG_SIGNATURE: &'static [u8] = &[137, 80, 78, 71, 13, 10, 26, 10];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+impl AnimationFormat {
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+        }
+    }
+}
+
+// the frames of an animation are either the pages of the document, or the
+// reveal steps of the page at the given index.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnimationSource {
+    Pages,
+    Steps(usize),
+}
+
+#[derive(Clone, Copy)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    // how long (in milliseconds) each frame is shown
+    pub frame_duration: u32,
+    // how long (in milliseconds) each frame takes to fade into the next one,
+    // 0 switch the frames without fading
+    pub fade_duration: u32,
+    // how many times the animation is played, 0 play it forever
+    pub loop_count: u32,
+    // frames wider than this are scaled down to it
+    pub max_width: Option<i32>,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        AnimationOptions {
+            format: AnimationFormat::Gif,
+            frame_duration: 1000,
+            fade_duration: 0,
+            loop_count: 0,
+            max_width: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct HtmlOptions {
+    // show every reveal step of the pages, the reader go through them with
+    // the keyboard
+    pub steps: bool,
+    // show the notes of the pages as captions below them
+    pub captions: bool,
+}
+
+impl Default for HtmlOptions {
+    fn default() -> Self {
+        HtmlOptions {
+            steps: true,
+            captions: true,
+        }
+    }
+}
+
+// a rendered frame and how long (in milliseconds) it's shown
+struct Frame {
+    surface: Surface,
+    duration: u32,
+}
+
+fn create_surface(width: i32, height: i32) -> Result<Surface, Error> {
+    match Surface::create(cairo::Format::ARgb32, width, height) {
+        Ok(val) => Ok(val),
+        Err(_) => Err(Error::from("Cairo: couldn't create surface")),
+    }
+}
+
+// draw the page at @index of @document in a new surface that have the page
+// size, with the shapes revealed up to @step if it's given.
+pub fn render_page(
+    document: &Document, index: usize, step: Option<u32>
+) -> Result<Surface, Error> {
+    let size = match document.get_pages().get(index) {
+        Some(page) => page.render_size(),
+        None => return Err(Error::from("There is no page to export")),
+    };
+
+    let surface = create_surface(size.width, size.height)?;
+    {
+        let cr = cairo::Context::new(&surface);
+        document.render_page(index, step, &cr);
+    }
+    Ok(surface)
+}
+
+// like render_page() but without the caption strip, the surface have the
+// page size.
+fn render_page_without_caption(
+    document: &Document, index: usize, step: Option<u32>
+) -> Result<Surface, Error> {
+    let page = match document.get_pages().get(index) {
+        Some(val) => val,
+        None => return Err(Error::from("There is no page to export")),
+    };
+
+    let size = page.get_size().clone();
+    let surface = create_surface(size.width, size.height)?;
+    {
+        let cr = cairo::Context::new(&surface);
+        page.render_without_caption(&cr, document.get_transparent(), step);
+    }
+    Ok(surface)
+}
+
+pub fn write_png<W: Write>(
+    surface: &Surface, output: &mut W
+) -> Result<(), Error> {
+    match surface.write_to_png(output) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Error::from("Cairo: couldn't encode surface as PNG")),
+    }
+}
+
+// write one PNG image for each reveal step of the page at @index into
+// @directory, each image show the shapes revealed up to its step. the images
+// are named after the page and their step so they sort in playback order.
+// return the paths of the written images.
+pub fn export_steps(
+    document: &Document, index: usize, directory: &Path
+) -> Result<Vec<PathBuf>, Error> {
+    let page = match document.get_pages().get(index) {
+        Some(val) => val,
+        None => return Err(Error::from("There is no page to export")),
+    };
+
+    let name = file_stem(page.get_name());
+    let mut paths = vec![];
+    for step in 0..page.last_step() + 1 {
+        let surface = render_page(document, index, Some(step))?;
+        let path = directory.join(format!("{}-{:02}.png", name, step));
+        write_png(&surface, &mut File::create(&path)?)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// return @name without the characters that can't be used in file names
+pub fn file_stem(name: &str) -> String {
+    let stem: String = name.trim()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c.is_control() { '-' } else { c })
+        .collect();
+    if stem.is_empty() {
+        String::from("page")
+    } else {
+        stem
+    }
+}
+
+// write the pages of @document, or the reveal steps of one page, as an
+// animated GIF or APNG image. the frames are drawn by Page::render() like
+// the other exports.
+pub fn export_animation(
+    document: &Document,
+    source: AnimationSource,
+    options: &AnimationOptions,
+    path: &Path
+) -> Result<(), Error> {
+    let frames = animation_frames(document, source, options)?;
+    let file = File::create(path)?;
+    match options.format {
+        AnimationFormat::Gif => write_gif(frames, options.loop_count, file),
+        AnimationFormat::Apng => write_apng(frames, options.loop_count, file),
+    }
+}
+
+fn animation_frames(
+    document: &Document,
+    source: AnimationSource,
+    options: &AnimationOptions
+) -> Result<Vec<Frame>, Error> {
+    let pages = document.get_pages();
+    let keys: Vec<(usize, Option<u32>)> = match source {
+        AnimationSource::Pages => {
+            (0..pages.len()).map(|index| (index, None)).collect()
+        },
+        AnimationSource::Steps(index) => match pages.get(index) {
+            Some(page) => {
+                (0..page.last_step() + 1).map(|step| (index, Some(step))).collect()
+            },
+            None => vec![],
+        },
+    };
+
+    // all the frames have the size of the largest page, smaller pages are
+    // centered in them
+    let sizes: Vec<Size<i32>> = keys
+        .iter()
+        .map(|&(index, _)| pages[index].render_size())
+        .collect();
+    let width = sizes.iter().map(|size| size.width).max().unwrap_or(0);
+    let height = sizes.iter().map(|size| size.height).max().unwrap_or(0);
+    if width <= 0 || height <= 0 {
+        return Err(Error::from("There is no page to export"));
+    }
+
+    let scale = match options.max_width {
+        Some(max_width) if max_width > 0 && max_width < width => {
+            max_width as f64 / width as f64
+        },
+        _ => 1.0,
+    };
+    let frame_width = ((width as f64 * scale).round() as i32).max(1);
+    let frame_height = ((height as f64 * scale).round() as i32).max(1);
+
+    let mut key_frames = vec![];
+    for (&(index, step), size) in keys.iter().zip(sizes.iter()) {
+        let surface = create_surface(frame_width, frame_height)?;
+        {
+            let cr = cairo::Context::new(&surface);
+            cr.scale(scale, scale);
+            cr.translate(
+                (width - size.width) as f64 / 2.0,
+                (height - size.height) as f64 / 2.0
+            );
+            document.render_page(index, step, &cr);
+        }
+        key_frames.push(surface);
+    }
+
+    let n_fade_frames = if options.fade_duration > 0 {
+        (options.fade_duration / FADE_FRAME_INTERVAL).max(1)
+    } else {
+        0
+    };
+    let mut fades = vec![];
+    for pair in key_frames.windows(2) {
+        let mut fade = vec![];
+        for i in 0..n_fade_frames {
+            let alpha = (i + 1) as f64 / (n_fade_frames + 1) as f64;
+            fade.push(Frame {
+                surface: cross_fade(&pair[0], &pair[1], alpha)?,
+                duration: options.fade_duration / n_fade_frames,
+            });
+        }
+        fades.push(fade);
+    }
+
+    let mut frames = vec![];
+    let mut fades = fades.into_iter();
+    for surface in key_frames.into_iter() {
+        frames.push(Frame {
+            surface: surface,
+            duration: options.frame_duration,
+        });
+        if let Some(fade) = fades.next() {
+            frames.extend(fade);
+        }
+    }
+    Ok(frames)
+}
+
+// blend @from into @to, @alpha is how much of @to is shown
+fn cross_fade(
+    from: &Surface, to: &Surface, alpha: f64
+) -> Result<Surface, Error> {
+    let surface = create_surface(from.get_width(), from.get_height())?;
+    {
+        // adding the two weighted frames keep the transparent areas right,
+        // drawing one over the other would not.
+        let cr = cairo::Context::new(&surface);
+        cr.set_operator(cairo::Operator::Add);
+        cr.set_source_surface(from, 0.0, 0.0);
+        cr.paint_with_alpha(1.0 - alpha);
+        cr.set_source_surface(to, 0.0, 0.0);
+        cr.paint_with_alpha(alpha);
+    }
+    Ok(surface)
+}
+
+fn write_gif<W: Write>(
+    frames: Vec<Frame>, loop_count: u32, output: W
+) -> Result<(), Error> {
+    let (width, height) = match frames.first() {
+        Some(frame) => (frame.surface.get_width(), frame.surface.get_height()),
+        None => return Err(Error::from("There is no page to export")),
+    };
+    if width > u16::max_value() as i32 || height > u16::max_value() as i32 {
+        return Err(Error::from("The page is too large for GIF images"));
+    }
+
+    let mut encoder =
+        gif::Encoder::new(output, width as u16, height as u16, &[])?;
+    // GIF count the repeats after the first play, and without the loop
+    // extension the image is played once
+    match loop_count {
+        0 => encoder.set(gif::Repeat::Infinite)?,
+        1 => {},
+        n => encoder.set(gif::Repeat::Finite(
+            (n - 1).min(u16::max_value() as u32) as u16
+        ))?,
+    };
+
+    for mut frame in frames.into_iter() {
+        let mut pixels = to_rgba(&mut frame.surface)?;
+        let mut gif_frame =
+            gif::Frame::from_rgba(width as u16, height as u16, &mut pixels);
+        // GIF delays are in centiseconds, and most viewers slow down delays
+        // shorter than 2
+        gif_frame.delay = (frame.duration / 10)
+            .max(2)
+            .min(u16::max_value() as u32) as u16;
+        gif_frame.dispose = gif::DisposalMethod::Background;
+        encoder.write_frame(&gif_frame)?;
+    }
+    Ok(())
+}
+
+// convert the premultiplied native endian ARGB pixels of @surface to RGBA
+fn to_rgba(surface: &mut Surface) -> Result<Vec<u8>, Error> {
+    surface.flush();
+    let width = surface.get_width() as usize;
+    let height = surface.get_height() as usize;
+    let stride = surface.get_stride() as usize;
+    let data = match surface.get_data() {
+        Ok(val) => val,
+        Err(_) => return Err(Error::from("Cairo: couldn't read surface data")),
+    };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * stride + x * 4;
+            let pixel = if cfg!(target_endian = "little") {
+                (data[i + 3] as u32) << 24 | (data[i + 2] as u32) << 16 |
+                (data[i + 1] as u32) << 8 | data[i] as u32
+            } else {
+                (data[i] as u32) << 24 | (data[i + 1] as u32) << 16 |
+                (data[i + 2] as u32) << 8 | data[i + 3] as u32
+            };
+
+            let alpha = pixel >> 24;
+            let unpremultiply = |color: u32| if alpha == 0 {
+                0
+            } else {
+                ((color * 255 + alpha / 2) / alpha).min(255) as u8
+            };
+            rgba.push(unpremultiply((pixel >> 16) & 0xFF));
+            rgba.push(unpremultiply((pixel >> 8) & 0xFF));
+            rgba.push(unpremultiply(pixel & 0xFF));
+            rgba.push(alpha as u8);
+        }
+    }
+    Ok(rgba)
+}
+
+// APNG is a PNG image with extra chunks that hold the other frames, so we
+// let cairo encode each frame as PNG and then move its image data into the
+// animation.
+fn write_apng<W: Write>(
+    frames: Vec<Frame>, loop_count: u32, mut output: W
+) -> Result<(), Error> {
+    if frames.is_empty() {
+        return Err(Error::from("There is no page to export"));
+    }
+
+    output.write_all(PNG_SIGNATURE)?;
+    let mut sequence = 0;
+    for (index, frame) in frames.iter().enumerate() {
+        let mut png = vec![];
+        if let Err(_) = frame.surface.write_to_png(&mut png) {
+            return Err(Error::from("Cairo: couldn't encode surface as PNG"));
+        }
+        let chunks = png_chunks(&png)?;
+
+        // the first frame is also the image shown by the viewers that don't
+        // support animation
+        if index == 0 {
+            for &(kind, data) in chunks.iter() {
+                if &kind == b"IHDR" {
+                    write_chunk(&mut output, b"IHDR", data)?;
+                }
+            }
+            let mut actl = vec![];
+            actl.extend_from_slice(&be_bytes(frames.len() as u32));
+            actl.extend_from_slice(&be_bytes(loop_count));
+            write_chunk(&mut output, b"acTL", &actl)?;
+        }
+
+        let mut fctl = vec![];
+        fctl.extend_from_slice(&be_bytes(sequence));
+        fctl.extend_from_slice(&be_bytes(frame.surface.get_width() as u32));
+        fctl.extend_from_slice(&be_bytes(frame.surface.get_height() as u32));
+        fctl.extend_from_slice(&be_bytes(0)); // x offset
+        fctl.extend_from_slice(&be_bytes(0)); // y offset
+        // the delay is a fraction, frame.duration / 1000 seconds
+        let delay = frame.duration.min(u16::max_value() as u32);
+        fctl.extend_from_slice(&be_bytes(delay << 16 | 1000));
+        fctl.push(0); // dispose op: none
+        fctl.push(0); // blend op: source
+        write_chunk(&mut output, b"fcTL", &fctl)?;
+        sequence += 1;
+
+        for &(kind, data) in chunks.iter() {
+            if &kind != b"IDAT" {
+                continue;
+            }
+            if index == 0 {
+                write_chunk(&mut output, b"IDAT", data)?;
+            } else {
+                let mut fdat = be_bytes(sequence).to_vec();
+                fdat.extend_from_slice(data);
+                write_chunk(&mut output, b"fdAT", &fdat)?;
+                sequence += 1;
+            }
+        }
+    }
+    write_chunk(&mut output, b"IEND", &[])?;
+    Ok(())
+}
+
+// split @png into its chunks, each chunk is its type and data
+fn png_chunks(png: &[u8]) -> Result<Vec<([u8; 4], &[u8])>, Error> {
+    if png.len() < PNG_SIGNATURE.len() ||
+       &png[..PNG_SIGNATURE.len()] != PNG_SIGNATURE {
+        return Err(Error::from("Invalid PNG data"));
+    }
+
+    let mut chunks = vec![];
+    let mut position = PNG_SIGNATURE.len();
+    // every chunk have 4 bytes for the length, 4 for the type and 4 for the
+    // CRC around its data
+    while position + 12 <= png.len() {
+        let length = (png[position] as usize) << 24 |
+                     (png[position + 1] as usize) << 16 |
+                     (png[position + 2] as usize) << 8 |
+                     png[position + 3] as usize;
+        let start = position + 8;
+        let end = start + length;
+        if end + 4 > png.len() {
+            return Err(Error::from("Invalid PNG data"));
+        }
+
+        let mut kind = [0; 4];
+        kind.copy_from_slice(&png[position + 4..start]);
+        chunks.push((kind, &png[start..end]));
+        position = end + 4;
+    }
+    Ok(chunks)
+}
+
+fn write_chunk<W: Write>(
+    output: &mut W, kind: &[u8; 4], data: &[u8]
+) -> Result<(), Error> {
+    output.write_all(&be_bytes(data.len() as u32))?;
+    output.write_all(kind)?;
+    output.write_all(data)?;
+    output.write_all(&be_bytes(crc32(kind, data)))?;
+    Ok(())
+}
+
+fn be_bytes(value: u32) -> [u8; 4] {
+    [(value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8]
+}
+
+// the CRC of PNG chunks, it's computed over the chunk type and data
+fn crc32(kind: &[u8], data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for byte in kind.iter().chain(data.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// write the pages of @document to a single HTML file that doesn't need
+// anything else, the pages are embedded as PNG images under their names.
+pub fn export_html(
+    document: &Document, title: &str, options: &HtmlOptions, path: &Path
+) -> Result<(), Error> {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape_html(title)));
+    html.push_str(HTML_STYLE);
+    html.push_str("</head>\n<body>\n");
+    html.push_str(&format!("<h1>{}</h1>\n", escape_html(title)));
+
+    for (index, page) in document.get_pages().iter().enumerate() {
+        let name = escape_html(page.get_name());
+        html.push_str(&format!("<section id=\"page-{}\">\n", index + 1));
+        html.push_str(&format!("<h2>{}</h2>\n<figure>\n", name));
+
+        let steps: Vec<Option<u32>> = if options.steps {
+            (0..page.last_step() + 1).map(|step| Some(step)).collect()
+        } else {
+            vec![None]
+        };
+        // the notes are written in the figure caption, so they are not
+        // drawn in the images
+        for (i, &step) in steps.iter().enumerate() {
+            let surface = render_page_without_caption(document, index, step)?;
+            let mut png = vec![];
+            write_png(&surface, &mut png)?;
+
+            let class = if i + 1 == steps.len() { " class=\"last\"" } else { "" };
+            let alt = if steps.len() > 1 {
+                format!("{} ({}/{})", name, i + 1, steps.len())
+            } else {
+                name.clone()
+            };
+            html.push_str(&format!(
+                "<img{} alt=\"{}\" src=\"data:image/png;base64,{}\">\n",
+                class, alt, base64::encode(&png)
+            ));
+        }
+
+        let notes = page.get_notes().trim();
+        if options.captions && !notes.is_empty() {
+            html.push_str(
+                &format!("<figcaption>{}</figcaption>\n", notes_to_html(notes))
+            );
+        }
+        html.push_str("</figure>\n</section>\n");
+    }
+
+    html.push_str(HTML_SCRIPT);
+    html.push_str("</body>\n</html>\n");
+
+    let mut file = File::create(path)?;
+    file.write_all(html.as_bytes())?;
+    Ok(())
+}
+
+// write the pages of @document to a Markdown file at @path that show each
+// page as a section with its image and notes. the images are written to an
+// "images" folder next to it, named after the page name so moving the pages
+// doesn't rename them. the images written by the previous export of the same
+// file that are not used anymore are removed.
+pub fn export_markdown(
+    document: &Document, title: &str, path: &Path
+) -> Result<(), Error> {
+    let directory = match path.parent() {
+        Some(val) => val.join("images"),
+        None => PathBuf::from("images"),
+    };
+    fs::create_dir_all(&directory)?;
+
+    let mut image_names: Vec<String> = vec![];
+    let mut markdown = format!("# {}\n", title.trim());
+    for (index, page) in document.get_pages().iter().enumerate() {
+        // pages with the same name are told apart by a counter, in the order
+        // they appear
+        let base_name = slug(page.get_name());
+        let mut image_name = format!("{}.png", base_name);
+        let mut count = 1;
+        while image_names.contains(&image_name) {
+            count += 1;
+            image_name = format!("{}-{}.png", base_name, count);
+        }
+        // the notes follow the image as text
+        let surface = render_page_without_caption(document, index, None)?;
+        write_png(&surface, &mut File::create(directory.join(&image_name))?)?;
+
+        let name = page.get_name().trim();
+        markdown.push_str(&format!("\n## {}\n\n", name));
+        markdown.push_str(&format!(
+            "![{}](images/{})\n",
+            name.replace('[', "\\[").replace(']', "\\]"),
+            image_name
+        ));
+
+        let notes = page.get_notes().trim();
+        if !notes.is_empty() {
+            markdown.push_str(&format!("\n{}\n", notes));
+        }
+        image_names.push(image_name);
+    }
+
+    let mut file = File::create(path)?;
+    file.write_all(markdown.as_bytes())?;
+
+    // only the files listed by the previous export are removed, the other
+    // files in the folder may belong to the user or to other documents
+    let images_list = directory.join(images_list_name(path));
+    for old_name in read_images_list(&images_list) {
+        if !image_names.contains(&old_name) {
+            let _ = fs::remove_file(directory.join(&old_name));
+        }
+    }
+    let mut list = File::create(&images_list)?;
+    list.write_all(image_names.join("\n").as_bytes())?;
+    Ok(())
+}
+
+// the hidden file in the images folder that list the images written for the
+// Markdown file at @path
+fn images_list_name(path: &Path) -> String {
+    let file_name = match path.file_name() {
+        Some(val) => val.to_string_lossy().into_owned(),
+        None => String::from("document.md"),
+    };
+    format!(".{}.images", file_name)
+}
+
+fn read_images_list(images_list: &Path) -> Vec<String> {
+    let mut content = String::new();
+    if let Ok(mut file) = File::open(images_list) {
+        if file.read_to_string(&mut content).is_err() {
+            return vec![];
+        }
+    }
+
+    // names that could point outside the images folder are ignored, the list
+    // is an ordinary file that could have been edited
+    content.lines()
+           .map(|line| line.trim())
+           .filter(|name| {
+               name.ends_with(".png") && !name.starts_with('.') &&
+                   !name.contains('/') && !name.contains('\\')
+           })
+           .map(|name| String::from(name))
+           .collect()
+}
+
+// return @name in lower case with dashes instead of spaces and symbols, so
+// it can be used in file names and links
+pub fn slug(name: &str) -> String {
+    let mut result = String::new();
+    for c in name.trim().chars().flat_map(|c| c.to_lowercase()) {
+        if c.is_alphanumeric() {
+            result.push(c);
+        } else if !result.is_empty() && !result.ends_with('-') {
+            result.push('-');
+        }
+    }
+    while result.ends_with('-') {
+        result.pop();
+    }
+
+    if result.is_empty() {
+        String::from("page")
+    } else {
+        result
+    }
+}
+
+// the notes as HTML with their bold and italic text, the paragraphs are
+// kept on their own lines since the caption is pre-wrapped
+fn notes_to_html(notes: &str) -> String {
+    notes.lines()
+         .map(|paragraph| {
+             notes::parse(paragraph)
+                 .iter()
+                 .map(|span| {
+                     let mut text = escape_html(span.text.as_str());
+                     if span.italic {
+                         text = format!("<em>{}</em>", text);
+                     }
+                     if span.bold {
+                         text = format!("<strong>{}</strong>", text);
+                     }
+                     text
+                 })
+                 .collect::<Vec<String>>()
+                 .concat()
+         })
+         .collect::<Vec<String>>()
+         .join("\n")
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+// without javascript only the last step of each page is shown
+const HTML_STYLE: &'static str = "<style>
+body { max-width: 60em; margin: 0 auto; padding: 1em;
+       font-family: sans-serif; color: #2E3436; }
+section { margin-bottom: 3em; }
+figure { margin: 0; }
+figure img { display: none; max-width: 100%; height: auto; }
+figure img.last { display: block; }
+.js figure img.last { display: none; }
+.js figure img.current { display: block; }
+.js figure { cursor: pointer; }
+figcaption { margin-top: 0.5em; white-space: pre-wrap; }
+</style>
+";
+
+// the arrow keys, space and clicks reveal the steps of the pages one by one
+const HTML_SCRIPT: &'static str = "<script>
+(function () {
+    document.body.classList.add(\"js\");
+    var sections = document.querySelectorAll(\"section\");
+    var current = 0;
+
+    function images(index) {
+        return sections[index].querySelectorAll(\"figure img\");
+    }
+
+    function step(index) {
+        var list = images(index);
+        for (var i = 0; i < list.length; i++) {
+            if (list[i].classList.contains(\"current\")) {
+                return i;
+            }
+        }
+        return 0;
+    }
+
+    function show(index, stepIndex) {
+        var list = images(index);
+        stepIndex = Math.max(0, Math.min(stepIndex, list.length - 1));
+        for (var i = 0; i < list.length; i++) {
+            list[i].classList.toggle(\"current\", i === stepIndex);
+        }
+        current = index;
+    }
+
+    function next() {
+        if (step(current) + 1 < images(current).length) {
+            show(current, step(current) + 1);
+        } else if (current + 1 < sections.length) {
+            show(current + 1, 0);
+            sections[current].scrollIntoView();
+        }
+    }
+
+    function previous() {
+        if (step(current) > 0) {
+            show(current, step(current) - 1);
+        } else if (current > 0) {
+            show(current - 1, images(current - 1).length - 1);
+            sections[current].scrollIntoView();
+        }
+    }
+
+    Array.prototype.forEach.call(sections, function (section, index) {
+        show(index, 0);
+        section.querySelector(\"figure\").addEventListener(\"click\", function () {
+            current = index;
+            next();
+        });
+    });
+    current = 0;
+
+    document.addEventListener(\"keydown\", function (event) {
+        if (event.altKey || event.ctrlKey || event.metaKey) {
+            return;
+        }
+        switch (event.key) {
+        case \"ArrowRight\":
+        case \" \":
+            next();
+            break;
+        case \"ArrowLeft\":
+        case \"Backspace\":
+            previous();
+            break;
+        default:
+            return;
+        }
+        event.preventDefault();
+    });
+})();
+</script>
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_known_vectors() {
+        assert_eq!(crc32(b"", b""), 0);
+        assert_eq!(crc32(b"1234", b"56789"), 0xCBF4_3926);
+        // every PNG ends with this chunk
+        assert_eq!(crc32(b"IEND", b""), 0xAE42_6082);
+    }
+
+    #[test]
+    fn write_iend_chunk() {
+        let mut png = vec![];
+        write_chunk(&mut png, b"IEND", b"").unwrap();
+        assert_eq!(png, vec![0, 0, 0, 0, 73, 69, 78, 68, 0xAE, 0x42, 0x60, 0x82]);
+    }
+
+    #[test]
+    fn png_chunks_round_trip() {
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"tEXt", b"key\0value").unwrap();
+        write_chunk(&mut png, b"IEND", b"").unwrap();
+
+        let chunks = png_chunks(&png).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(&chunks[0].0, b"tEXt");
+        assert_eq!(chunks[0].1, b"key\0value");
+        assert_eq!(&chunks[1].0, b"IEND");
+        assert!(chunks[1].1.is_empty());
+    }
+
+    #[test]
+    fn png_chunks_invalid_data() {
+        assert!(png_chunks(b"not a png").is_err());
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"tEXt", b"key\0value").unwrap();
+        png.truncate(png.len() - 6);
+        assert!(png_chunks(&png).is_err());
+    }
+
+    #[test]
+    fn apng_frames() {
+        let frames = (0..2).map(|_| Frame {
+            surface: create_surface(4, 3).unwrap(),
+            duration: 500,
+        }).collect();
+        let mut apng = vec![];
+        write_apng(frames, 0, &mut apng).unwrap();
+
+        // the CRC of each chunk follows its data
+        let mut position = PNG_SIGNATURE.len();
+        for (kind, data) in png_chunks(&apng).unwrap() {
+            position += 8 + data.len();
+            let crc = &apng[position..position + 4];
+            assert_eq!(crc, &be_bytes(crc32(&kind, data)));
+            position += 4;
+        }
+        assert_eq!(position, apng.len());
+
+        let chunks = png_chunks(&apng).unwrap();
+        let kinds: Vec<&[u8]> = chunks.iter().map(|chunk| &chunk.0[..]).collect();
+        assert_eq!(kinds[0], b"IHDR");
+        assert_eq!(kinds[1], b"acTL");
+        assert_eq!(kinds[2], b"fcTL");
+        assert_eq!(*kinds.last().unwrap(), b"IEND");
+        assert_eq!(kinds.iter().filter(|kind| **kind == &b"fcTL"[..]).count(), 2);
+
+        // two frames that loop forever
+        assert_eq!(chunks[1].1, &[0u8, 0, 0, 2, 0, 0, 0, 0][..]);
+        // the sequence numbers of the second frame follow the first fcTL
+        let fctl = chunks.iter().filter(|chunk| &chunk.0 == b"fcTL").nth(1).unwrap();
+        assert_eq!(&fctl.1[..4], &be_bytes(1));
+        let fdat = chunks.iter().find(|chunk| &chunk.0 == b"fdAT").unwrap();
+        assert_eq!(&fdat.1[..4], &be_bytes(2));
+        // 500/1000 seconds
+        assert_eq!(&fctl.1[20..24], &be_bytes(500 << 16 | 1000));
+    }
+
+    #[test]
+    fn escape_html_quotes_and_ampersands() {
+        assert_eq!(
+            escape_html("Tom & \"Jerry's\" <b>"),
+            "Tom &amp; &quot;Jerry&#39;s&quot; &lt;b&gt;"
+        );
+        assert_eq!(escape_html("&amp;"), "&amp;amp;");
+    }
+
+    #[test]
+    fn slug_names() {
+        assert_eq!(slug("  Hello, World!  "), "hello-world");
+        assert_eq!(slug("Élan Vital"), "élan-vital");
+        assert_eq!(slug("Große Straße"), "große-straße");
+        assert_eq!(slug("مرحبا بالعالم"), "مرحبا-بالعالم");
+        assert_eq!(slug("!!!"), "page");
+    }
+
+    #[test]
+    fn notes_to_html_styles() {
+        assert_eq!(
+            notes_to_html("**a** & *b*\nc"),
+            "<strong>a</strong> &amp; <em>b</em>\nc"
+        );
+    }
+}
@@ -19,21 +19,37 @@
 
 pub mod draw_objects;
 pub mod context;
+pub mod snap;
+pub mod notes;
+pub mod data;
+pub mod recovery;
+pub mod export;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::mem;
 
-use gtk;
 use gdk::{EventMotion, EventButton, EventKey};
 use cairo;
+use serde_json;
 
-use self::draw_objects::Page;
-use common::types::Size;
+use self::draw_objects::{Page, OptionsWidget};
+use self::data::DocumentData;
+use common::types::{Size, Vector};
+use error::Error;
+
+// the extension of the file a document is written to before it replace the
+// document file
+const SAVE_TEMP_EXTENSION: &'static str = "teha-saving";
 
 pub struct Document {
     pages: Vec<Page>,
     active_page_index: usize,
     path: PathBuf,
     transparent: bool,
+    modified: bool,
+    scroll: Vector,
 }
 
 impl Document {
@@ -50,6 +66,8 @@ impl Document {
             active_page_index: 0,
             path: path,
             transparent: transparent,
+            modified: false,
+            scroll: Vector::new(0.0, 0.0),
         }
     }
 
@@ -81,6 +99,72 @@ impl Document {
         self.active_page_index = active_page_index;
     }
 
+    // add a new page after the active page and make it the active one, the
+    // new page take the size of the active page.
+    pub fn add_page(&mut self) -> usize {
+        let mut page = Page::new();
+        let index = if self.pages.is_empty() {
+            0
+        } else {
+            page.set_size(self.get_active_page().get_size().clone());
+            self.active_page_index + 1
+        };
+        self.pages.insert(index, page);
+        self.active_page_index = index;
+        self.modified = true;
+        index
+    }
+
+    // insert a copy of the page at @index after it and make it the active
+    // page.
+    pub fn duplicate_page(&mut self, index: usize) -> Result<usize, Error> {
+        if index >= self.pages.len() {
+            return Err(Error::from("Page index is out of bounds"));
+        }
+
+        let page = self.pages[index].duplicate()?;
+        self.pages.insert(index + 1, page);
+        self.active_page_index = index + 1;
+        self.modified = true;
+        Ok(index + 1)
+    }
+
+    // remove the page at @index, the document always keep one page at least.
+    pub fn remove_page(&mut self, index: usize) -> bool {
+        if self.pages.len() < 2 || index >= self.pages.len() {
+            return false;
+        }
+
+        self.pages.remove(index);
+        if self.active_page_index >= self.pages.len() ||
+           self.active_page_index > index {
+            self.active_page_index -= 1;
+        }
+        self.modified = true;
+        true
+    }
+
+    // move the page at @from to @to, the active page stay the same page.
+    pub fn move_page(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.pages.len() || to >= self.pages.len() || from == to {
+            return false;
+        }
+
+        let page = self.pages.remove(from);
+        self.pages.insert(to, page);
+
+        let active = self.active_page_index;
+        if active == from {
+            self.active_page_index = to;
+        } else if from < active && active <= to {
+            self.active_page_index -= 1;
+        } else if to <= active && active < from {
+            self.active_page_index += 1;
+        }
+        self.modified = true;
+        true
+    }
+
     pub fn get_path(&self) -> &PathBuf {
         &self.path
     }
@@ -101,36 +185,150 @@ impl Document {
         self.transparent = transparent;
     }
 
-    // pub fn save(&self) -> io::Result<()> {
-        // TODO write save method
-    //     Ok(())
-    // }
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn set_modified(&mut self, modified: bool) {
+        self.modified = modified;
+    }
+
+    // the scroll position of the editor when this document was last shown
+    pub fn get_scroll(&self) -> &Vector {
+        &self.scroll
+    }
+
+    pub fn set_scroll(&mut self, scroll: Vector) {
+        self.scroll = scroll;
+    }
+
+    pub fn open(path: PathBuf) -> Result<Self, Error> {
+        let file = File::open(&path)?;
+        let data: DocumentData = serde_json::from_reader(file)?;
+        Document::from_data(data, path)
+    }
+
+    pub fn from_data(data: DocumentData, path: PathBuf) -> Result<Self, Error> {
+        let mut pages = vec![];
+        for page in data.pages.into_iter() {
+            pages.push(Page::from_data(page)?);
+        }
+        if pages.is_empty() {
+            return Err(Error::from("Document doesn't have any page"));
+        }
+
+        let active_page_index = if data.active_page_index < pages.len() {
+            data.active_page_index
+        } else {
+            0
+        };
+
+        Ok(Document {
+            pages: pages,
+            active_page_index: active_page_index,
+            path: path,
+            transparent: data.transparent,
+            modified: false,
+            scroll: Vector::new(0.0, 0.0),
+        })
+    }
+
+    pub fn to_data(&self) -> DocumentData {
+        DocumentData {
+            transparent: self.transparent,
+            active_page_index: self.active_page_index,
+            pages: self.pages.iter().map(|page| page.to_data()).collect(),
+        }
+    }
+
+    // the document is written to a temporary file next to its file, which is
+    // then renamed over it, so a failed save never leave a truncated file.
+    pub fn save(&mut self) -> Result<(), Error> {
+        let temp_path = self.path.with_extension(SAVE_TEMP_EXTENSION);
+        let result = self.write_to(&temp_path)
+                         .and_then(|_| Ok(fs::rename(&temp_path, &self.path)?));
+        if let Err(why) = result {
+            let _ = fs::remove_file(&temp_path);
+            return Err(why);
+        }
+
+        self.modified = false;
+        Ok(())
+    }
+
+    fn write_to(&self, path: &Path) -> Result<(), Error> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &self.to_data())?;
+        writer.flush()?;
+        Ok(())
+    }
 
-    // pub fn save_as(&mut self, path: PathBuf) -> io::Result<()> {
-        // TODO write save_as method
-    //     Ok(())
-    // }
+    // the document keep its old file if it couldn't be saved to @path
+    pub fn save_as(&mut self, path: PathBuf) -> Result<(), Error> {
+        let old_path = mem::replace(&mut self.path, path);
+        let result = self.save();
+        if result.is_err() {
+            self.path = old_path;
+        }
+        result
+    }
 
+    // draw the active page as shown in the editor
     pub fn draw(&self, cr: &cairo::Context) {
-        self.pages[self.active_page_index].draw(cr);
+        let page = &self.pages[self.active_page_index];
+        page.draw(cr);
+        page.draw_guides(cr);
+    }
+
+    // draw the page at @index as it's exported, with the shapes revealed up
+    // to @step if it's given.
+    pub fn render_page(
+        &self, index: usize, step: Option<u32>, cr: &cairo::Context
+    ) {
+        if let Some(page) = self.pages.get(index) {
+            page.render(cr, self.transparent, step);
+        }
+    }
+
+    // the events mark the document as modified only if they changed the
+    // active page, a click that only select a shape doesn't.
+    fn take_page_changes(&mut self) {
+        if self.pages[self.active_page_index].take_changed() {
+            self.modified = true;
+        }
+    }
+
+    // the options of the selected shapes in the active page changed
+    pub fn options_changed(&mut self) {
+        self.pages[self.active_page_index].options_changed();
+        self.modified = true;
     }
 
     pub fn motion_notify(&mut self, event: &EventMotion) -> bool {
-        self.pages[self.active_page_index].motion_notify(event)
+        let result = self.pages[self.active_page_index].motion_notify(event);
+        self.take_page_changes();
+        result
     }
 
     pub fn button_press(
-        &mut self, event: &EventButton, options_widget: &gtk::Notebook
+        &mut self, event: &EventButton, options_widget: &OptionsWidget
     ) -> bool {
-        self.pages[self.active_page_index].button_press(event, options_widget)
+        let result = self.pages[self.active_page_index]
+                         .button_press(event, options_widget);
+        self.take_page_changes();
+        result
     }
 
     pub fn button_release(&mut self, event: &EventButton) -> bool {
-        self.pages[self.active_page_index].button_release(event)
+        let result = self.pages[self.active_page_index].button_release(event);
+        self.take_page_changes();
+        result
     }
 
     pub fn key_press(&mut self, event: &EventKey) -> bool {
-        self.pages[self.active_page_index].key_press(event)
+        let result = self.pages[self.active_page_index].key_press(event);
+        self.take_page_changes();
+        result
     }
 
     pub fn key_release(&mut self, event: &EventKey) -> bool {
@@ -165,6 +363,106 @@ impl Default for Document {
             active_page_index: 0,
             path: PathBuf::new(),
             transparent: true,
+            modified: false,
+            scroll: Vector::new(0.0, 0.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+    use std::process;
+
+    use common::types::Size;
+    use super::*;
+
+    fn document(names: &[&str]) -> Document {
+        let mut document =
+            Document::new(names.len(), PathBuf::new(), Size::new(640, 480), false);
+        for (page, name) in document.get_mut_pages().iter_mut().zip(names.iter()) {
+            page.set_name(String::from(*name));
         }
+        document
+    }
+
+    fn names(document: &Document) -> Vec<String> {
+        document.get_pages().iter().map(|page| page.get_name().clone()).collect()
+    }
+
+    #[test]
+    fn save_replaces_the_file() {
+        let path = env::temp_dir()
+            .join(format!("teha-save-test-{}.teha", process::id()));
+        File::create(&path).unwrap().write_all(b"old content").unwrap();
+
+        let mut document = document(&["a", "b"]);
+        document.set_path(path.clone());
+        document.set_modified(true);
+        document.save().unwrap();
+        assert!(!document.is_modified());
+        assert!(!path.with_extension(SAVE_TEMP_EXTENSION).exists());
+
+        let opened = Document::open(path.clone()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(names(&opened), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn add_page_after_the_active_page() {
+        let mut document = document(&["a", "b"]);
+        assert_eq!(document.add_page(), 1);
+        assert_eq!(document.get_active_page_index(), 1);
+        assert_eq!(document.get_pages().len(), 3);
+        assert_eq!(names(&document)[0], "a");
+        assert_eq!(names(&document)[2], "b");
+        assert_eq!(document.get_active_page().get_size(), &Size::new(640, 480));
+    }
+
+    #[test]
+    fn duplicate_page() {
+        let mut document = document(&["a", "b"]);
+        assert_eq!(document.duplicate_page(0).unwrap(), 1);
+        assert_eq!(names(&document), vec!["a", "a", "b"]);
+        assert_eq!(document.get_active_page_index(), 1);
+        assert!(document.duplicate_page(3).is_err());
+    }
+
+    #[test]
+    fn remove_page() {
+        let mut document = document(&["a", "b", "c"]);
+        document.set_active_page_index(2);
+        assert!(document.remove_page(0));
+        assert_eq!(names(&document), vec!["b", "c"]);
+        // the active page is still "c"
+        assert_eq!(document.get_active_page_index(), 1);
+
+        assert!(document.remove_page(1));
+        assert_eq!(document.get_active_page_index(), 0);
+        // the last page is kept
+        assert!(!document.remove_page(0));
+        assert!(!document.remove_page(5));
+        assert_eq!(names(&document), vec!["b"]);
+    }
+
+    #[test]
+    fn move_page_keeps_the_active_page() {
+        let mut document = document(&["a", "b", "c", "d"]);
+        document.set_active_page_index(1);
+        assert!(document.move_page(0, 2));
+        assert_eq!(names(&document), vec!["b", "c", "a", "d"]);
+        assert_eq!(document.get_active_page_index(), 0);
+
+        assert!(document.move_page(3, 0));
+        assert_eq!(names(&document), vec!["d", "b", "c", "a"]);
+        assert_eq!(document.get_active_page_index(), 1);
+
+        assert!(document.move_page(1, 3));
+        assert_eq!(names(&document), vec!["d", "c", "a", "b"]);
+        assert_eq!(document.get_active_page_index(), 3);
+
+        assert!(!document.move_page(1, 1));
+        assert!(!document.move_page(0, 4));
     }
 }
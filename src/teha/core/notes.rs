@@ -0,0 +1,130 @@
+//
+// notes.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+// The page notes are plain text with a small part of the markdown syntax:
+// text between `**` is bold and text between `*` is italic. a marker that
+// is never closed is kept as it is.
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+}
+
+impl Span {
+    pub fn new(text: &str, bold: bool, italic: bool) -> Self {
+        Self {
+            text: String::from(text),
+            bold: bold,
+            italic: italic,
+        }
+    }
+}
+
+// split one paragraph of the notes into spans of the same style
+pub fn parse(paragraph: &str) -> Vec<Span> {
+    let mut spans = vec![];
+    let mut text = String::new();
+    let mut bold = false;
+    let mut italic = false;
+    let mut rest = paragraph;
+
+    while let Some(c) = rest.chars().next() {
+        let marker = if rest.starts_with("**") { "**" } else { "*" };
+        if c == '*' {
+            let after = &rest[marker.len()..];
+            let active = if marker == "**" { bold } else { italic };
+            if active || opens(after, marker) {
+                if !text.is_empty() {
+                    spans.push(Span::new(text.as_str(), bold, italic));
+                    text.clear();
+                }
+                if marker == "**" {
+                    bold = !bold;
+                } else {
+                    italic = !italic;
+                }
+                rest = after;
+                continue;
+            }
+        }
+        text.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    if !text.is_empty() {
+        spans.push(Span::new(text.as_str(), bold, italic));
+    }
+    spans
+}
+
+// whether @marker starts a styled text, that is if it's followed by a word
+// and closed later in @after
+fn opens(after: &str, marker: &str) -> bool {
+    if after.is_empty() || after.starts_with(char::is_whitespace) {
+        return false;
+    }
+    if marker == "**" {
+        after.contains("**")
+    } else {
+        after.replace("**", "").contains('*')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(parse("just text"), vec![Span::new("just text", false, false)]);
+    }
+
+    #[test]
+    fn bold_and_italic() {
+        assert_eq!(
+            parse("a **bold** and *italic* word"),
+            vec![
+                Span::new("a ", false, false),
+                Span::new("bold", true, false),
+                Span::new(" and ", false, false),
+                Span::new("italic", false, true),
+                Span::new(" word", false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(
+            parse("*a **b** c*"),
+            vec![
+                Span::new("a ", false, true),
+                Span::new("b", true, true),
+                Span::new(" c", false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_markers_are_kept() {
+        assert_eq!(parse("2 * 3 **x"), vec![Span::new("2 * 3 **x", false, false)]);
+        assert_eq!(parse("*a"), vec![Span::new("*a", false, false)]);
+    }
+}
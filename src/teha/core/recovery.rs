@@ -0,0 +1,240 @@
+//
+// recovery.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+// Recovery files are written by the autosave to the recovery directory, each
+// running instance prefix its files with its process id, so files whose owner
+// is no longer running are leftovers from an instance that didn't quit
+// cleanly.
+
+use std::fs::{self, File};
+#[cfg(unix)]
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use glib;
+#[cfg(unix)]
+use libc;
+use serde_json;
+
+use error::Error;
+use super::Document;
+use super::data::RecoveryData;
+
+pub fn recovery_dir() -> Option<PathBuf> {
+    glib::get_user_data_dir().map(|dir| dir.join("teacher-hand").join("recovery"))
+}
+
+const TEMP_EXTENSION: &'static str = "teha-tmp";
+
+fn session_prefix() -> String {
+    format!("{}-", process::id())
+}
+
+// write the modified documents to the recovery directory, replacing the
+// files written by the last call. each file is written under a temporary name
+// and then renamed over the old one, so a crash in the middle of the save
+// never leave us without a recovery file.
+pub fn save_documents(documents: &[Document]) -> Result<(), Error> {
+    let dir = match recovery_dir() {
+        Some(val) => val,
+        None => return Err(Error::from("Couldn't find user data directory")),
+    };
+    fs::create_dir_all(&dir)?;
+
+    let mut written = vec![];
+    for (index, document) in documents.iter().enumerate() {
+        if !document.is_modified() {
+            continue;
+        }
+
+        let data = RecoveryData {
+            path: document.get_path().clone(),
+            document: document.to_data(),
+        };
+        let path = dir.join(format!("{}{}.teha", session_prefix(), index));
+        let temp_path = path.with_extension(TEMP_EXTENSION);
+        {
+            let file = File::create(&temp_path)?;
+            serde_json::to_writer(file, &data)?;
+        }
+        fs::rename(&temp_path, &path)?;
+        written.push(path);
+    }
+
+    // remove the files of the documents that got saved or closed
+    for path in list_files()? {
+        if is_session_file(&path) && !written.contains(&path) {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+// remove the files written by this instance
+pub fn remove_session_files() -> Result<(), Error> {
+    for path in list_files()? {
+        if is_session_file(&path) {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+// return the recovery files left by instances that are no longer running,
+// files owned by a running instance are still in use by that instance.
+pub fn find_leftovers() -> Result<Vec<PathBuf>, Error> {
+    let mut leftovers = vec![];
+    for path in list_files()? {
+        match owner_pid(&path) {
+            Some(pid) if pid == process::id() || is_running(pid) => continue,
+            _ => {},
+        }
+
+        // a temporary file means its owner died in the middle of a save, the
+        // file it was going to replace is still there.
+        if path.extension().map_or(false, |ext| ext == TEMP_EXTENSION) {
+            let _ = fs::remove_file(&path);
+        } else {
+            leftovers.push(path);
+        }
+    }
+    Ok(leftovers)
+}
+
+// load the document stored in the recovery file at @path, the document is
+// marked as modified since its changes are not saved in its own file.
+pub fn restore(path: &Path) -> Result<Document, Error> {
+    let file = File::open(path)?;
+    let data: RecoveryData = serde_json::from_reader(file)?;
+    let mut document = Document::from_data(data.document, data.path)?;
+    document.set_modified(true);
+    Ok(document)
+}
+
+pub fn remove(path: &Path) -> Result<(), Error> {
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+fn is_session_file(path: &Path) -> bool {
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name.starts_with(session_prefix().as_str()),
+        None => false,
+    }
+}
+
+// the process id is the part of the file name before the first '-'
+fn owner_pid(path: &Path) -> Option<u32> {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.split('-').next())
+        .and_then(|pid| pid.parse().ok())
+}
+
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    // 0 and the negative ids stand for process groups
+    if pid == 0 || pid > libc::pid_t::max_value() as u32 {
+        return false;
+    }
+
+    // signal 0 doesn't send anything, it only check that the process exist.
+    // EPERM means it exist but belong to another user.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 ||
+        io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn is_running(_pid: u32) -> bool {
+    false
+}
+
+fn list_files() -> Result<Vec<PathBuf>, Error> {
+    let dir = match recovery_dir() {
+        Some(val) => val,
+        None => return Ok(vec![]),
+    };
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut files = vec![];
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use common::types::Size;
+    use super::*;
+
+    #[test]
+    fn restore_round_trip() {
+        let mut document =
+            Document::new(2, PathBuf::from("lesson.teha"), Size::new(640, 480), true);
+        document.get_mut_pages()[1].set_name(String::from("second"));
+        document.set_active_page_index(1);
+
+        let data = RecoveryData {
+            path: document.get_path().clone(),
+            document: document.to_data(),
+        };
+        let path = env::temp_dir()
+            .join(format!("teha-recovery-test-{}.teha", process::id()));
+        serde_json::to_writer(File::create(&path).unwrap(), &data).unwrap();
+
+        let restored = restore(&path).unwrap();
+        remove(&path).unwrap();
+        assert!(!path.exists());
+
+        // the changes are not in the document file yet
+        assert!(restored.is_modified());
+        assert_eq!(restored.get_path(), &PathBuf::from("lesson.teha"));
+        assert!(restored.get_transparent());
+        assert_eq!(restored.get_pages().len(), 2);
+        assert_eq!(restored.get_active_page_index(), 1);
+        assert_eq!(restored.get_pages()[1].get_name(), "second");
+        assert_eq!(restored.get_pages()[0].get_size(), &Size::new(640, 480));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn this_process_is_running() {
+        assert!(is_running(process::id()));
+        assert!(!is_running(0));
+    }
+
+    #[test]
+    fn restore_rejects_invalid_files() {
+        let path = env::temp_dir()
+            .join(format!("teha-recovery-invalid-{}.teha", process::id()));
+        File::create(&path).unwrap();
+        assert!(restore(&path).is_err());
+        remove(&path).unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+//
+// snap.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+// Smart guides: while a shape is dragged its points and edges snap to the
+// edges and centers of the other shapes, and the lines they got aligned to
+// are shown to the user as guides. all values here are in page (user)
+// coordinates.
+
+use common::types::*;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Guide {
+    // a vertical line at x
+    Vertical(f64),
+    // a horizontal line at y
+    Horizontal(f64),
+}
+
+pub struct SnapTargets {
+    xs: Vec<f64>,
+    ys: Vec<f64>,
+    boxes: Vec<Rectangle>,
+    // how close a point should be to a target to snap to it
+    tolerance: f64,
+}
+
+impl SnapTargets {
+    pub fn new(tolerance: f64) -> Self {
+        SnapTargets {
+            xs: vec![],
+            ys: vec![],
+            boxes: vec![],
+            tolerance: tolerance,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty() && self.ys.is_empty()
+    }
+
+    // add the edges and the center of @rect as targets, points can also snap
+    // to the border of @rect.
+    pub fn add_rectangle(&mut self, rect: &Rectangle) {
+        let (mins, maxs) = (rect.mins(), rect.maxs());
+        self.xs.extend_from_slice(&[mins.x, (mins.x + maxs.x) / 2.0, maxs.x]);
+        self.ys.extend_from_slice(&[mins.y, (mins.y + maxs.y) / 2.0, maxs.y]);
+        self.boxes.push(rect.clone());
+    }
+
+    pub fn add_x(&mut self, x: f64) {
+        self.xs.push(x);
+    }
+
+    pub fn add_y(&mut self, y: f64) {
+        self.ys.push(y);
+    }
+
+    // snap @pos to the nearest box border or aligned edge/center, return the
+    // new point with the guides it's aligned to.
+    pub fn snap_point(&self, pos: &Point) -> (Point, Vec<Guide>) {
+        let mut result = pos.clone();
+        let mut guides = vec![];
+
+        if let Some(point) = self.nearest_border_point(pos) {
+            result = point;
+        }
+
+        if let Some(x) = nearest(&self.xs, &[result.x], self.tolerance)
+                             .map(|(target, _)| target) {
+            result.x = x;
+            guides.push(Guide::Vertical(x));
+        }
+        if let Some(y) = nearest(&self.ys, &[result.y], self.tolerance)
+                             .map(|(target, _)| target) {
+            result.y = y;
+            guides.push(Guide::Horizontal(y));
+        }
+        (result, guides)
+    }
+
+    // return the offset that align the edges or the center of @rect with
+    // the nearest targets, with the guides they got aligned to.
+    pub fn snap_rectangle(&self, rect: &Rectangle) -> (Vector, Vec<Guide>) {
+        let (mins, maxs) = (rect.mins(), rect.maxs());
+        let mut offset = Vector::new(0.0, 0.0);
+        let mut guides = vec![];
+
+        let xs = [mins.x, (mins.x + maxs.x) / 2.0, maxs.x];
+        if let Some((target, value)) = nearest(&self.xs, &xs, self.tolerance) {
+            offset.x = target - value;
+            guides.push(Guide::Vertical(target));
+        }
+
+        let ys = [mins.y, (mins.y + maxs.y) / 2.0, maxs.y];
+        if let Some((target, value)) = nearest(&self.ys, &ys, self.tolerance) {
+            offset.y = target - value;
+            guides.push(Guide::Horizontal(target));
+        }
+        (offset, guides)
+    }
+
+    // on ties the box that was added first wins
+    fn nearest_border_point(&self, pos: &Point) -> Option<Point> {
+        let mut result = None;
+        let mut min_distance = self.tolerance;
+        for rect in self.boxes.iter() {
+            let point = border_point(rect, pos);
+            let distance = ((point.x - pos.x).powi(2) +
+                            (point.y - pos.y).powi(2)).sqrt();
+            if distance < min_distance ||
+               (result.is_none() && distance <= min_distance) {
+                min_distance = distance;
+                result = Some(point);
+            }
+        }
+        result
+    }
+}
+
+// return the (target, value) pair that are the closest to each other, if
+// they are within @tolerance. on ties the first target wins, then the first
+// value, so shapes snap the same way whatever the other targets are.
+fn nearest(
+    targets: &[f64], values: &[f64], tolerance: f64
+) -> Option<(f64, f64)> {
+    let mut result = None;
+    let mut min_distance = tolerance;
+    for target in targets.iter() {
+        for value in values.iter() {
+            let distance = (target - value).abs();
+            if distance < min_distance ||
+               (result.is_none() && distance <= min_distance) {
+                min_distance = distance;
+                result = Some((*target, *value));
+            }
+        }
+    }
+    result
+}
+
+// return the nearest point to @pos on the border of @rect
+fn border_point(rect: &Rectangle, pos: &Point) -> Point {
+    let (mins, maxs) = (rect.mins(), rect.maxs());
+    let clamped = Point::new(
+        pos.x.max(mins.x).min(maxs.x),
+        pos.y.max(mins.y).min(maxs.y)
+    );
+
+    // points outside the box are already on its border after clamping
+    if clamped != *pos {
+        return clamped;
+    }
+
+    let to_left = pos.x - mins.x;
+    let to_right = maxs.x - pos.x;
+    let to_top = pos.y - mins.y;
+    let to_bottom = maxs.y - pos.y;
+    let min = to_left.min(to_right).min(to_top).min(to_bottom);
+    if min == to_left {
+        Point::new(mins.x, pos.y)
+    } else if min == to_right {
+        Point::new(maxs.x, pos.y)
+    } else if min == to_top {
+        Point::new(pos.x, mins.y)
+    } else {
+        Point::new(pos.x, maxs.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_includes_the_tolerance() {
+        assert_eq!(nearest(&[10.0], &[2.0], 8.0), Some((10.0, 2.0)));
+        assert_eq!(nearest(&[10.0], &[1.5], 8.0), None);
+    }
+
+    #[test]
+    fn nearest_prefers_the_closest_pair() {
+        assert_eq!(nearest(&[0.0, 9.0], &[5.0, 10.0], 8.0), Some((9.0, 10.0)));
+    }
+
+    #[test]
+    fn nearest_ties_pick_the_first_target() {
+        assert_eq!(nearest(&[0.0, 10.0], &[5.0], 8.0), Some((0.0, 5.0)));
+        assert_eq!(nearest(&[4.0], &[0.0, 8.0], 8.0), Some((4.0, 0.0)));
+    }
+
+    #[test]
+    fn snap_point_tie_between_targets() {
+        let mut targets = SnapTargets::new(8.0);
+        targets.add_x(0.0);
+        targets.add_x(10.0);
+        let (point, guides) = targets.snap_point(&Point::new(5.0, 50.0));
+        assert_eq!(point, Point::new(0.0, 50.0));
+        assert!(guides == vec![Guide::Vertical(0.0)]);
+    }
+
+    #[test]
+    fn snap_point_tie_between_boxes() {
+        let mut targets = SnapTargets::new(8.0);
+        targets.add_rectangle(&Rectangle::new(Point::new(0.0, 0.0),
+                                              Point::new(10.0, 10.0)));
+        targets.add_rectangle(&Rectangle::new(Point::new(20.0, 0.0),
+                                              Point::new(30.0, 10.0)));
+        // 5 away from the right edge of the first box and from the left
+        // edge of the second one
+        let (point, _) = targets.snap_point(&Point::new(15.0, 5.0));
+        assert_eq!(point.x, 10.0);
+    }
+
+    #[test]
+    fn snap_rectangle_tie_between_edges() {
+        let mut targets = SnapTargets::new(10.0);
+        targets.add_y(0.0);
+        targets.add_y(30.0);
+        // the top is 10 below the first target and the bottom is 10 above
+        // the second one
+        let rect = Rectangle::new(Point::new(0.0, 10.0), Point::new(10.0, 20.0));
+        let (offset, guides) = targets.snap_rectangle(&rect);
+        assert_eq!(offset, Vector::new(0.0, -10.0));
+        assert!(guides == vec![Guide::Horizontal(0.0)]);
+    }
+}
@@ -22,9 +22,14 @@ use std::io;
 use std::fmt;
 use std::error;
 
+use glib;
+use serde_json;
+
 #[derive(Debug)]
 pub enum Error {
     Io(io::Error),
+    Json(serde_json::Error),
+    Glib(glib::Error),
     Message(&'static str),
 }
 
@@ -32,6 +37,8 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Io(ref err) => write!(f, "IO Error: {}", err),
+            Error::Json(ref err) => write!(f, "JSON Error: {}", err),
+            Error::Glib(ref err) => write!(f, "GLib Error: {}", err),
             Error::Message(ref msg) => write!(f, "Message Error: {}", msg),
         }
     }
@@ -41,6 +48,8 @@ impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::Io(ref err) => err.description(),
+            Error::Json(ref err) => err.description(),
+            Error::Glib(ref err) => err.description(),
             Error::Message(ref msg) => msg,
         }
     }
@@ -48,6 +57,8 @@ impl error::Error for Error {
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             Error::Io(ref err) => Some(err),
+            Error::Json(ref err) => Some(err),
+            Error::Glib(ref err) => Some(err),
             Error::Message(_) => None,
         }
     }
@@ -59,6 +70,18 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Json(error)
+    }
+}
+
+impl From<glib::Error> for Error {
+    fn from(error: glib::Error) -> Self {
+        Error::Glib(error)
+    }
+}
+
 impl From<&'static str> for Error {
     fn from(message: &'static str) -> Self {
         Error::Message(message)
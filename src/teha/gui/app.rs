@@ -27,6 +27,8 @@ use gio::{self, SimpleAction, SimpleActionExt, ActionMapExt, MenuExt};
 
 use super::*;
 use super::main_window::MainWindow;
+use super::pages_panel::PagesPanel;
+use super::document_tabs::DocumentTabs;
 
 pub struct Application {
     parent: gtk::Application,
@@ -130,6 +132,10 @@ impl Application {
 
         let header_bar = window.borrow().get_header_bar();
         header_bar.borrow_mut().update_view(mode);
+
+        if let ViewMode::Editing = mode {
+            PagesPanel::update(&window);
+        }
     }
 
     fn setup_app_menu(&self) {
@@ -159,9 +165,12 @@ impl Application {
         {
             let teha_app = app.borrow_mut();
             let gtk_app = teha_app.parent.clone();
+            let app = app.clone();
             let action = SimpleAction::new("quit", None);
             action.connect_activate(move |_action, _data| {
-                gtk_app.quit();
+                if DocumentTabs::close_all_documents(&app) {
+                    gtk_app.quit();
+                }
             });
             teha_app.parent.add_action(&action);
             teha_app.parent.set_accels_for_action("app.quit", &["<Ctrl>Q"]);
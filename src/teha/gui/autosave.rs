@@ -0,0 +1,130 @@
+//
+// autosave.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use gtk;
+use gtk::prelude::*;
+
+use gettextrs::*;
+
+use super::*;
+use super::app::Application;
+use super::document_tabs::DocumentTabs;
+use core::recovery;
+
+// seconds between two saves of the recovery files
+const AUTOSAVE_INTERVAL: u32 = 60;
+
+pub struct Autosave {
+    infobar: gtk::InfoBar,
+    label: gtk::Label,
+    // recovery files found on start-up
+    leftovers: Vec<PathBuf>,
+}
+
+impl Autosave {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let infobar: gtk::InfoBar =
+            builder.get_object("stup_recovery_infobar").unwrap();
+        let label: gtk::Label =
+            builder.get_object("stup_recovery_label").unwrap();
+
+        infobar.add_button(
+            gettext("Discard").as_str(), gtk::ResponseType::Reject.into()
+        );
+        infobar.add_button(
+            gettext("Restore").as_str(), gtk::ResponseType::Accept.into()
+        );
+
+        Autosave {
+            infobar: infobar,
+            label: label,
+            leftovers: recovery::find_leftovers().unwrap_or(vec![]),
+        }
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let autosave = window.borrow().get_autosave();
+
+        // offer to restore the documents left by the last session
+        {
+            let autosave = autosave.borrow();
+            let n_leftovers = autosave.leftovers.len();
+            if n_leftovers > 0 {
+                autosave.label.set_text(
+                    /* TRANSLATORS: {number} is replaced by the number of documents, keep it as is */
+                    gettext("{number} unsaved document(s) were found from the last session, restore them?")
+                        .replace("{number}", n_leftovers.to_string().as_str())
+                        .as_str()
+                );
+                autosave.infobar.show();
+            }
+        }
+
+        {
+            let app = app.clone();
+            let autosave = autosave.clone();
+            let infobar = autosave.borrow().infobar.clone();
+            infobar.connect_response(move |me, response| {
+                me.hide();
+                let leftovers: Vec<PathBuf> =
+                    autosave.borrow_mut().leftovers.drain(..).collect();
+
+                let restore: i32 = gtk::ResponseType::Accept.into();
+                if response == restore {
+                    Autosave::restore(&app, &leftovers);
+                }
+                for path in leftovers.iter() {
+                    let _ = recovery::remove(path);
+                }
+            });
+        }
+
+        // keep the recovery files up to date with the modified documents
+        {
+            let window = window.clone();
+            gtk::timeout_add_seconds(AUTOSAVE_INTERVAL, move || {
+                let _ = recovery::save_documents(window.borrow().get_documents());
+                gtk::Continue(true)
+            });
+        }
+    }
+
+    fn restore(app: &Rc<RefCell<Application>>, paths: &Vec<PathBuf>) {
+        let window = app.borrow().get_main_window();
+        let mut restored = false;
+        for path in paths.iter() {
+            if let Ok(document) = recovery::restore(path) {
+                window.borrow_mut().new_documents(document);
+                restored = true;
+            }
+        }
+
+        if restored {
+            app.borrow_mut().update_view(ViewMode::Editing);
+            DocumentTabs::update(app);
+            DocumentTabs::restore_view(&window);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//
+// background_panel.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+use gtk;
+use gtk::prelude::*;
+
+use gettextrs::*;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use super::drawing_area::DrawingArea;
+use super::pages_panel::PagesPanel;
+use super::dialogs;
+use core::draw_objects::{Page, Background};
+use common::types::*;
+use common::image::Picture;
+
+pub struct BackgroundPanel {
+    parent: gtk::Grid,
+    choose: gtk::Button,
+    remove: gtk::Button,
+    scale: gtk::SpinButton,
+    x: gtk::SpinButton,
+    y: gtk::SpinButton,
+    lock: gtk::Switch,
+    fit_page: gtk::Button,
+    hide_previous: gtk::Button,
+    // true while the widgets are filled from the active page, so their
+    // signals don't mark the document as modified.
+    updating: Rc<Cell<bool>>,
+}
+
+impl BackgroundPanel {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let side_panel: gtk::Notebook =
+            builder.get_object("ed_side_panel").unwrap();
+
+        let create_label = |text: &str| {
+            let label = gtk::Label::new(text);
+            label.set_halign(gtk::Align::End);
+            label
+        };
+
+        let grid = gtk::Grid::new();
+        grid.set_property_margin(10);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(10);
+
+        let image_label = create_label(gettext("Image:").as_str());
+        let image_box = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        image_box.get_style_context()
+                 .map(|style| style.add_class("linked"));
+        let choose = gtk::Button::new_with_label(gettext("Choose…").as_str());
+        choose.set_tooltip_text(
+            gettext("Draw a PNG or JPEG image below the shapes").as_str()
+        );
+        let remove = gtk::Button::new_from_icon_name("edit-delete-symbolic", 1);
+        remove.set_tooltip_text(gettext("Remove the image").as_str());
+        image_box.pack_start(&choose, true, true, 0);
+        image_box.pack_start(&remove, false, false, 0);
+        grid.attach(&image_label, 0, 0, 1, 1);
+        grid.attach(&image_box, 1, 0, 1, 1);
+
+        let scale_label = create_label(gettext("Scale:").as_str());
+        let scale = gtk::SpinButton::new_with_range(0.05, 10.0, 0.05);
+        scale.set_digits(2);
+        grid.attach(&scale_label, 0, 1, 1, 1);
+        grid.attach(&scale, 1, 1, 1, 1);
+
+        let x_label = create_label(gettext("X:").as_str());
+        let x = gtk::SpinButton::new_with_range(-10000.0, 10000.0, 1.0);
+        grid.attach(&x_label, 0, 2, 1, 1);
+        grid.attach(&x, 1, 2, 1, 1);
+
+        let y_label = create_label(gettext("Y:").as_str());
+        let y = gtk::SpinButton::new_with_range(-10000.0, 10000.0, 1.0);
+        grid.attach(&y_label, 0, 3, 1, 1);
+        grid.attach(&y, 1, 3, 1, 1);
+
+        let lock_label = create_label(gettext("Lock:").as_str());
+        let lock = gtk::Switch::new();
+        lock.set_halign(gtk::Align::Start);
+        lock.set_tooltip_text(
+            gettext("Unlock to drag the image, locked images let the clicks \
+                     reach the shapes").as_str()
+        );
+        grid.attach(&lock_label, 0, 4, 1, 1);
+        grid.attach(&lock, 1, 4, 1, 1);
+
+        let fit_page = gtk::Button::new_with_label(
+            gettext("Fit Page to Image").as_str()
+        );
+        grid.attach(&fit_page, 0, 5, 2, 1);
+
+        let hide_previous = gtk::Button::new_with_label(
+            gettext("Hide Replaced Image").as_str()
+        );
+        hide_previous.set_tooltip_text(
+            gettext("The replaced image is shown faded until it's hidden").as_str()
+        );
+        grid.attach(&hide_previous, 0, 6, 2, 1);
+
+        grid.show_all();
+        // it's shown after replacing the image
+        hide_previous.hide();
+        let tab_label = gtk::Label::new(gettext("Background").as_str());
+        side_panel.append_page(&grid, Some(&tab_label));
+
+        BackgroundPanel {
+            parent: grid,
+            choose: choose,
+            remove: remove,
+            scale: scale,
+            x: x,
+            y: y,
+            lock: lock,
+            fit_page: fit_page,
+            hide_previous: hide_previous,
+            updating: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn get_parent(&self) -> gtk::Grid {
+        self.parent.clone()
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let background_panel = window.borrow().get_background_panel();
+        let background_panel = background_panel.borrow();
+
+        // run @change on the background of the active page and mark the
+        // document as modified, unless the widgets are being updated from
+        // the page.
+        let edit_background = {
+            let window = window.clone();
+            let updating = background_panel.updating.clone();
+            Rc::new(move |change: &Fn(&mut Background)| {
+                if updating.get() || window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    let page = window.get_mut_active_document()
+                                     .get_mut_active_page();
+                    match *page.get_mut_background() {
+                        Some(ref mut background) => change(background),
+                        None => return,
+                    }
+                }
+                DocumentTabs::mark_modified(&window);
+                PagesPanel::update_thumbnail(&window);
+            })
+        };
+
+        {
+            let window = window.clone();
+            background_panel.choose.connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let parent = window.borrow().get_parent();
+                let path = match dialogs::choose_image(
+                    &parent, gettext("Choose Background Image").as_str()
+                ) {
+                    Some(val) => val,
+                    None => return,
+                };
+                let picture = match Picture::from_file(&path) {
+                    Ok(val) => val,
+                    Err(why) => {
+                        dialogs::show_error(
+                            &parent,
+                            gettext("Couldn't open the image").as_str(),
+                            format!("{}", why).as_str()
+                        );
+                        return;
+                    },
+                };
+
+                // replacing an image keep the shapes, the user choose if
+                // they follow the new image size.
+                let has_background = window.borrow()
+                                           .get_active_document()
+                                           .get_active_page()
+                                           .get_background()
+                                           .is_some();
+                let rescale = if has_background {
+                    match dialogs::ask_rescale_shapes(&parent) {
+                        Some(val) => val,
+                        None => return,
+                    }
+                } else {
+                    false
+                };
+
+                BackgroundPanel::edit_page(&window, &|page| {
+                    page.replace_background(picture.clone(), rescale);
+                });
+            });
+        }
+
+        {
+            let window = window.clone();
+            background_panel.remove.connect_clicked(move |_me| {
+                BackgroundPanel::edit_page(&window, &|page| {
+                    page.set_background(None);
+                });
+            });
+        }
+
+        {
+            let window = window.clone();
+            background_panel.fit_page.connect_clicked(move |_me| {
+                BackgroundPanel::edit_page(&window, &|page| {
+                    page.fit_to_background();
+                });
+            });
+        }
+
+        {
+            let window = window.clone();
+            background_panel.hide_previous.connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                window.borrow_mut()
+                      .get_mut_active_document()
+                      .get_mut_active_page()
+                      .clear_previous_background();
+                BackgroundPanel::update(&window);
+                DrawingArea::queue_draw(&window);
+            });
+        }
+
+        {
+            let edit_background = edit_background.clone();
+            background_panel.scale.connect_property_value_notify(move |me| {
+                let scale = me.get_value();
+                edit_background(&|background| background.set_scale(scale));
+            });
+        }
+
+        {
+            let edit_background = edit_background.clone();
+            let y = background_panel.y.clone();
+            background_panel.x.connect_property_value_notify(move |me| {
+                let position = Point::new(me.get_value(), y.get_value());
+                edit_background(&|background| background.set_position(position));
+            });
+        }
+
+        {
+            let edit_background = edit_background.clone();
+            let x = background_panel.x.clone();
+            background_panel.y.connect_property_value_notify(move |me| {
+                let position = Point::new(x.get_value(), me.get_value());
+                edit_background(&|background| background.set_position(position));
+            });
+        }
+
+        {
+            let edit_background = edit_background.clone();
+            background_panel.lock.connect_property_active_notify(move |me| {
+                let locked = me.get_active();
+                edit_background(&|background| background.set_locked(locked));
+            });
+        }
+    }
+
+    // run @change on the active page, then show the new background in the
+    // panel and mark the document as modified.
+    fn edit_page(window: &Rc<RefCell<MainWindow>>, change: &Fn(&mut Page)) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        change(window.borrow_mut()
+                     .get_mut_active_document()
+                     .get_mut_active_page());
+        BackgroundPanel::update(window);
+        DocumentTabs::mark_modified(window);
+        PagesPanel::update_thumbnail(window);
+    }
+
+    // show the background of the active page
+    pub fn update(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let background_panel = window.borrow().get_background_panel();
+        let background_panel = background_panel.borrow();
+        let (background, has_previous) = {
+            let window = window.borrow();
+            let page = window.get_active_document().get_active_page();
+            (
+                page.get_background().as_ref().map(|val| {
+                    (val.get_scale(), *val.get_position(), val.is_locked())
+                }),
+                page.get_previous_background().is_some(),
+            )
+        };
+
+        background_panel.updating.set(true);
+        let has_background = background.is_some();
+        if let Some((scale, position, locked)) = background {
+            background_panel.scale.set_value(scale);
+            background_panel.x.set_value(position.x);
+            background_panel.y.set_value(position.y);
+            background_panel.lock.set_active(locked);
+        }
+        background_panel.remove.set_sensitive(has_background);
+        background_panel.scale.set_sensitive(has_background);
+        background_panel.x.set_sensitive(has_background);
+        background_panel.y.set_sensitive(has_background);
+        background_panel.lock.set_sensitive(has_background);
+        background_panel.fit_page.set_sensitive(has_background);
+        background_panel.hide_previous.set_visible(has_previous);
+        background_panel.updating.set(false);
+    }
+}
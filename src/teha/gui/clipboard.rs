@@ -0,0 +1,224 @@
+//
+// clipboard.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use gtk;
+use gdk::{self, EventKey};
+use cairo;
+
+use ncollide::bounding_volume::BoundingVolume;
+
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use core::context::Context;
+use core::data::{self, ShapeData};
+use core::draw_objects::{Page, Image};
+use common::types::*;
+use common::image::Picture;
+
+// pasted shapes are moved by this value each time they get pasted, so they
+// don't cover the original shapes.
+const PASTE_OFFSET: f64 = 10.0;
+
+// copied shapes are offered on the CLIPBOARD selection in two formats, the
+// serialized shapes so teacher hand can paste them back as shapes (even
+// between documents), and a PNG of them for other apps.
+const SHAPES_TARGET: &'static str = "application/x-teacher-hand-shapes";
+const PNG_TARGET: &'static str = "image/png";
+const SHAPES_INFO: u32 = 0;
+const PNG_INFO: u32 = 1;
+
+pub struct Clipboard {
+    clipboard: gtk::Clipboard,
+    paste_count: usize,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        let clipboard = gtk::Clipboard::get(&gdk::Atom::intern("CLIPBOARD"));
+
+        Clipboard {
+            clipboard: clipboard,
+            paste_count: 0,
+        }
+    }
+
+    // handle Ctrl+C, Ctrl+X and Ctrl+V, return true if the event was handled.
+    pub fn key_press(
+        &mut self, window: &Rc<RefCell<MainWindow>>, event: &EventKey
+    ) -> bool {
+        if !event.get_state().contains(gdk::CONTROL_MASK) {
+            return false;
+        }
+
+        let key = event.get_keyval();
+        if key == gdk::enums::key::c || key == gdk::enums::key::C {
+            let mut window = window.borrow_mut();
+            self.copy(window.get_mut_active_document().get_mut_active_page());
+            true
+        } else if key == gdk::enums::key::x || key == gdk::enums::key::X {
+            let cut = {
+                let mut window = window.borrow_mut();
+                self.cut(window.get_mut_active_document().get_mut_active_page())
+            };
+            if cut {
+                DocumentTabs::mark_modified(window);
+            }
+            true
+        } else if key == gdk::enums::key::v || key == gdk::enums::key::V {
+            if self.paste(window) {
+                DocumentTabs::mark_modified(window);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn copy(&mut self, page: &mut Page) -> bool {
+        let shapes = page.snapshot_selected_shapes();
+        if shapes.is_empty() {
+            return false;
+        }
+
+        let text = match data::shapes_to_string(&shapes) {
+            Ok(val) => val,
+            Err(_) => return false,
+        };
+
+        let pixbuf = Clipboard::render(&shapes)
+                         .and_then(|picture| picture.to_pixbuf());
+        let mut targets = vec![gtk::TargetEntry::new(
+            SHAPES_TARGET, gtk::TargetFlags::empty(), SHAPES_INFO
+        )];
+        if pixbuf.is_some() {
+            targets.push(gtk::TargetEntry::new(
+                PNG_TARGET, gtk::TargetFlags::empty(), PNG_INFO
+            ));
+        }
+
+        // the data is handed out when an app ask for one of the targets
+        let stored = self.clipboard.set_with_data(
+            &targets, move |_clipboard, selection_data, info| {
+            if info == SHAPES_INFO {
+                selection_data.set(
+                    &gdk::Atom::intern(SHAPES_TARGET), 8, text.as_bytes()
+                );
+            } else if let Some(ref pixbuf) = pixbuf {
+                selection_data.set_pixbuf(pixbuf);
+            }
+        });
+        self.paste_count = 0;
+        stored
+    }
+
+    pub fn cut(&mut self, page: &mut Page) -> bool {
+        if !self.copy(page) {
+            return false;
+        }
+
+        page.remove_selected_shapes();
+        true
+    }
+
+    pub fn paste(&mut self, window: &Rc<RefCell<MainWindow>>) -> bool {
+        // note: waiting for the clipboard runs a nested main loop, so we must
+        // read it before borrowing the window.
+        let shapes = self.clipboard
+                         .wait_for_contents(&gdk::Atom::intern(SHAPES_TARGET))
+                         .and_then(|contents| String::from_utf8(contents.get_data()).ok())
+                         .and_then(|text| data::shapes_from_string(&text).ok());
+
+        if let Some(shapes) = shapes {
+            self.paste_count += 1;
+            let offset = PASTE_OFFSET * self.paste_count as f64;
+            return window.borrow_mut()
+                         .get_mut_active_document()
+                         .get_mut_active_page()
+                         .paste_shapes(shapes, &Vector::new(offset, offset))
+                         .is_ok();
+        }
+
+        let picture = self.clipboard
+                          .wait_for_image()
+                          .and_then(|pixbuf| Picture::from_pixbuf(&pixbuf).ok());
+
+        if let Some(picture) = picture {
+            let mut window = window.borrow_mut();
+            let page = window.get_mut_active_document().get_mut_active_page();
+            let center = Point::new(
+                page.get_size().width as f64 / 2.0,
+                page.get_size().height as f64 / 2.0
+            );
+            let mut image = Image::new(picture, center);
+            page.remove_shapes_in_creating_mode();
+            page.unselect_all_shapes();
+            image.select();
+            page.add_shape(Box::new(image));
+            return true;
+        }
+        false
+    }
+
+    // render the given shapes in a surface that fit them exactly
+    fn render(shapes: &Vec<ShapeData>) -> Option<Picture> {
+        let shapes = match data::shapes_from_data(shapes.clone()) {
+            Ok(val) => val,
+            Err(_) => return None,
+        };
+
+        let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, 0, 0)
+            .expect("Cairo: couldn't create surface");
+        let cr = cairo::Context::new(&surface);
+        let cr = Context::new(&cr, 1.0, &Vector::new(0.0, 0.0));
+
+        let mut iter = shapes.iter().filter_map(|s| s.draw_extents(&cr));
+        let init = match iter.next() {
+            None => return None,
+            Some(val) => val,
+        };
+        let extents = iter.fold(init, |acc, ref x| acc.merged(x));
+
+        let width = (extents.maxs().x - extents.mins().x).ceil() as i32;
+        let height = (extents.maxs().y - extents.mins().y).ceil() as i32;
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+
+        let surface = match cairo::ImageSurface::create(
+            cairo::Format::ARgb32, width, height
+        ) {
+            Ok(val) => val,
+            Err(_) => return None,
+        };
+
+        {
+            let cr = cairo::Context::new(&surface);
+            let translate = Vector::new(-extents.mins().x, -extents.mins().y);
+            let cr = Context::new(&cr, 1.0, &translate);
+            for shape in shapes.iter() {
+                shape.draw(&cr);
+            }
+        }
+
+        Picture::from_surface(surface).ok()
+    }
+}
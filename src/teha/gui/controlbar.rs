@@ -25,6 +25,7 @@ use gtk::prelude::*;
 
 use super::app::Application;
 use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
 
 pub struct Controlbar {
     parent: gtk::Stack,
@@ -216,5 +217,26 @@ impl Controlbar {
                       .rotate_selected_children_to_right();
             });
         }
+
+        // all the actions above change the active document
+        {
+            let controlbar = controlbar.borrow();
+            let buttons = [
+                &controlbar.move_forward,
+                &controlbar.move_to_front,
+                &controlbar.move_backward,
+                &controlbar.move_to_rear,
+                &controlbar.flip_horizontal,
+                &controlbar.flip_vertical,
+                &controlbar.rotate_left,
+                &controlbar.rotate_right,
+            ];
+            for button in buttons.iter() {
+                let window = window.clone();
+                button.connect_clicked(move |_me| {
+                    DocumentTabs::mark_modified(&window);
+                });
+            }
+        }
     }
 }
@@ -0,0 +1,208 @@
+//
+// crop_panel.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+use gtk;
+use gtk::prelude::*;
+use gdk::{self, EventKey};
+
+use gettextrs::*;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use super::drawing_area::DrawingArea;
+use super::pages_panel::PagesPanel;
+use super::background_panel::BackgroundPanel;
+
+// the default space left around the shapes when trimming the page
+const TRIM_MARGIN: f64 = 20.0;
+
+pub struct CropPanel {
+    parent: gtk::Grid,
+    crop: gtk::ToggleButton,
+    margin: gtk::SpinButton,
+    trim: gtk::Button,
+    // true while the widgets are filled from the active page, so their
+    // signals don't change the page.
+    updating: Rc<Cell<bool>>,
+}
+
+impl CropPanel {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let side_panel: gtk::Notebook =
+            builder.get_object("ed_side_panel").unwrap();
+
+        let create_label = |text: &str| {
+            let label = gtk::Label::new(text);
+            label.set_halign(gtk::Align::End);
+            label
+        };
+
+        let grid = gtk::Grid::new();
+        grid.set_property_margin(10);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(10);
+
+        let crop = gtk::ToggleButton::new_with_label(
+            gettext("Crop Page").as_str()
+        );
+        crop.set_tooltip_text(
+            gettext("Drag a rectangle on the page to crop it, Escape cancel \
+                     cropping").as_str()
+        );
+        grid.attach(&crop, 0, 0, 2, 1);
+
+        let margin_label = create_label(gettext("Margin:").as_str());
+        let margin = gtk::SpinButton::new_with_range(0.0, 500.0, 1.0);
+        margin.set_value(TRIM_MARGIN);
+        margin.set_tooltip_text(
+            gettext("The space left around the shapes when trimming").as_str()
+        );
+        grid.attach(&margin_label, 0, 1, 1, 1);
+        grid.attach(&margin, 1, 1, 1, 1);
+
+        let trim = gtk::Button::new_with_label(
+            gettext("Trim to Content").as_str()
+        );
+        trim.set_tooltip_text(
+            gettext("Fit the page tightly around the shapes").as_str()
+        );
+        grid.attach(&trim, 0, 2, 2, 1);
+
+        grid.show_all();
+        let tab_label = gtk::Label::new(gettext("Crop").as_str());
+        side_panel.append_page(&grid, Some(&tab_label));
+
+        CropPanel {
+            parent: grid,
+            crop: crop,
+            margin: margin,
+            trim: trim,
+            updating: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn get_parent(&self) -> gtk::Grid {
+        self.parent.clone()
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let crop_panel = window.borrow().get_crop_panel();
+        let crop_panel = crop_panel.borrow();
+
+        {
+            let window = window.clone();
+            let updating = crop_panel.updating.clone();
+            crop_panel.crop.connect_toggled(move |me| {
+                if updating.get() || window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                window.borrow_mut()
+                      .get_mut_active_document()
+                      .get_mut_active_page()
+                      .set_cropping(me.get_active());
+                DrawingArea::queue_draw(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            let margin = crop_panel.margin.clone();
+            crop_panel.trim.connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let trimmed = window.borrow_mut()
+                                    .get_mut_active_document()
+                                    .get_mut_active_page()
+                                    .trim_to_content(margin.get_value());
+                if trimmed {
+                    CropPanel::page_resized(&window);
+                }
+            });
+        }
+    }
+
+    // show the new page size after cropping it
+    fn page_resized(window: &Rc<RefCell<MainWindow>>) {
+        DocumentTabs::mark_modified(window);
+        PagesPanel::update_thumbnail(window);
+        BackgroundPanel::update(window);
+    }
+
+    // show if the active page is in the crop mode
+    pub fn update(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let crop_panel = window.borrow().get_crop_panel();
+        let crop_panel = crop_panel.borrow();
+        let cropping = window.borrow()
+                             .get_active_document()
+                             .get_active_page()
+                             .is_cropping();
+
+        crop_panel.updating.set(true);
+        crop_panel.crop.set_active(cropping);
+        crop_panel.updating.set(false);
+    }
+
+    // the page leave the crop mode once the crop rectangle is dragged, called
+    // after the drawing area events to show the cropped page.
+    pub fn end_crop(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let was_cropping = {
+            let crop_panel = window.borrow().get_crop_panel();
+            let active = crop_panel.borrow().crop.get_active();
+            active
+        };
+        let cropping = window.borrow()
+                             .get_active_document()
+                             .get_active_page()
+                             .is_cropping();
+        if was_cropping && !cropping {
+            CropPanel::update(window);
+            CropPanel::page_resized(window);
+        }
+    }
+
+    // handle Escape that cancel cropping, return true if the event was
+    // handled.
+    pub fn key_press(&self, event: &EventKey) -> bool {
+        if event.get_keyval() != gdk::enums::key::Escape ||
+           !self.crop.get_active() {
+            return false;
+        }
+
+        // the button signal update the page
+        self.crop.set_active(false);
+        true
+    }
+}
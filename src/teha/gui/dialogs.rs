@@ -0,0 +1,229 @@
+//
+// dialogs.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::path::PathBuf;
+
+use gtk;
+use gtk::prelude::*;
+
+use gettextrs::*;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum SaveChanges {
+    Save,
+    Discard,
+    Cancel,
+}
+
+// ask the user what to do with the unsaved changes of the document that
+// have @title.
+pub fn ask_save_changes(
+    parent: &gtk::ApplicationWindow, title: &str
+) -> SaveChanges {
+    let dialog = gtk::MessageDialog::new(
+        Some(parent),
+        gtk::DIALOG_MODAL | gtk::DIALOG_DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        /* TRANSLATORS: {name} is replaced by the document name, keep it as is */
+        gettext("Save changes to \"{name}\" before closing?")
+            .replace("{name}", title)
+            .as_str()
+    );
+    dialog.set_property_secondary_text(Some(
+        gettext("If you don't save, your changes will be lost.").as_str()
+    ));
+    dialog.add_button(
+        gettext("Discard").as_str(), gtk::ResponseType::Reject.into()
+    );
+    dialog.add_button(
+        gettext("Cancel").as_str(), gtk::ResponseType::Cancel.into()
+    );
+    dialog.add_button(
+        gettext("Save").as_str(), gtk::ResponseType::Accept.into()
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept.into());
+
+    let save: i32 = gtk::ResponseType::Accept.into();
+    let discard: i32 = gtk::ResponseType::Reject.into();
+    let response = dialog.run();
+    dialog.destroy();
+
+    if response == save {
+        SaveChanges::Save
+    } else if response == discard {
+        SaveChanges::Discard
+    } else {
+        SaveChanges::Cancel
+    }
+}
+
+pub fn show_error(
+    parent: &gtk::ApplicationWindow, title: &str, description: &str
+) {
+    let dialog = gtk::MessageDialog::new(
+        Some(parent),
+        gtk::DIALOG_MODAL | gtk::DIALOG_DESTROY_WITH_PARENT,
+        gtk::MessageType::Error,
+        gtk::ButtonsType::Close,
+        title
+    );
+    dialog.set_property_secondary_text(Some(description));
+    dialog.run();
+    dialog.destroy();
+}
+
+// ask the user for a folder, return None if the dialog was canceled.
+pub fn choose_folder(
+    parent: &gtk::ApplicationWindow, title: &str
+) -> Option<PathBuf> {
+    let dialog = gtk::FileChooserDialog::new(
+        Some(title),
+        Some(parent),
+        gtk::FileChooserAction::SelectFolder
+    );
+    dialog.add_button(
+        gettext("Cancel").as_str(), gtk::ResponseType::Cancel.into()
+    );
+    dialog.add_button(
+        gettext("Select").as_str(), gtk::ResponseType::Accept.into()
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept.into());
+    dialog.set_create_folders(true);
+
+    let accept: i32 = gtk::ResponseType::Accept.into();
+    let response = dialog.run();
+    let folder = dialog.get_filename();
+    dialog.destroy();
+
+    if response == accept {
+        folder
+    } else {
+        None
+    }
+}
+
+// ask the user where to save a new file, @name is the suggested file name.
+// return None if the dialog was canceled.
+pub fn choose_save_file(
+    parent: &gtk::ApplicationWindow, title: &str, name: &str
+) -> Option<PathBuf> {
+    let dialog = gtk::FileChooserDialog::new(
+        Some(title),
+        Some(parent),
+        gtk::FileChooserAction::Save
+    );
+    dialog.add_button(
+        gettext("Cancel").as_str(), gtk::ResponseType::Cancel.into()
+    );
+    dialog.add_button(
+        gettext("Save").as_str(), gtk::ResponseType::Accept.into()
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept.into());
+    dialog.set_do_overwrite_confirmation(true);
+    dialog.set_current_name(name);
+
+    let accept: i32 = gtk::ResponseType::Accept.into();
+    let response = dialog.run();
+    let file = dialog.get_filename();
+    dialog.destroy();
+
+    if response == accept {
+        file
+    } else {
+        None
+    }
+}
+
+// ask the user for a PNG or JPEG image, return None if the dialog was
+// canceled.
+pub fn choose_image(
+    parent: &gtk::ApplicationWindow, title: &str
+) -> Option<PathBuf> {
+    let dialog = gtk::FileChooserDialog::new(
+        Some(title),
+        Some(parent),
+        gtk::FileChooserAction::Open
+    );
+    dialog.add_button(
+        gettext("Cancel").as_str(), gtk::ResponseType::Cancel.into()
+    );
+    dialog.add_button(
+        gettext("Open").as_str(), gtk::ResponseType::Accept.into()
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept.into());
+
+    let filter = gtk::FileFilter::new();
+    filter.set_name(Some(gettext("Images").as_str()));
+    filter.add_mime_type("image/png");
+    filter.add_mime_type("image/jpeg");
+    dialog.add_filter(&filter);
+
+    let accept: i32 = gtk::ResponseType::Accept.into();
+    let response = dialog.run();
+    let file = dialog.get_filename();
+    dialog.destroy();
+
+    if response == accept {
+        file
+    } else {
+        None
+    }
+}
+
+// ask the user if the shapes should keep their positions when the
+// background image is replaced, or be rescaled with the new image. return
+// None if the dialog was canceled.
+pub fn ask_rescale_shapes(parent: &gtk::ApplicationWindow) -> Option<bool> {
+    let dialog = gtk::MessageDialog::new(
+        Some(parent),
+        gtk::DIALOG_MODAL | gtk::DIALOG_DESTROY_WITH_PARENT,
+        gtk::MessageType::Question,
+        gtk::ButtonsType::None,
+        gettext("Rescale the shapes to the new image?").as_str()
+    );
+    dialog.set_property_secondary_text(Some(
+        gettext("The shapes can keep their positions, or move and stretch \
+                 with the image if its size changed. The old image is shown \
+                 faded to help realigning them.").as_str()
+    ));
+    dialog.add_button(
+        gettext("Cancel").as_str(), gtk::ResponseType::Cancel.into()
+    );
+    dialog.add_button(
+        gettext("Keep Positions").as_str(), gtk::ResponseType::Reject.into()
+    );
+    dialog.add_button(
+        gettext("Rescale").as_str(), gtk::ResponseType::Accept.into()
+    );
+    dialog.set_default_response(gtk::ResponseType::Reject.into());
+
+    let rescale: i32 = gtk::ResponseType::Accept.into();
+    let keep: i32 = gtk::ResponseType::Reject.into();
+    let response = dialog.run();
+    dialog.destroy();
+
+    if response == rescale {
+        Some(true)
+    } else if response == keep {
+        Some(false)
+    } else {
+        None
+    }
+}
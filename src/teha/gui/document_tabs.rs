@@ -0,0 +1,388 @@
+//
+// document_tabs.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+use gtk;
+use gtk::prelude::*;
+use gdk::{self, EventKey};
+
+use gettextrs::*;
+
+use super::*;
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::header_bar::HeaderBar;
+use super::drawing_area::DrawingArea;
+use super::pages_panel::PagesPanel;
+use super::dialogs::{self, SaveChanges};
+use core::Document;
+use core::recovery;
+use error::Error;
+
+// note: the notebook pages are empty boxes, we only use the notebook for its
+// tabs while the drawing area shows the active document.
+pub struct DocumentTabs {
+    parent: gtk::Notebook,
+    labels: Vec<gtk::Label>,
+    // true while the tabs are being rebuilt, so switch-page signals emitted
+    // by the notebook are ignored.
+    updating: Rc<Cell<bool>>,
+}
+
+impl DocumentTabs {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let documents_tabs: gtk::Notebook =
+            builder.get_object("ed_documents_tabs").unwrap();
+
+        DocumentTabs {
+            parent: documents_tabs,
+            labels: vec![],
+            updating: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn get_parent(&self) -> gtk::Notebook {
+        self.parent.clone()
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let document_tabs = window.borrow().get_document_tabs();
+
+        {
+            let window = window.clone();
+            let updating = document_tabs.borrow().updating.clone();
+            document_tabs.borrow()
+                         .parent
+                         .connect_switch_page(move |_me, _page, index| {
+                if updating.get() {
+                    return;
+                }
+
+                let index = index as usize;
+                if index == window.borrow().get_active_document_index() {
+                    return;
+                }
+
+                if window.borrow_mut().switch_document(index) {
+                    DocumentTabs::restore_view(&window);
+                    PagesPanel::update(&window);
+                }
+            });
+        }
+    }
+
+    // rebuild the tabs from the opened documents
+    pub fn update(app: &Rc<RefCell<Application>>) {
+        let window = app.borrow().get_main_window();
+        let document_tabs = window.borrow().get_document_tabs();
+        let notebook = document_tabs.borrow().parent.clone();
+        let updating = document_tabs.borrow().updating.clone();
+
+        updating.set(true);
+        for _ in 0..notebook.get_n_pages() {
+            notebook.remove_page(Some(0));
+        }
+        document_tabs.borrow_mut().labels.clear();
+
+        let n_documents = window.borrow().get_documents().len();
+        for index in 0..n_documents {
+            let label = gtk::Label::new(None);
+            let close = gtk::Button::new_from_icon_name("window-close-symbolic", 1);
+            close.set_relief(gtk::ReliefStyle::None);
+            close.set_tooltip_text(gettext("Close Document").as_str());
+            {
+                let app = app.clone();
+                close.connect_clicked(move |_me| {
+                    DocumentTabs::close_document(&app, index);
+                });
+            }
+
+            let tab = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+            tab.pack_start(&label, true, true, 0);
+            tab.pack_start(&close, false, false, 0);
+            tab.show_all();
+
+            let content = gtk::Box::new(gtk::Orientation::Vertical, 0);
+            content.show();
+            notebook.append_page(&content, Some(&tab));
+            document_tabs.borrow_mut().labels.push(label);
+        }
+
+        if n_documents > 0 {
+            let index = window.borrow().get_active_document_index();
+            notebook.set_current_page(Some(index as u32));
+        }
+        updating.set(false);
+
+        DocumentTabs::update_titles(&window);
+    }
+
+    // update the tabs title, modified documents are marked with asterisk
+    pub fn update_titles(window: &Rc<RefCell<MainWindow>>) {
+        let document_tabs = window.borrow().get_document_tabs();
+        let window = window.borrow();
+        let document_tabs = document_tabs.borrow();
+        for (label, document) in document_tabs.labels
+                                              .iter()
+                                              .zip(window.get_documents()) {
+            label.set_text(DocumentTabs::title(document).as_str());
+        }
+
+        let header_bar = window.get_header_bar();
+        if window.get_documents().len() > 0 {
+            let title = DocumentTabs::title(window.get_active_document());
+            header_bar.borrow().set_title(title.as_str());
+        }
+    }
+
+    // return the title of @document as shown to the user
+    pub fn title(document: &Document) -> String {
+        let name = if document.name().is_empty() {
+            gettext("Untitled")
+        } else {
+            String::from(document.name())
+        };
+
+        if document.is_modified() {
+            format!("*{}", name)
+        } else {
+            name
+        }
+    }
+
+    // show the zoom level and the scroll position of the active document
+    pub fn restore_view(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        HeaderBar::sync_zoom_level(window);
+        DrawingArea::queue_draw(window);
+
+        // note: the drawing area get its new size when it's drawn, so the
+        // scroll position is restored when idle.
+        let scroll = window.borrow().get_active_document().get_scroll().clone();
+        let drawing_area = window.borrow().get_drawing_area();
+        gtk::idle_add(move || {
+            drawing_area.borrow().set_scroll(&scroll);
+            gtk::Continue(false)
+        });
+    }
+
+    // mark the active document as modified and show that to the user, the
+    // drawing area is redrawn since the change didn't come from its events.
+    pub fn mark_modified(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        window.borrow_mut().get_mut_active_document().set_modified(true);
+        DocumentTabs::update_titles(window);
+        DrawingArea::queue_draw(window);
+    }
+
+    // like mark_modified() but for the options of the selected shapes, only
+    // their extents before and after the change are redrawn.
+    pub fn mark_options_modified(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        window.borrow_mut().get_mut_active_document().options_changed();
+        DocumentTabs::update_titles(window);
+        DrawingArea::queue_damage(window);
+    }
+
+    // save the document at @index, return false and show the error to the
+    // user if it couldn't be saved.
+    pub fn save_document(window: &Rc<RefCell<MainWindow>>, index: usize) -> bool {
+        // documents that don't have a file yet (e.g. recovered ones) are
+        // saved where the user choose
+        let has_file = match window.borrow().get_documents().get(index) {
+            Some(document) => document.get_path().file_name().is_some(),
+            None => return false,
+        };
+        if !has_file {
+            return DocumentTabs::save_document_as(window, index);
+        }
+
+        let result = match window.borrow_mut().get_mut_documents().get_mut(index) {
+            Some(document) => document.save(),
+            None => return false,
+        };
+        DocumentTabs::save_result(window, result)
+    }
+
+    // ask the user for a new file for the document at @index and save it
+    // there, return false if the user canceled or the save failed.
+    pub fn save_document_as(window: &Rc<RefCell<MainWindow>>, index: usize) -> bool {
+        let name = match window.borrow().get_documents().get(index) {
+            Some(document) if !document.name().is_empty() => {
+                String::from(document.name())
+            },
+            Some(_) => format!("{}.teha", gettext("Untitled")),
+            None => return false,
+        };
+
+        let parent = window.borrow().get_parent();
+        let path = match dialogs::choose_save_file(
+            &parent, gettext("Save Document").as_str(), name.as_str()
+        ) {
+            Some(val) => val,
+            None => return false,
+        };
+
+        let result = match window.borrow_mut().get_mut_documents().get_mut(index) {
+            Some(document) => document.save_as(path),
+            None => return false,
+        };
+        DocumentTabs::save_result(window, result)
+    }
+
+    // handle Ctrl+S and Ctrl+Shift+S for the active document, return true
+    // if the event was handled.
+    pub fn key_press(window: &Rc<RefCell<MainWindow>>, event: &EventKey) -> bool {
+        let state = event.get_state();
+        let key = event.get_keyval();
+        if !state.contains(gdk::CONTROL_MASK) ||
+           (key != gdk::enums::key::s && key != gdk::enums::key::S) {
+            return false;
+        }
+
+        let index = window.borrow().get_active_document_index();
+        if state.contains(gdk::SHIFT_MASK) {
+            DocumentTabs::save_document_as(window, index);
+        } else {
+            DocumentTabs::save_document(window, index);
+        }
+        true
+    }
+
+    // show the error of a failed save, or the new title of the saved document
+    fn save_result(
+        window: &Rc<RefCell<MainWindow>>, result: Result<(), Error>
+    ) -> bool {
+        if let Err(why) = result {
+            let parent = window.borrow().get_parent();
+            dialogs::show_error(
+                &parent,
+                gettext("Couldn't Save The Document").as_str(),
+                format!("{}", why).as_str()
+            );
+            return false;
+        }
+
+        DocumentTabs::update_titles(window);
+        true
+    }
+
+    // ask the user to save the document at @index if it was modified, return
+    // false if the user canceled closing the document.
+    fn confirm_close(window: &Rc<RefCell<MainWindow>>, index: usize) -> bool {
+        let (modified, title) = match window.borrow().get_documents().get(index) {
+            Some(document) => {
+                (document.is_modified(), DocumentTabs::title(document))
+            },
+            None => return false,
+        };
+
+        if !modified {
+            return true;
+        }
+
+        // show the document that we are asking about
+        if index != window.borrow().get_active_document_index() {
+            window.borrow_mut().switch_document(index);
+            DocumentTabs::select_active_tab(window);
+            DocumentTabs::restore_view(window);
+            PagesPanel::update(window);
+        }
+
+        let parent = window.borrow().get_parent();
+        match dialogs::ask_save_changes(&parent, title.as_str()) {
+            SaveChanges::Save => DocumentTabs::save_document(window, index),
+            SaveChanges::Discard => true,
+            SaveChanges::Cancel => false,
+        }
+    }
+
+    // close the document at @index, the user is asked first if the document
+    // have changes that will be lost.
+    pub fn close_document(app: &Rc<RefCell<Application>>, index: usize) {
+        let window = app.borrow().get_main_window();
+        if !DocumentTabs::confirm_close(&window, index) {
+            return;
+        }
+
+        let active_closed = index == window.borrow().get_active_document_index();
+        window.borrow_mut().close_document(index);
+        // the changes of the closed document are saved or discarded, and the
+        // recovery files are named after the document positions, so they are
+        // written again without it.
+        let _ = recovery::save_documents(window.borrow().get_documents());
+
+        if window.borrow().get_documents().len() == 0 {
+            app.borrow_mut().update_view(ViewMode::StartUp);
+            DocumentTabs::update(app);
+            return;
+        }
+
+        DocumentTabs::update(app);
+        if active_closed {
+            DocumentTabs::restore_view(&window);
+            PagesPanel::update(&window);
+        }
+    }
+
+    // close all documents, return false if the user canceled closing one of
+    // them, in that case the documents before it are already closed.
+    pub fn close_all_documents(app: &Rc<RefCell<Application>>) -> bool {
+        let window = app.borrow().get_main_window();
+        while window.borrow().get_documents().len() > 0 {
+            let index = window.borrow().get_active_document_index();
+            if !DocumentTabs::confirm_close(&window, index) {
+                DocumentTabs::update(app);
+                return false;
+            }
+            window.borrow_mut().close_document(index);
+        }
+
+        // nothing left to recover
+        let _ = recovery::remove_session_files();
+        app.borrow_mut().update_view(ViewMode::StartUp);
+        DocumentTabs::update(app);
+        true
+    }
+
+    // select the tab of the active document without switching documents
+    fn select_active_tab(window: &Rc<RefCell<MainWindow>>) {
+        let document_tabs = window.borrow().get_document_tabs();
+        let notebook = document_tabs.borrow().parent.clone();
+        let updating = document_tabs.borrow().updating.clone();
+        let index = window.borrow().get_active_document_index();
+
+        updating.set(true);
+        notebook.set_current_page(Some(index as u32));
+        updating.set(false);
+    }
+}
@@ -19,16 +19,45 @@
 
 use gtk;
 use gtk::prelude::*;
+use gdk::{self, EventKey};
 
 use std::rc::Rc;
 use std::cell::RefCell;
 
 use super::app::Application;
+use super::main_window::MainWindow;
+use super::header_bar::HeaderBar;
+use super::pages_panel::PagesPanel;
+use super::document_tabs::DocumentTabs;
+use super::grid_panel::GridPanel;
+use super::background_panel::BackgroundPanel;
+use super::crop_panel::CropPanel;
+use super::rulers::Rulers;
+use core::draw_objects::OptionsWidget;
 use common::types::*;
 
+// the zoom level range, same as the zoom scale in the header bar
+pub const MIN_ZOOM_LEVEL: f64 = 0.1;
+pub const MAX_ZOOM_LEVEL: f64 = 5.0;
+// each scroll step zoom in or out by this factor
+const ZOOM_STEP: f64 = 1.1;
+// the empty space around the page
+const MARGIN: f64 = 100.0;
+// damaged areas are grown by this (in pixels) to cover the antialiasing
+const DAMAGE_PADDING: f64 = 2.0;
+
 pub struct DrawingArea {
     parent: gtk::DrawingArea,
     scrolled_drawing_area: gtk::ScrolledWindow,
+    hadjustment: gtk::Adjustment,
+    vadjustment: gtk::Adjustment,
+    zoom_gesture: gtk::GestureZoom,
+    // the zoom level when the pinch gesture began
+    gesture_zoom_level: f64,
+    // the pointer root position and the scroll when panning began
+    pan_start: Option<(Point, Vector)>,
+    // while space is pressed dragging with the first button pan the view
+    space_pressed: bool,
 }
 
 impl DrawingArea {
@@ -37,10 +66,22 @@ impl DrawingArea {
             builder.get_object("drawing_area").unwrap();
         let scrolled_drawing_area: gtk::ScrolledWindow =
             builder.get_object("scrolled_drawing_area").unwrap();
+        let hadjustment: gtk::Adjustment =
+            builder.get_object("scrolled_drawing_area_hor").unwrap();
+        let vadjustment: gtk::Adjustment =
+            builder.get_object("scrolled_drawing_area_ver").unwrap();
+
+        let zoom_gesture = gtk::GestureZoom::new(&drawing_area);
 
         DrawingArea {
             parent: drawing_area,
             scrolled_drawing_area: scrolled_drawing_area,
+            hadjustment: hadjustment,
+            vadjustment: vadjustment,
+            zoom_gesture: zoom_gesture,
+            gesture_zoom_level: 1.0,
+            pan_start: None,
+            space_pressed: false,
         }
     }
 
@@ -52,10 +93,364 @@ impl DrawingArea {
         self.scrolled_drawing_area.clone()
     }
 
+    pub fn get_scroll(&self) -> Vector {
+        Vector::new(self.hadjustment.get_value(), self.vadjustment.get_value())
+    }
+
+    pub fn set_scroll(&self, scroll: &Vector) {
+        self.hadjustment.set_value(scroll.x);
+        self.vadjustment.set_value(scroll.y);
+    }
+
+    // redraw the whole drawing area, used when the page changed in a way
+    // we can't track (e.g. zoom level or active page).
+    pub fn queue_draw(window: &Rc<RefCell<MainWindow>>) {
+        DrawingArea::update_size(window);
+        let drawing_area = window.borrow().get_drawing_area();
+        drawing_area.borrow().parent.queue_draw();
+    }
+
+    // redraw only the areas the last events changed in the active page
+    pub fn queue_damage(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let damage = window.borrow_mut()
+                           .get_mut_active_document()
+                           .get_mut_active_page()
+                           .take_damage();
+        if damage.is_empty() {
+            return;
+        }
+
+        // the shapes could have moved outside the page
+        DrawingArea::update_size(window);
+        let drawing_area = window.borrow().get_drawing_area();
+        let drawing_area = drawing_area.borrow();
+        for rect in damage.iter() {
+            let x = (rect.mins().x - DAMAGE_PADDING).floor();
+            let y = (rect.mins().y - DAMAGE_PADDING).floor();
+            let width = (rect.maxs().x + DAMAGE_PADDING).ceil() - x;
+            let height = (rect.maxs().y + DAMAGE_PADDING).ceil() - y;
+            drawing_area.parent.queue_draw_area(
+                x as i32, y as i32, width as i32, height as i32
+            );
+        }
+    }
+
+    // the options of the selected shape mark the document as modified and
+    // redraw the shape when they change. note: the options widgets can emit their signals while
+    // the document is still borrowed by the event that created them, so the
+    // change is handled when idle.
+    fn options_widget(window: &Rc<RefCell<MainWindow>>) -> OptionsWidget {
+        let notebook = window.borrow().get_ed_options();
+        let window = window.clone();
+        OptionsWidget::new(notebook, move || {
+            let window = window.clone();
+            gtk::idle_add(move || {
+                DocumentTabs::mark_options_modified(&window);
+                gtk::Continue(false)
+            });
+        })
+    }
+
+    // the size of the visible part of the drawing area
+    fn view_size(&self) -> Vector {
+        Vector::new(
+            self.scrolled_drawing_area.get_allocated_width() as f64,
+            self.scrolled_drawing_area.get_allocated_height() as f64
+        )
+    }
+
+    // convert @pos from the drawing area coordinates to the visible area
+    // coordinates.
+    fn to_view(&self, pos: &Point) -> Point {
+        let view_pos = self.parent.translate_coordinates(
+            &self.scrolled_drawing_area, pos.x as i32, pos.y as i32
+        );
+        match view_pos {
+            Some((x, y)) => Point::new(x as f64, y as f64),
+            None => pos.clone(),
+        }
+    }
+
+    // the center of the visible area in the drawing area coordinates
+    fn view_center(&self) -> Point {
+        let size = self.view_size();
+        let center = self.scrolled_drawing_area.translate_coordinates(
+            &self.parent, (size.x / 2.0) as i32, (size.y / 2.0) as i32
+        );
+        match center {
+            Some((x, y)) => Point::new(x as f64, y as f64),
+            None => Point::new(size.x / 2.0, size.y / 2.0),
+        }
+    }
+
+    // resize the drawing area to fit the active page at its zoom level, and
+    // move the page away from the edges. the whole drawing area is redrawn
+    // when its size or the page position change.
+    fn update_size(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let page_bound = window.borrow()
+                               .get_active_document()
+                               .get_active_page()
+                               .draw_extents();
+        let val = match page_bound {
+            Some(val) => val,
+            None => return,
+        };
+
+        let width = (val.maxs().x - val.mins().x).abs();
+        let height = (val.maxs().y - val.mins().y).abs();
+        let size = ((width + MARGIN) as i32, (height + MARGIN) as i32);
+        let drawing_area = window.borrow().get_drawing_area();
+        let parent = drawing_area.borrow().parent.clone();
+        let size_changed = parent.get_size_request() != size;
+        if size_changed {
+            parent.set_size_request(size.0, size.1);
+        }
+
+        let mut translate = Vector::new(MARGIN / 2.0, MARGIN / 2.0);
+        translate.x += val.mins().x.abs();
+        translate.y += val.mins().y.abs();
+
+        let translate_changed = {
+            let mut window = window.borrow_mut();
+            let page = window.get_mut_active_document().get_mut_active_page();
+            if page.get_translate() != &translate {
+                page.set_translate(translate);
+                true
+            } else {
+                false
+            }
+        };
+
+        if size_changed || translate_changed {
+            parent.queue_draw();
+        }
+    }
+
+    // set the zoom level of the active page keeping the page point under
+    // @anchor (in the drawing area coordinates) in its place on the screen,
+    // the center of the visible area is used when @anchor is None.
+    pub fn zoom_at(
+        window: &Rc<RefCell<MainWindow>>, zoom_level: f64, anchor: Option<Point>
+    ) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let zoom_level = zoom_level.max(MIN_ZOOM_LEVEL).min(MAX_ZOOM_LEVEL);
+        let drawing_area = window.borrow().get_drawing_area();
+        let anchor = match anchor {
+            Some(val) => val,
+            None => drawing_area.borrow().view_center(),
+        };
+        let view_pos = drawing_area.borrow().to_view(&anchor);
+
+        let page_pos = {
+            let mut window = window.borrow_mut();
+            let page = window.get_mut_active_document().get_mut_active_page();
+            let page_pos = page.device_to_page(&anchor);
+            page.set_zoom_level(zoom_level);
+            page_pos
+        };
+        HeaderBar::sync_zoom_level(window);
+        // resize the drawing area before scrolling in it
+        DrawingArea::queue_draw(window);
+        DrawingArea::scroll_to(window, page_pos, view_pos);
+    }
+
+    // zoom the active page so @rect (in the page coordinates) fill the
+    // visible area, and show it in the center.
+    pub fn zoom_to_rect(window: &Rc<RefCell<MainWindow>>, rect: &Rectangle) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let width = rect.maxs().x - rect.mins().x;
+        let height = rect.maxs().y - rect.mins().y;
+        if width <= 0.0 || height <= 0.0 {
+            return;
+        }
+
+        let drawing_area = window.borrow().get_drawing_area();
+        let view_size = drawing_area.borrow().view_size();
+        let zoom_level = ((view_size.x - MARGIN) / width)
+            .min((view_size.y - MARGIN) / height)
+            .max(MIN_ZOOM_LEVEL)
+            .min(MAX_ZOOM_LEVEL);
+
+        window.borrow_mut()
+              .get_mut_active_document()
+              .get_mut_active_page()
+              .set_zoom_level(zoom_level);
+        HeaderBar::sync_zoom_level(window);
+        // resize the drawing area before scrolling in it
+        DrawingArea::queue_draw(window);
+
+        let center = Point::new(
+            (rect.mins().x + rect.maxs().x) / 2.0,
+            (rect.mins().y + rect.maxs().y) / 2.0
+        );
+        let view_center = Point::new(view_size.x / 2.0, view_size.y / 2.0);
+        DrawingArea::scroll_to(window, center, view_center);
+    }
+
+    pub fn zoom_to_fit_page(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let size = window.borrow()
+                         .get_active_document()
+                         .get_active_page()
+                         .get_size()
+                         .clone();
+        let rect = Rectangle::new(
+            Point::new(0.0, 0.0),
+            Point::new(size.width as f64, size.height as f64)
+        );
+        DrawingArea::zoom_to_rect(window, &rect);
+    }
+
+    // return false if there is no selected shapes
+    pub fn zoom_to_selection(window: &Rc<RefCell<MainWindow>>) -> bool {
+        if window.borrow().get_documents().len() == 0 {
+            return false;
+        }
+
+        let extents = window.borrow()
+                            .get_active_document()
+                            .get_active_page()
+                            .selected_extents();
+        match extents {
+            Some(rect) => {
+                DrawingArea::zoom_to_rect(window, &rect);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // scroll so @page_pos (in the page coordinates) is shown at @view_pos
+    // (in the visible area coordinates).
+    fn scroll_to(
+        window: &Rc<RefCell<MainWindow>>, page_pos: Point, view_pos: Point
+    ) {
+        // note: the drawing area get its new size when idle, so does the
+        // scroll range.
+        let window = window.clone();
+        gtk::idle_add(move || {
+            if window.borrow().get_documents().len() == 0 {
+                return gtk::Continue(false);
+            }
+
+            let pos = window.borrow()
+                            .get_active_document()
+                            .get_active_page()
+                            .page_to_device(&page_pos);
+            let drawing_area = window.borrow().get_drawing_area();
+            drawing_area.borrow().set_scroll(
+                &Vector::new(pos.x - view_pos.x, pos.y - view_pos.y)
+            );
+            gtk::Continue(false)
+        });
+    }
+
+    // handle the zoom shortcuts and the space key used for panning, return
+    // true if the event was handled.
+    pub fn key_press(window: &Rc<RefCell<MainWindow>>, event: &EventKey) -> bool {
+        let key = event.get_keyval();
+        if key == gdk::enums::key::space {
+            let drawing_area = window.borrow().get_drawing_area();
+            drawing_area.borrow_mut().space_pressed = true;
+            return true;
+        }
+
+        if !event.get_state().contains(gdk::CONTROL_MASK) {
+            return false;
+        }
+
+        let zoom_level = window.borrow()
+                               .get_active_document()
+                               .get_active_page()
+                               .get_zoom_level();
+        if key == gdk::enums::key::_0 {
+            DrawingArea::zoom_to_fit_page(window);
+        } else if key == gdk::enums::key::_1 {
+            DrawingArea::zoom_at(window, 1.0, None);
+        } else if key == gdk::enums::key::_2 {
+            DrawingArea::zoom_to_selection(window);
+        } else if key == gdk::enums::key::plus ||
+                  key == gdk::enums::key::equal {
+            DrawingArea::zoom_at(window, zoom_level * ZOOM_STEP, None);
+        } else if key == gdk::enums::key::minus {
+            DrawingArea::zoom_at(window, zoom_level / ZOOM_STEP, None);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    pub fn key_release(window: &Rc<RefCell<MainWindow>>, event: &EventKey) -> bool {
+        if event.get_keyval() == gdk::enums::key::space {
+            let drawing_area = window.borrow().get_drawing_area();
+            drawing_area.borrow_mut().space_pressed = false;
+            return true;
+        }
+        false
+    }
+
+    // start panning if @event is a middle button press, or a first button
+    // press while space is held. return true if panning started.
+    fn begin_pan(window: &Rc<RefCell<MainWindow>>, event: &gdk::EventButton) -> bool {
+        let drawing_area = window.borrow().get_drawing_area();
+        let mut drawing_area = drawing_area.borrow_mut();
+        let button = event.get_button();
+        if button != 2 && !(button == 1 && drawing_area.space_pressed) {
+            return false;
+        }
+
+        let (x, y) = event.get_root();
+        let scroll = drawing_area.get_scroll();
+        drawing_area.pan_start = Some((Point::new(x, y), scroll));
+        true
+    }
+
+    // scroll the view with the pointer, return true if we are panning
+    fn pan(window: &Rc<RefCell<MainWindow>>, event: &gdk::EventMotion) -> bool {
+        let drawing_area = window.borrow().get_drawing_area();
+        let drawing_area = drawing_area.borrow();
+        let (start, scroll) = match drawing_area.pan_start {
+            Some(ref val) => val.clone(),
+            None => return false,
+        };
+
+        let (x, y) = event.get_root();
+        drawing_area.set_scroll(&Vector::new(
+            scroll.x - (x - start.x),
+            scroll.y - (y - start.y)
+        ));
+        true
+    }
+
+    // return true if we were panning
+    fn end_pan(window: &Rc<RefCell<MainWindow>>) -> bool {
+        let drawing_area = window.borrow().get_drawing_area();
+        let pan_start = drawing_area.borrow_mut().pan_start.take();
+        pan_start.is_some()
+    }
+
     pub fn connect_ui(app: Rc<RefCell<Application>>) {
         let teha_app = app.borrow();
         let window = teha_app.get_main_window();
         let drawing_area = window.borrow().get_drawing_area();
+        let zoom_gesture = drawing_area.borrow().zoom_gesture.clone();
         let drawing_area = drawing_area
             .borrow()
             .parent
@@ -64,46 +459,27 @@ impl DrawingArea {
         // connect drawing_area::draw to document::draw
         {
             let window = window.clone();
-            drawing_area.connect_draw(move |me, cr| {
+            drawing_area.connect_draw(move |_me, cr| {
                 if window.borrow().get_documents().len() == 0 {
                     return Inhibit(false);
                 }
 
-                let page_bound = window.borrow()
-                                       .get_active_document()
-                                       .get_active_page()
-                                       .draw_extents();
-
-                if let Some(val) = page_bound {
-                    let fixed_margin = 100.0;
-                    let width = (val.maxs().x - val.mins().x).abs();
-                    let height = (val.maxs().y - val.mins().y).abs();
-                    me.set_size_request(
-                        (width + fixed_margin) as i32,
-                        (height + fixed_margin) as i32
-                    );
-
-                    let mut translate =
-                        Vector::new(fixed_margin / 2.0, fixed_margin / 2.0);
-                    translate.x += val.mins().x.abs();
-                    translate.y += val.mins().y.abs();
-
-                    {
-                        window.borrow_mut()
-                            .get_mut_active_document()
-                            .get_mut_active_page()
-                            .set_translate(translate);
-                    }
-                }
-
+                // note: gtk clip @cr to the damaged areas
                 window.borrow().get_active_document().draw(cr);
-
-                // FIXME: I'm pretty sure this is not the right way!
-                me.queue_draw();
+                window.borrow().get_rulers().borrow().queue_draw();
                 Inhibit(true)
             });
         }
 
+        // fit the drawing area to the active page when it's first shown and
+        // when it's resized.
+        {
+            let window = window.clone();
+            drawing_area.connect_size_allocate(move |_me, _allocation| {
+                DrawingArea::update_size(&window);
+            });
+        }
+
         // connect drawing_area::connect_motion_notify_event to document::motion_notify
         {
             let window = window.clone();
@@ -112,9 +488,20 @@ impl DrawingArea {
                     return Inhibit(false);
                 }
 
+                if DrawingArea::pan(&window, event) {
+                    return Inhibit(true);
+                }
+
+                // the guide lines are dragged before the shapes
+                let (x, y) = event.get_position();
+                if Rulers::pointer_moved(&window, &Point::new(x, y)) {
+                    return Inhibit(true);
+                }
+
                 window.borrow_mut()
                       .get_mut_active_document()
                       .motion_notify(event);
+                DrawingArea::queue_damage(&window);
                 Inhibit(true)
             });
         }
@@ -128,11 +515,22 @@ impl DrawingArea {
                     return Inhibit(false);
                 }
 
-                let options_widget = window.borrow().get_ed_options();
+                if DrawingArea::begin_pan(&window, event) {
+                    return Inhibit(true);
+                }
+
+                let (x, y) = event.get_position();
+                if event.get_button() == 1 &&
+                   Rulers::begin_drag(&window, &Point::new(x, y)) {
+                    return Inhibit(true);
+                }
+
+                let options_widget = DrawingArea::options_widget(&window);
 
                 window.borrow_mut()
                       .get_mut_active_document()
                       .button_press(event, &options_widget);
+                DrawingArea::queue_damage(&window);
                 Inhibit(true)
             });
         }
@@ -145,9 +543,24 @@ impl DrawingArea {
                     return Inhibit(false);
                 }
 
+                if DrawingArea::end_pan(&window) {
+                    return Inhibit(true);
+                }
+
+                let (x, y) = event.get_position();
+                if Rulers::end_drag(&window, &Point::new(x, y)) {
+                    return Inhibit(true);
+                }
+
                 window.borrow_mut()
                       .get_mut_active_document()
                       .button_release(event);
+                DrawingArea::queue_damage(&window);
+                PagesPanel::update_thumbnail(&window);
+                // the background could have been dragged
+                BackgroundPanel::update(&window);
+                CropPanel::end_crop(&window);
+                DocumentTabs::update_titles(&window);
                 Inhibit(true)
             });
         }
@@ -160,7 +573,36 @@ impl DrawingArea {
                     return Inhibit(false);
                 }
 
+                // save shortcuts
+                if DocumentTabs::key_press(&window, event) {
+                    return Inhibit(true);
+                }
+
+                // copy, cut and paste shortcuts
+                let clipboard = window.borrow().get_clipboard();
+                if clipboard.borrow_mut().key_press(&window, event) {
+                    return Inhibit(true);
+                }
+
+                // snap to grid shortcut
+                let grid_panel = window.borrow().get_grid_panel();
+                if grid_panel.borrow().key_press(event) {
+                    return Inhibit(true);
+                }
+
+                // cancel cropping
+                let crop_panel = window.borrow().get_crop_panel();
+                if crop_panel.borrow().key_press(event) {
+                    return Inhibit(true);
+                }
+
+                // zoom shortcuts and panning with space
+                if DrawingArea::key_press(&window, event) {
+                    return Inhibit(true);
+                }
+
                 window.borrow_mut().get_mut_active_document().key_press(event);
+                DrawingArea::queue_damage(&window);
                 Inhibit(true)
             });
         }
@@ -173,14 +615,83 @@ impl DrawingArea {
                     return Inhibit(false);
                 }
 
+                if DrawingArea::key_release(&window, event) {
+                    return Inhibit(true);
+                }
+
                 let current_document =
                     window.borrow().get_active_document_index();
 
                 window.borrow_mut()
                       .get_mut_documents()[current_document]
                       .key_release(event);
+                DrawingArea::queue_damage(&window);
+                Inhibit(true)
+            });
+        }
+
+        // zoom with Ctrl+scroll, keeping the point under the pointer in its
+        // place.
+        {
+            let window = window.clone();
+            drawing_area.connect_scroll_event(move |_me, event| {
+                if window.borrow().get_documents().len() == 0 ||
+                   !event.get_state().contains(gdk::CONTROL_MASK) {
+                    return Inhibit(false);
+                }
+
+                let factor = match event.get_direction() {
+                    gdk::ScrollDirection::Up => ZOOM_STEP,
+                    gdk::ScrollDirection::Down => 1.0 / ZOOM_STEP,
+                    gdk::ScrollDirection::Smooth => {
+                        let (_, delta_y) = event.get_delta();
+                        ZOOM_STEP.powf(-delta_y)
+                    },
+                    _ => return Inhibit(false),
+                };
+
+                let zoom_level = window.borrow()
+                                       .get_active_document()
+                                       .get_active_page()
+                                       .get_zoom_level();
+                let (x, y) = event.get_position();
+                DrawingArea::zoom_at(
+                    &window, zoom_level * factor, Some(Point::new(x, y))
+                );
                 Inhibit(true)
             });
         }
+
+        // pinch to zoom on touchpads and touchscreens
+        {
+            let window = window.clone();
+            zoom_gesture.connect_begin(move |_me, _sequence| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let zoom_level = window.borrow()
+                                       .get_active_document()
+                                       .get_active_page()
+                                       .get_zoom_level();
+                let drawing_area = window.borrow().get_drawing_area();
+                drawing_area.borrow_mut().gesture_zoom_level = zoom_level;
+            });
+        }
+
+        {
+            let window = window.clone();
+            zoom_gesture.connect_scale_changed(move |me, scale| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let drawing_area = window.borrow().get_drawing_area();
+                let zoom_level = drawing_area.borrow().gesture_zoom_level * scale;
+                let anchor = me.get_bounding_box_center()
+                               .map(|(x, y)| Point::new(x, y));
+                DrawingArea::zoom_at(&window, zoom_level, anchor);
+            });
+        }
     }
 }
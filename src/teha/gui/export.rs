@@ -0,0 +1,433 @@
+//
+// export.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use gtk;
+use gtk::prelude::*;
+
+use gettextrs::*;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::dialogs;
+use core::export::{self, AnimationFormat, AnimationSource, AnimationOptions,
+                   HtmlOptions};
+
+pub struct Export {
+    export_steps: gtk::MenuItem,
+    export_animation: gtk::MenuItem,
+    export_html: gtk::MenuItem,
+    export_markdown: gtk::MenuItem,
+    // the options used by the last exported animation
+    animation_options: AnimationOptions,
+    // true if the last exported animation was made from the reveal steps of
+    // a page instead of the pages
+    animate_steps: bool,
+    // the options used by the last exported HTML file
+    html_options: HtmlOptions,
+}
+
+impl Export {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let export_steps: gtk::MenuItem =
+            builder.get_object("ed_export_steps").unwrap();
+        let export_animation: gtk::MenuItem =
+            builder.get_object("ed_export_animation").unwrap();
+        let export_html: gtk::MenuItem =
+            builder.get_object("ed_export_html").unwrap();
+        let export_markdown: gtk::MenuItem =
+            builder.get_object("ed_export_markdown").unwrap();
+
+        Export {
+            export_steps: export_steps,
+            export_animation: export_animation,
+            export_html: export_html,
+            export_markdown: export_markdown,
+            animation_options: AnimationOptions::default(),
+            animate_steps: false,
+            html_options: HtmlOptions::default(),
+        }
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let export = window.borrow().get_export();
+        let export = export.borrow();
+
+        {
+            let window = window.clone();
+            export.export_steps.connect_activate(move |_me| {
+                Export::export_steps(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            export.export_animation.connect_activate(move |_me| {
+                Export::export_animation(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            export.export_html.connect_activate(move |_me| {
+                Export::export_html(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            export.export_markdown.connect_activate(move |_me| {
+                Export::export_markdown(&window);
+            });
+        }
+    }
+
+    // the name of the active document without its extension
+    fn document_stem(window: &Rc<RefCell<MainWindow>>) -> String {
+        let window = window.borrow();
+        window.get_active_document()
+              .get_path()
+              .file_stem()
+              .and_then(|stem| stem.to_str())
+              .map(export::file_stem)
+              .unwrap_or(String::from("page"))
+    }
+
+    // the name suggested for a file exported from the active document
+    fn suggested_name(
+        window: &Rc<RefCell<MainWindow>>, extension: &str
+    ) -> String {
+        format!("{}.{}", Export::document_stem(window), extension)
+    }
+
+    // write the active page as one image for each of its reveal steps
+    pub fn export_steps(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let parent = window.borrow().get_parent();
+        let folder = match dialogs::choose_folder(
+            &parent, gettext("Export Steps as Images").as_str()
+        ) {
+            Some(val) => val,
+            None => return,
+        };
+
+        let result = {
+            let window = window.borrow();
+            let document = window.get_active_document();
+            export::export_steps(
+                document, document.get_active_page_index(), &folder
+            )
+        };
+
+        if let Err(why) = result {
+            dialogs::show_error(
+                &parent,
+                gettext("Couldn't Export The Steps").as_str(),
+                format!("{}", why).as_str()
+            );
+        }
+    }
+
+    // write the pages of the active document, or the reveal steps of its
+    // active page, as an animated image
+    pub fn export_animation(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let parent = window.borrow().get_parent();
+        let (options, animate_steps) = {
+            let export = window.borrow().get_export();
+            let export = export.borrow();
+            (export.animation_options, export.animate_steps)
+        };
+        let (options, animate_steps) =
+            match ask_animation_options(&parent, &options, animate_steps) {
+                Some(val) => val,
+                None => return,
+            };
+        {
+            let export = window.borrow().get_export();
+            let mut export = export.borrow_mut();
+            export.animation_options = options;
+            export.animate_steps = animate_steps;
+        }
+
+        let name = Export::suggested_name(window, options.format.extension());
+        let path = match dialogs::choose_save_file(
+            &parent, gettext("Export Animation").as_str(), name.as_str()
+        ) {
+            Some(val) => val,
+            None => return,
+        };
+
+        let result = {
+            let window = window.borrow();
+            let document = window.get_active_document();
+            let source = if animate_steps {
+                AnimationSource::Steps(document.get_active_page_index())
+            } else {
+                AnimationSource::Pages
+            };
+            export::export_animation(document, source, &options, &path)
+        };
+
+        if let Err(why) = result {
+            dialogs::show_error(
+                &parent,
+                gettext("Couldn't Export The Animation").as_str(),
+                format!("{}", why).as_str()
+            );
+        }
+    }
+
+    // write all the pages of the active document to a single HTML file
+    pub fn export_html(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let parent = window.borrow().get_parent();
+        let options = window.borrow().get_export().borrow().html_options;
+        let options = match ask_html_options(&parent, &options) {
+            Some(val) => val,
+            None => return,
+        };
+        window.borrow().get_export().borrow_mut().html_options = options;
+
+        let name = Export::suggested_name(window, "html");
+        let path = match dialogs::choose_save_file(
+            &parent, gettext("Export HTML").as_str(), name.as_str()
+        ) {
+            Some(val) => val,
+            None => return,
+        };
+
+        let title = Export::document_stem(window);
+        let result = export::export_html(
+            window.borrow().get_active_document(), &title, &options, &path
+        );
+
+        if let Err(why) = result {
+            dialogs::show_error(
+                &parent,
+                gettext("Couldn't Export The HTML File").as_str(),
+                format!("{}", why).as_str()
+            );
+        }
+    }
+
+    // write the pages of the active document to a Markdown file with their
+    // images in a folder next to it
+    pub fn export_markdown(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let parent = window.borrow().get_parent();
+        let name = Export::suggested_name(window, "md");
+        let path = match dialogs::choose_save_file(
+            &parent, gettext("Export Markdown").as_str(), name.as_str()
+        ) {
+            Some(val) => val,
+            None => return,
+        };
+
+        let title = Export::document_stem(window);
+        let result = export::export_markdown(
+            window.borrow().get_active_document(), &title, &path
+        );
+
+        if let Err(why) = result {
+            dialogs::show_error(
+                &parent,
+                gettext("Couldn't Export The Markdown File").as_str(),
+                format!("{}", why).as_str()
+            );
+        }
+    }
+}
+
+// ask the user for the HTML options starting from @options, return None if
+// the dialog was canceled.
+fn ask_html_options(
+    parent: &gtk::ApplicationWindow, options: &HtmlOptions
+) -> Option<HtmlOptions> {
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some(gettext("Export HTML").as_str()),
+        Some(parent),
+        gtk::DIALOG_MODAL | gtk::DIALOG_DESTROY_WITH_PARENT,
+        &[
+            (gettext("Cancel").as_str(), gtk::ResponseType::Cancel.into()),
+            (gettext("Next").as_str(), gtk::ResponseType::Accept.into()),
+        ]
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept.into());
+
+    let grid = gtk::Grid::new();
+    grid.set_property_margin(10);
+    grid.set_row_spacing(6);
+
+    let steps_check = gtk::CheckButton::new_with_label(
+        gettext("Reveal the steps of the pages with the keyboard").as_str()
+    );
+    steps_check.set_active(options.steps);
+    grid.attach(&steps_check, 0, 0, 1, 1);
+
+    let captions_check = gtk::CheckButton::new_with_label(
+        gettext("Show the page notes as captions").as_str()
+    );
+    captions_check.set_active(options.captions);
+    grid.attach(&captions_check, 0, 1, 1, 1);
+
+    grid.show_all();
+    dialog.get_content_area().add(&grid);
+
+    let accept: i32 = gtk::ResponseType::Accept.into();
+    let response = dialog.run();
+    let result = HtmlOptions {
+        steps: steps_check.get_active(),
+        captions: captions_check.get_active(),
+    };
+    dialog.destroy();
+
+    if response == accept {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+// ask the user for the animation options starting from @options, return the
+// chosen options and whether the frames are the reveal steps of the active
+// page, or None if the dialog was canceled.
+fn ask_animation_options(
+    parent: &gtk::ApplicationWindow,
+    options: &AnimationOptions,
+    animate_steps: bool
+) -> Option<(AnimationOptions, bool)> {
+    let dialog = gtk::Dialog::new_with_buttons(
+        Some(gettext("Export Animation").as_str()),
+        Some(parent),
+        gtk::DIALOG_MODAL | gtk::DIALOG_DESTROY_WITH_PARENT,
+        &[
+            (gettext("Cancel").as_str(), gtk::ResponseType::Cancel.into()),
+            (gettext("Next").as_str(), gtk::ResponseType::Accept.into()),
+        ]
+    );
+    dialog.set_default_response(gtk::ResponseType::Accept.into());
+
+    let create_label = |text: &str| {
+        let label = gtk::Label::new(text);
+        label.set_halign(gtk::Align::End);
+        label
+    };
+
+    let grid = gtk::Grid::new();
+    grid.set_property_margin(10);
+    grid.set_row_spacing(6);
+    grid.set_column_spacing(10);
+
+    let frames_label = create_label(gettext("Frames:").as_str());
+    let frames_combo = gtk::ComboBoxText::new();
+    frames_combo.append_text(gettext("All Pages").as_str());
+    frames_combo.append_text(gettext("Steps of the Active Page").as_str());
+    frames_combo.set_active(if animate_steps { 1 } else { 0 });
+    grid.attach(&frames_label, 0, 0, 1, 1);
+    grid.attach(&frames_combo, 1, 0, 1, 1);
+
+    let format_label = create_label(gettext("Format:").as_str());
+    let format_combo = gtk::ComboBoxText::new();
+    format_combo.append_text("GIF");
+    format_combo.append_text("APNG");
+    format_combo.set_active(match options.format {
+        AnimationFormat::Gif => 0,
+        AnimationFormat::Apng => 1,
+    });
+    grid.attach(&format_label, 0, 1, 1, 1);
+    grid.attach(&format_combo, 1, 1, 1, 1);
+
+    let duration_label = create_label(gettext("Frame Duration (ms):").as_str());
+    let duration_spin = gtk::SpinButton::new_with_range(20.0, 60000.0, 100.0);
+    duration_spin.set_value(options.frame_duration as f64);
+    grid.attach(&duration_label, 0, 2, 1, 1);
+    grid.attach(&duration_spin, 1, 2, 1, 1);
+
+    let fade_label = create_label(gettext("Cross-Fade (ms):").as_str());
+    let fade_spin = gtk::SpinButton::new_with_range(0.0, 10000.0, 100.0);
+    fade_spin.set_tooltip_text(
+        gettext("0 switch the frames without fading").as_str()
+    );
+    fade_spin.set_value(options.fade_duration as f64);
+    grid.attach(&fade_label, 0, 3, 1, 1);
+    grid.attach(&fade_spin, 1, 3, 1, 1);
+
+    let loop_label = create_label(gettext("Loop Count:").as_str());
+    let loop_spin = gtk::SpinButton::new_with_range(0.0, 1000.0, 1.0);
+    loop_spin.set_tooltip_text(
+        gettext("0 play the animation forever").as_str()
+    );
+    loop_spin.set_value(options.loop_count as f64);
+    grid.attach(&loop_label, 0, 4, 1, 1);
+    grid.attach(&loop_spin, 1, 4, 1, 1);
+
+    let width_label = create_label(gettext("Max Width:").as_str());
+    let width_spin = gtk::SpinButton::new_with_range(0.0, 10000.0, 10.0);
+    width_spin.set_tooltip_text(
+        gettext("0 keep the size of the pages").as_str()
+    );
+    width_spin.set_value(options.max_width.unwrap_or(0) as f64);
+    grid.attach(&width_label, 0, 5, 1, 1);
+    grid.attach(&width_spin, 1, 5, 1, 1);
+
+    grid.show_all();
+    dialog.get_content_area().add(&grid);
+
+    let accept: i32 = gtk::ResponseType::Accept.into();
+    let response = dialog.run();
+    let max_width = width_spin.get_value_as_int();
+    let result = (
+        AnimationOptions {
+            format: if format_combo.get_active() == 1 {
+                AnimationFormat::Apng
+            } else {
+                AnimationFormat::Gif
+            },
+            frame_duration: duration_spin.get_value_as_int() as u32,
+            fade_duration: fade_spin.get_value_as_int() as u32,
+            loop_count: loop_spin.get_value_as_int() as u32,
+            max_width: if max_width > 0 { Some(max_width) } else { None },
+        },
+        frames_combo.get_active() == 1
+    );
+    dialog.destroy();
+
+    if response == accept {
+        Some(result)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,298 @@
+//
+// file_drop.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use gtk;
+use gtk::prelude::*;
+use gdk;
+use gio::{self, FileExt};
+
+use gettextrs::*;
+
+use super::*;
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use super::pages_panel::PagesPanel;
+use super::header_bar::HeaderBar;
+use super::dialogs;
+use core::Document;
+use core::draw_objects::{Image, Background, Select};
+use error::Error;
+use common::types::*;
+use common::image::Picture;
+
+// where the files were dropped
+enum DropTarget {
+    // the drawing area, at this point in its coordinates
+    Canvas(Point),
+    Pages,
+    Window,
+}
+
+// dropped .teha files are opened, and dropped images are added to the
+// active page, added as new pages from the pages panel or start new
+// documents from the start-up screen.
+pub struct FileDrop {
+    drawing_area: gtk::DrawingArea,
+}
+
+impl FileDrop {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let drawing_area: gtk::DrawingArea =
+            builder.get_object("drawing_area").unwrap();
+
+        FileDrop {
+            drawing_area: drawing_area,
+        }
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let file_drop = window.borrow().get_file_drop();
+        let file_drop = file_drop.borrow();
+
+        // images dropped on the canvas become image shapes at the drop point
+        {
+            let app = app.clone();
+            let drawing_area = file_drop.drawing_area.clone();
+            drawing_area.drag_dest_set(
+                gtk::DEST_DEFAULT_ALL, &vec![], gdk::ACTION_COPY
+            );
+            drawing_area.drag_dest_add_uri_targets();
+            drawing_area.connect_drag_data_received(
+                move |_me, _context, x, y, selection_data, _info, _time| {
+                let target = DropTarget::Canvas(Point::new(x as f64, y as f64));
+                FileDrop::open_files(&app, selection_data.get_uris(), target);
+            });
+        }
+
+        // images dropped on the pages panel become new pages. note: the rows
+        // only accept dragged pages, so the other drops reach the panel.
+        {
+            let app = app.clone();
+            let pages_panel = window.borrow().get_pages_panel();
+            let pages_panel = pages_panel.borrow().get_parent();
+            pages_panel.drag_dest_set(
+                gtk::DEST_DEFAULT_ALL, &vec![], gdk::ACTION_COPY
+            );
+            pages_panel.drag_dest_add_uri_targets();
+            pages_panel.connect_drag_data_received(
+                move |_me, _context, _x, _y, selection_data, _info, _time| {
+                let target = DropTarget::Pages;
+                FileDrop::open_files(&app, selection_data.get_uris(), target);
+            });
+        }
+
+        // the start-up screen and the rest of the window
+        {
+            let app = app.clone();
+            let parent = window.borrow().get_parent();
+            parent.drag_dest_set(
+                gtk::DEST_DEFAULT_ALL, &vec![], gdk::ACTION_COPY
+            );
+            parent.drag_dest_add_uri_targets();
+            parent.connect_drag_data_received(
+                move |_me, _context, _x, _y, selection_data, _info, _time| {
+                let target = DropTarget::Window;
+                FileDrop::open_files(&app, selection_data.get_uris(), target);
+            });
+        }
+    }
+
+    // open the dropped .teha files and add the dropped images to the active
+    // document depending on @target. images dropped while no document is
+    // being edited start new documents, and they are ignored while
+    // previewing since the preview can't be edited.
+    fn open_files(
+        app: &Rc<RefCell<Application>>, uris: Vec<String>, target: DropTarget
+    ) {
+        let window = app.borrow().get_main_window();
+        let parent = window.borrow().get_parent();
+        let (editing, previewing) = match app.borrow().get_view_mode() {
+            ViewMode::Editing => {
+                (window.borrow().get_documents().len() > 0, false)
+            },
+            ViewMode::Previewing => (false, true),
+            _ => (false, false),
+        };
+
+        let mut opened = false;
+        let mut added = false;
+        let mut pages_added = false;
+        for uri in uris.iter() {
+            let path = match gio::File::new_for_uri(uri).get_path() {
+                Some(val) => val,
+                None => continue,
+            };
+
+            if path.extension().map_or(false, |ext| ext == "teha") {
+                match FileDrop::open_document(&window, path) {
+                    Ok(()) => opened = true,
+                    Err(why) => dialogs::show_error(
+                        &parent,
+                        gettext("Couldn't open the document").as_str(),
+                        format!("{}", why).as_str()
+                    ),
+                }
+                continue;
+            }
+
+            if previewing {
+                continue;
+            }
+
+            let picture = match Picture::from_file(&path) {
+                Ok(val) => val,
+                Err(why) => {
+                    dialogs::show_error(
+                        &parent,
+                        gettext("Couldn't open the image").as_str(),
+                        format!("{}", why).as_str()
+                    );
+                    continue;
+                },
+            };
+
+            if !editing {
+                FileDrop::new_document(&window, &path, picture);
+                opened = true;
+                continue;
+            }
+
+            match target {
+                DropTarget::Canvas(ref pos) => {
+                    FileDrop::add_image(&window, picture, Some(pos));
+                    added = true;
+                },
+                DropTarget::Window => {
+                    FileDrop::add_image(&window, picture, None);
+                    added = true;
+                },
+                DropTarget::Pages => {
+                    FileDrop::add_page(&window, picture);
+                    pages_added = true;
+                },
+            }
+        }
+
+        if added {
+            DocumentTabs::mark_modified(&window);
+            PagesPanel::update_thumbnail(&window);
+        }
+        if pages_added {
+            PagesPanel::update(&window);
+            HeaderBar::sync_zoom_level(&window);
+        }
+        if opened {
+            app.borrow_mut().update_view(ViewMode::Editing);
+            DocumentTabs::update(app);
+            DocumentTabs::restore_view(&window);
+        }
+    }
+
+    // open the document saved in @path, or switch to it if it's already open
+    fn open_document(
+        window: &Rc<RefCell<MainWindow>>, path: PathBuf
+    ) -> Result<(), Error> {
+        let index = window.borrow()
+                          .get_documents()
+                          .iter()
+                          .position(|document| document.get_path() == &path);
+        if let Some(index) = index {
+            window.borrow_mut().switch_document(index);
+            return Ok(());
+        }
+
+        let document = Document::open(path)?;
+        window.borrow_mut().new_documents(document);
+        Ok(())
+    }
+
+    // add @picture as a selected image shape centered at @pos (in the
+    // drawing area coordinates), or at the page center.
+    fn add_image(
+        window: &Rc<RefCell<MainWindow>>, picture: Picture, pos: Option<&Point>
+    ) {
+        let mut window = window.borrow_mut();
+        let page = window.get_mut_active_document().get_mut_active_page();
+        let center = match pos {
+            Some(pos) => page.device_to_page(pos),
+            None => Point::new(
+                page.get_size().width as f64 / 2.0,
+                page.get_size().height as f64 / 2.0
+            ),
+        };
+        let mut image = Image::new(picture, center);
+        page.remove_shapes_in_creating_mode();
+        page.unselect_all_shapes();
+        image.select();
+        page.add_shape(Box::new(image));
+    }
+
+    // add a page sized to @picture with it as the background after the
+    // active page.
+    fn add_page(window: &Rc<RefCell<MainWindow>>, picture: Picture) {
+        let mut window = window.borrow_mut();
+        let document = window.get_mut_active_document();
+        {
+            let page = document.get_mut_active_page();
+            page.remove_shapes_in_creating_mode();
+            page.unselect_all_shapes();
+        }
+        document.add_page();
+        let page = document.get_mut_active_page();
+        page.set_size(Size::new(picture.get_width(), picture.get_height()));
+        page.set_background(Some(Background::new(picture)));
+    }
+
+    // start a document sized to @picture with it as the page background. the
+    // document isn't written until the user save it, then it's saved next to
+    // the image at @image_path.
+    fn new_document(
+        window: &Rc<RefCell<MainWindow>>, image_path: &Path, picture: Picture
+    ) {
+        let size = Size::new(picture.get_width(), picture.get_height());
+        let mut document =
+            Document::new(1, unused_path(image_path), size, false);
+        document.get_mut_active_page()
+                .set_background(Some(Background::new(picture)));
+        document.set_modified(true);
+        window.borrow_mut().new_documents(document);
+    }
+}
+
+// @image_path with the .teha extension, a number is added to the name if
+// there is a file with that name already.
+fn unused_path(image_path: &Path) -> PathBuf {
+    let mut path = image_path.with_extension("teha");
+    let stem = image_path.file_stem()
+                         .map(|stem| stem.to_string_lossy().into_owned())
+                         .unwrap_or(String::from("document"));
+    let mut number = 2;
+    while path.exists() {
+        path = image_path.with_file_name(format!("{}-{}.teha", stem, number));
+        number += 1;
+    }
+    path
+}
@@ -0,0 +1,263 @@
+//
+// grid_panel.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+use gtk;
+use gtk::prelude::*;
+use gdk::{self, EventKey};
+
+use gettextrs::*;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use core::draw_objects::Page;
+use common::types::*;
+
+pub struct GridPanel {
+    parent: gtk::Grid,
+    show_grid: gtk::Switch,
+    color: gtk::ColorChooser,
+    spacing: gtk::SpinButton,
+    subdivisions: gtk::SpinButton,
+    snap_to_grid: gtk::Switch,
+    // true while the widgets are filled from the active page, so their
+    // signals don't mark the document as modified.
+    updating: Rc<Cell<bool>>,
+}
+
+impl GridPanel {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let side_panel: gtk::Notebook =
+            builder.get_object("ed_side_panel").unwrap();
+
+        let create_label = |text: &str| {
+            let label = gtk::Label::new(text);
+            label.set_halign(gtk::Align::End);
+            label
+        };
+
+        let grid = gtk::Grid::new();
+        grid.set_property_margin(10);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(10);
+
+        let show_grid_label = create_label(gettext("Show Grid:").as_str());
+        let show_grid = gtk::Switch::new();
+        show_grid.set_halign(gtk::Align::Start);
+        grid.attach(&show_grid_label, 0, 0, 1, 1);
+        grid.attach(&show_grid, 1, 0, 1, 1);
+
+        let color_label = create_label(gettext("Color:").as_str());
+        let color_button =
+            gtk::ColorButton::new_with_rgba(&grid_rgba(&default_color()));
+        color_button.set_title(gettext("Grid Color").as_str());
+        color_button.set_halign(gtk::Align::Start);
+        let color = color_button
+            .clone()
+            .dynamic_cast::<gtk::ColorChooser>()
+            .expect("GtkButtonChooser does implmente GtkColorChooser \
+                     interface");
+        grid.attach(&color_label, 0, 1, 1, 1);
+        grid.attach(&color_button, 1, 1, 1, 1);
+
+        let spacing_label = create_label(gettext("Spacing:").as_str());
+        let spacing = gtk::SpinButton::new_with_range(5.0, 500.0, 5.0);
+        spacing.set_tooltip_text(
+            gettext("The distance between the main grid lines").as_str()
+        );
+        grid.attach(&spacing_label, 0, 2, 1, 1);
+        grid.attach(&spacing, 1, 2, 1, 1);
+
+        let subdivisions_label = create_label(gettext("Subdivisions:").as_str());
+        let subdivisions = gtk::SpinButton::new_with_range(1.0, 20.0, 1.0);
+        subdivisions.set_tooltip_text(
+            gettext("The number of cells between the main grid lines").as_str()
+        );
+        grid.attach(&subdivisions_label, 0, 3, 1, 1);
+        grid.attach(&subdivisions, 1, 3, 1, 1);
+
+        let snap_to_grid_label = create_label(gettext("Snap to Grid:").as_str());
+        let snap_to_grid = gtk::Switch::new();
+        snap_to_grid.set_halign(gtk::Align::Start);
+        snap_to_grid.set_tooltip_text(
+            gettext("Toggle snapping with Ctrl+'").as_str()
+        );
+        grid.attach(&snap_to_grid_label, 0, 4, 1, 1);
+        grid.attach(&snap_to_grid, 1, 4, 1, 1);
+
+        grid.show_all();
+        let tab_label = gtk::Label::new(gettext("Grid").as_str());
+        side_panel.append_page(&grid, Some(&tab_label));
+
+        GridPanel {
+            parent: grid,
+            show_grid: show_grid,
+            color: color,
+            spacing: spacing,
+            subdivisions: subdivisions,
+            snap_to_grid: snap_to_grid,
+            updating: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn get_parent(&self) -> gtk::Grid {
+        self.parent.clone()
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let grid_panel = window.borrow().get_grid_panel();
+        let grid_panel = grid_panel.borrow();
+
+        // run @change on the active page and mark the document as modified,
+        // unless the widgets are being updated from the page.
+        let edit_page = {
+            let window = window.clone();
+            let updating = grid_panel.updating.clone();
+            Rc::new(move |change: &Fn(&mut Page)| {
+                if updating.get() || window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    change(window.get_mut_active_document().get_mut_active_page());
+                }
+                DocumentTabs::mark_modified(&window);
+            })
+        };
+
+        {
+            let edit_page = edit_page.clone();
+            let color = grid_panel.color.clone();
+            grid_panel.show_grid.connect_property_active_notify(move |me| {
+                let grid = if me.get_active() {
+                    Some(grid_color(&color.get_rgba()))
+                } else {
+                    None
+                };
+                edit_page(&|page| page.set_grid(grid));
+            });
+        }
+
+        {
+            let edit_page = edit_page.clone();
+            let show_grid = grid_panel.show_grid.clone();
+            grid_panel.color.connect_property_rgba_notify(move |me| {
+                if !show_grid.get_active() {
+                    return;
+                }
+                let grid = grid_color(&me.get_rgba());
+                edit_page(&|page| page.set_grid(Some(grid)));
+            });
+        }
+
+        {
+            let edit_page = edit_page.clone();
+            grid_panel.spacing.connect_property_value_notify(move |me| {
+                let spacing = me.get_value();
+                edit_page(&|page| page.set_grid_spacing(spacing));
+            });
+        }
+
+        {
+            let edit_page = edit_page.clone();
+            grid_panel.subdivisions.connect_property_value_notify(move |me| {
+                let subdivisions = me.get_value_as_int() as u32;
+                edit_page(&|page| page.set_grid_subdivisions(subdivisions));
+            });
+        }
+
+        {
+            let edit_page = edit_page.clone();
+            grid_panel.snap_to_grid.connect_property_active_notify(move |me| {
+                let snap_to_grid = me.get_active();
+                edit_page(&|page| page.set_snap_to_grid(snap_to_grid));
+            });
+        }
+    }
+
+    // show the grid settings of the active page
+    pub fn update(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let grid_panel = window.borrow().get_grid_panel();
+        let grid_panel = grid_panel.borrow();
+        let (grid, spacing, subdivisions, snap_to_grid) = {
+            let window = window.borrow();
+            let page = window.get_active_document().get_active_page();
+            (
+                page.get_grid().clone(),
+                page.get_grid_spacing(),
+                page.get_grid_subdivisions(),
+                page.get_snap_to_grid(),
+            )
+        };
+
+        grid_panel.updating.set(true);
+        grid_panel.show_grid.set_active(grid.is_some());
+        if let Some(color) = grid {
+            grid_panel.color.set_rgba(&grid_rgba(&color));
+        }
+        grid_panel.spacing.set_value(spacing);
+        grid_panel.subdivisions.set_value(subdivisions as f64);
+        grid_panel.snap_to_grid.set_active(snap_to_grid);
+        grid_panel.updating.set(false);
+    }
+
+    // handle Ctrl+' that toggle snapping to the grid, return true if the
+    // event was handled.
+    pub fn key_press(&self, event: &EventKey) -> bool {
+        if !event.get_state().contains(gdk::CONTROL_MASK) {
+            return false;
+        }
+
+        if event.get_keyval() == gdk::enums::key::apostrophe {
+            // the switch signal update the page
+            self.snap_to_grid.set_active(!self.snap_to_grid.get_active());
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// #D3D3D3
+fn default_color() -> RgbColor {
+    RgbColor::new(0.83, 0.83, 0.83)
+}
+
+fn grid_rgba(color: &RgbColor) -> gdk::RGBA {
+    gdk::RGBA {
+        red: color.red,
+        green: color.green,
+        blue: color.blue,
+        alpha: 1.0,
+    }
+}
+
+fn grid_color(rgba: &gdk::RGBA) -> RgbColor {
+    RgbColor::new(rgba.red, rgba.green, rgba.blue)
+}
@@ -29,6 +29,9 @@ use gettextrs::*;
 
 use super::*;
 use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use super::drawing_area::DrawingArea;
 use common;
 use core::Document;
 use common::types::*;
@@ -41,6 +44,12 @@ pub struct HeaderBar {
     crwo_back: gtk::Button,         // create work back
     crwo_forward: gtk::Button,      // create work forward
     ed_zoom_level: gtk::Scale,      // editor zoom level
+    ed_zoom_fit: gtk::Button,       // editor zoom to fit page
+    ed_zoom_selection: gtk::Button, // editor zoom to selection
+    ed_zoom_original: gtk::Button,  // editor zoom to 100%
+    ed_title: gtk::Label,           // editor title
+    ed_back: gtk::Button,           // editor back
+    ed_save: gtk::Button,           // editor save
 }
 
 impl HeaderBar {
@@ -59,6 +68,18 @@ impl HeaderBar {
             builder.get_object("crwo_forward").unwrap();
         let ed_zoom_level: gtk::Scale =
             builder.get_object("ed_zoom_level").unwrap();
+        let ed_zoom_fit: gtk::Button =
+            builder.get_object("ed_zoom_fit").unwrap();
+        let ed_zoom_selection: gtk::Button =
+            builder.get_object("ed_zoom_selection").unwrap();
+        let ed_zoom_original: gtk::Button =
+            builder.get_object("ed_zoom_original").unwrap();
+        let ed_title: gtk::Label =
+            builder.get_object("ed_title").unwrap();
+        let ed_back: gtk::Button =
+            builder.get_object("ed_back").unwrap();
+        let ed_save: gtk::Button =
+            builder.get_object("ed_save").unwrap();
 
         let teha_headerbar = HeaderBar {
             parent: header_bar,
@@ -68,6 +89,12 @@ impl HeaderBar {
             crwo_back: crwo_back,
             crwo_forward: crwo_forward,
             ed_zoom_level: ed_zoom_level,
+            ed_zoom_fit: ed_zoom_fit,
+            ed_zoom_selection: ed_zoom_selection,
+            ed_zoom_original: ed_zoom_original,
+            ed_title: ed_title,
+            ed_back: ed_back,
+            ed_save: ed_save,
         };
 
         teha_headerbar
@@ -77,6 +104,10 @@ impl HeaderBar {
         self.parent.clone()
     }
 
+    pub fn set_title(&self, title: &str) {
+        self.ed_title.set_text(title);
+    }
+
     pub fn connect_ui(app: Rc<RefCell<Application>>) {
         let teha_app = app.borrow();
         let window = teha_app.get_main_window();
@@ -196,6 +227,10 @@ impl HeaderBar {
                     )
                 );
 
+                // write the new document to the created file
+                let index = window.borrow().get_active_document_index();
+                DocumentTabs::save_document(&window, index);
+
                 // cleanup the widgets for reuse
                 crwo_file_name.set_text("");
                 crwo_file_location.unselect_all();
@@ -207,6 +242,8 @@ impl HeaderBar {
                 }
 
                 app.borrow_mut().update_view(ViewMode::Editing);
+                DocumentTabs::update(&app);
+                DocumentTabs::restore_view(&window);
             });
         }
 
@@ -221,12 +258,83 @@ impl HeaderBar {
             let ed_zoom_level = header_bar.borrow().ed_zoom_level.clone();
             let window = window.clone();
             ed_zoom_level.connect_value_changed(move |me| {
-                window.borrow_mut()
-                      .get_mut_active_document()
-                      .get_mut_active_page()
-                      .set_zoom_level(me.get_value());
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                // ignore the value set by sync_zoom_level()
+                let zoom_level = window.borrow()
+                                       .get_active_document()
+                                       .get_active_page()
+                                       .get_zoom_level();
+                if (me.get_value() - zoom_level).abs() < 1e-6 {
+                    return;
+                }
+
+                DrawingArea::zoom_at(&window, me.get_value(), None);
+            });
+        }
+
+        {
+            let ed_zoom_fit = header_bar.borrow().ed_zoom_fit.clone();
+            let window = window.clone();
+            ed_zoom_fit.connect_clicked(move |_me| {
+                DrawingArea::zoom_to_fit_page(&window);
+            });
+        }
+
+        {
+            let ed_zoom_selection = header_bar.borrow().ed_zoom_selection.clone();
+            let window = window.clone();
+            ed_zoom_selection.connect_clicked(move |_me| {
+                DrawingArea::zoom_to_selection(&window);
             });
         }
+
+        {
+            let ed_zoom_original = header_bar.borrow().ed_zoom_original.clone();
+            let window = window.clone();
+            ed_zoom_original.connect_clicked(move |_me| {
+                DrawingArea::zoom_at(&window, 1.0, None);
+            });
+        }
+
+        {
+            let ed_save = header_bar.borrow().ed_save.clone();
+            let window = window.clone();
+            ed_save.connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let index = window.borrow().get_active_document_index();
+                DocumentTabs::save_document(&window, index);
+            });
+        }
+
+        {
+            // going back to the start up screen close all documents
+            let ed_back = header_bar.borrow().ed_back.clone();
+            let app = app.clone();
+            ed_back.connect_clicked(move |_me| {
+                DocumentTabs::close_all_documents(&app);
+            });
+        }
+    }
+
+    // show the zoom level of the active page in the zoom scale
+    pub fn sync_zoom_level(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let header_bar = window.borrow().get_header_bar();
+        let ed_zoom_level = header_bar.borrow().ed_zoom_level.clone();
+        let zoom_level = window.borrow()
+                               .get_active_document()
+                               .get_active_page()
+                               .get_zoom_level();
+        ed_zoom_level.set_value(zoom_level);
     }
 
     pub fn update_view(&mut self, view_mode: ViewMode) {
@@ -0,0 +1,406 @@
+//
+// layers_panel.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use gtk;
+use gtk::prelude::*;
+
+use gettextrs::*;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use core::draw_objects::{Name, Lock, Visible};
+
+// information needed to build one row in the layers list
+struct LayerRow {
+    name: String,
+    visible: bool,
+    locked: bool,
+}
+
+pub struct LayersPanel {
+    parent: gtk::Box,
+    list: gtk::ListBox,
+    add: gtk::Button,
+    remove: gtk::Button,
+    raise: gtk::Button,
+    lower: gtk::Button,
+    move_selection: gtk::Button,
+}
+
+impl LayersPanel {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let side_panel: gtk::Notebook =
+            builder.get_object("ed_side_panel").unwrap();
+
+        let create_button = |icon: &str, tooltip: &str| {
+            let button = gtk::Button::new_from_icon_name(icon, 1);
+            button.set_tooltip_text(tooltip);
+            button
+        };
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::Browse);
+        let scrolled = gtk::ScrolledWindow::new(None, None);
+        scrolled.set_vexpand(true);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&list);
+
+        let add = create_button(
+            "list-add-symbolic", gettext("Add Layer").as_str()
+        );
+        let remove = create_button(
+            "list-remove-symbolic", gettext("Remove Layer").as_str()
+        );
+        let raise = create_button(
+            "go-up-symbolic", gettext("Raise Layer").as_str()
+        );
+        let lower = create_button(
+            "go-down-symbolic", gettext("Lower Layer").as_str()
+        );
+        let move_selection = create_button(
+            "document-send-symbolic",
+            gettext("Move Selected Shapes to This Layer").as_str()
+        );
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        buttons.get_style_context()
+               .map(|style| style.add_class("linked"));
+        buttons.pack_start(&add, false, false, 0);
+        buttons.pack_start(&remove, false, false, 0);
+        buttons.pack_start(&raise, false, false, 0);
+        buttons.pack_start(&lower, false, false, 0);
+        buttons.pack_end(&move_selection, false, false, 0);
+
+        let parent = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        parent.set_border_width(6);
+        parent.pack_start(&scrolled, true, true, 0);
+        parent.pack_start(&buttons, false, false, 0);
+        parent.show_all();
+
+        let tab_label = gtk::Label::new(gettext("Layers").as_str());
+        side_panel.append_page(&parent, Some(&tab_label));
+
+        LayersPanel {
+            parent: parent,
+            list: list,
+            add: add,
+            remove: remove,
+            raise: raise,
+            lower: lower,
+            move_selection: move_selection,
+        }
+    }
+
+    pub fn get_parent(&self) -> gtk::Box {
+        self.parent.clone()
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let layers_panel = window.borrow().get_layers_panel();
+
+        // choosing a row make its layer the active one, new shapes will be
+        // added to it.
+        {
+            let window = window.clone();
+            layers_panel.borrow()
+                        .list
+                        .connect_row_selected(move |_me, row| {
+                let row = match *row {
+                    Some(ref val) => val.clone(),
+                    None => return,
+                };
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let mut window = window.borrow_mut();
+                let page = window.get_mut_active_document()
+                                 .get_mut_active_page();
+                let n_layers = page.get_layers().len();
+                let index = row.get_index() as usize;
+                if index < n_layers {
+                    page.set_active_layer_index(n_layers - 1 - index);
+                }
+            });
+        }
+
+        {
+            let window = window.clone();
+            layers_panel.borrow()
+                        .add
+                        .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                window.borrow_mut()
+                      .get_mut_active_document()
+                      .get_mut_active_page()
+                      .add_layer();
+                LayersPanel::update(&window);
+                DocumentTabs::mark_modified(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            layers_panel.borrow()
+                        .remove
+                        .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    let page = window.get_mut_active_document()
+                                     .get_mut_active_page();
+                    let index = page.get_active_layer_index();
+                    page.remove_layer(index);
+                }
+                LayersPanel::update(&window);
+                DocumentTabs::mark_modified(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            layers_panel.borrow()
+                        .raise
+                        .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    let page = window.get_mut_active_document()
+                                     .get_mut_active_page();
+                    let index = page.get_active_layer_index();
+                    page.move_layer_up(index);
+                }
+                LayersPanel::update(&window);
+                DocumentTabs::mark_modified(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            layers_panel.borrow()
+                        .lower
+                        .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    let page = window.get_mut_active_document()
+                                     .get_mut_active_page();
+                    let index = page.get_active_layer_index();
+                    page.move_layer_down(index);
+                }
+                LayersPanel::update(&window);
+                DocumentTabs::mark_modified(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            layers_panel.borrow()
+                        .move_selection
+                        .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    let page = window.get_mut_active_document()
+                                     .get_mut_active_page();
+                    let index = page.get_active_layer_index();
+                    page.move_selected_shapes_to_layer(index);
+                }
+                DocumentTabs::mark_modified(&window);
+            });
+        }
+    }
+
+    // rebuild the layers list from the active page, the top layer is shown
+    // first.
+    pub fn update(window: &Rc<RefCell<MainWindow>>) {
+        let layers_panel = window.borrow().get_layers_panel();
+        let list = layers_panel.borrow().list.clone();
+
+        for child in list.get_children() {
+            list.remove(&child);
+        }
+
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let (rows, active_layer_index) = {
+            let window = window.borrow();
+            let page = window.get_active_document().get_active_page();
+            let rows: Vec<LayerRow> = page.get_layers()
+                .iter()
+                .map(|layer| LayerRow {
+                    name: layer.name(),
+                    visible: layer.is_visible(),
+                    locked: layer.is_locked(),
+                })
+                .collect();
+            (rows, page.get_active_layer_index())
+        };
+
+        let n_layers = rows.len();
+        for (index, row) in rows.iter().enumerate().rev() {
+            let visible_button = gtk::ToggleButton::new();
+            visible_button.set_relief(gtk::ReliefStyle::None);
+            visible_button.set_tooltip_text(gettext("Show/Hide Layer").as_str());
+            visible_button.set_active(row.visible);
+            visible_button.set_image(&gtk::Image::new_from_icon_name(
+                if row.visible {
+                    "view-reveal-symbolic"
+                } else {
+                    "view-conceal-symbolic"
+                },
+                1
+            ));
+
+            let lock_button = gtk::ToggleButton::new();
+            lock_button.set_relief(gtk::ReliefStyle::None);
+            lock_button.set_tooltip_text(gettext("Lock/Unlock Layer").as_str());
+            lock_button.set_active(row.locked);
+            lock_button.set_image(&gtk::Image::new_from_icon_name(
+                if row.locked {
+                    "changes-prevent-symbolic"
+                } else {
+                    "changes-allow-symbolic"
+                },
+                1
+            ));
+
+            let name_entry = gtk::Entry::new();
+            name_entry.set_has_frame(false);
+            name_entry.set_hexpand(true);
+            name_entry.set_text(row.name.as_str());
+
+            // signals are connected after setting the initial state, so
+            // building the rows doesn't modify the page.
+            {
+                let window = window.clone();
+                visible_button.connect_toggled(move |me| {
+                    {
+                        let mut window = window.borrow_mut();
+                        let page = window.get_mut_active_document()
+                                         .get_mut_active_page();
+                        if let Some(layer) = page.get_mut_layers()
+                                                 .get_mut(index) {
+                            if me.get_active() {
+                                layer.show();
+                            } else {
+                                layer.hide();
+                            }
+                        }
+                    }
+                    DocumentTabs::mark_modified(&window);
+                    me.set_image(&gtk::Image::new_from_icon_name(
+                        if me.get_active() {
+                            "view-reveal-symbolic"
+                        } else {
+                            "view-conceal-symbolic"
+                        },
+                        1
+                    ));
+                });
+            }
+
+            {
+                let window = window.clone();
+                lock_button.connect_toggled(move |me| {
+                    {
+                        let mut window = window.borrow_mut();
+                        let page = window.get_mut_active_document()
+                                         .get_mut_active_page();
+                        if let Some(layer) = page.get_mut_layers()
+                                                 .get_mut(index) {
+                            if me.get_active() {
+                                layer.lock();
+                                layer.unselect_all_shapes();
+                            } else {
+                                layer.unlock();
+                            }
+                        }
+                    }
+                    DocumentTabs::mark_modified(&window);
+                    me.set_image(&gtk::Image::new_from_icon_name(
+                        if me.get_active() {
+                            "changes-prevent-symbolic"
+                        } else {
+                            "changes-allow-symbolic"
+                        },
+                        1
+                    ));
+                });
+            }
+
+            {
+                let window = window.clone();
+                name_entry.connect_changed(move |me| {
+                    let text = match me.get_text() {
+                        Some(val) => val,
+                        None => return,
+                    };
+                    {
+                        let mut window = window.borrow_mut();
+                        let page = window.get_mut_active_document()
+                                         .get_mut_active_page();
+                        if let Some(layer) = page.get_mut_layers()
+                                                 .get_mut(index) {
+                            layer.set_name(&text);
+                        }
+                    }
+                    DocumentTabs::mark_modified(&window);
+                });
+            }
+
+            let row_box = gtk::Box::new(gtk::Orientation::Horizontal, 2);
+            row_box.pack_start(&visible_button, false, false, 0);
+            row_box.pack_start(&lock_button, false, false, 0);
+            row_box.pack_start(&name_entry, true, true, 0);
+            row_box.show_all();
+            list.add(&row_box);
+        }
+
+        if active_layer_index < n_layers {
+            let row_index = (n_layers - 1 - active_layer_index) as i32;
+            if let Some(row) = list.get_row_at_index(row_index) {
+                list.select_row(&row);
+            }
+        }
+    }
+}
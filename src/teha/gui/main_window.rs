@@ -28,6 +28,19 @@ use super::header_bar::HeaderBar;
 use super::drawing_area::DrawingArea;
 use super::toolbar::Toolbar;
 use super::controlbar::Controlbar;
+use super::clipboard::Clipboard;
+use super::layers_panel::LayersPanel;
+use super::pages_panel::PagesPanel;
+use super::document_tabs::DocumentTabs;
+use super::autosave::Autosave;
+use super::grid_panel::GridPanel;
+use super::rulers::Rulers;
+use super::preview::Preview;
+use super::export::Export;
+use super::notes_panel::NotesPanel;
+use super::background_panel::BackgroundPanel;
+use super::file_drop::FileDrop;
+use super::crop_panel::CropPanel;
 use core::Document;
 
 // note: acronyms names used here refer to:
@@ -41,6 +54,19 @@ pub struct MainWindow {
     drawing_area: Rc<RefCell<DrawingArea>>,
     toolbar: Rc<RefCell<Toolbar>>,
     controlbar: Rc<RefCell<Controlbar>>,
+    clipboard: Rc<RefCell<Clipboard>>,
+    layers_panel: Rc<RefCell<LayersPanel>>,
+    pages_panel: Rc<RefCell<PagesPanel>>,
+    grid_panel: Rc<RefCell<GridPanel>>,
+    rulers: Rc<RefCell<Rulers>>,
+    preview: Rc<RefCell<Preview>>,
+    export: Rc<RefCell<Export>>,
+    notes_panel: Rc<RefCell<NotesPanel>>,
+    background_panel: Rc<RefCell<BackgroundPanel>>,
+    file_drop: Rc<RefCell<FileDrop>>,
+    crop_panel: Rc<RefCell<CropPanel>>,
+    document_tabs: Rc<RefCell<DocumentTabs>>,
+    autosave: Rc<RefCell<Autosave>>,
     documents: Vec<Document>,
     active_document_index: usize,
     root_stack: gtk::Stack,
@@ -96,6 +122,19 @@ impl MainWindow {
         let drawing_area = DrawingArea::new(builder.clone());
         let toolbar = Toolbar::new(builder.clone());
         let controlbar = Controlbar::new(builder.clone());
+        let clipboard = Clipboard::new();
+        let layers_panel = LayersPanel::new(builder.clone());
+        let pages_panel = PagesPanel::new(builder.clone());
+        let grid_panel = GridPanel::new(builder.clone());
+        let rulers = Rulers::new(builder.clone());
+        let preview = Preview::new(builder.clone());
+        let export = Export::new(builder.clone());
+        let notes_panel = NotesPanel::new(builder.clone());
+        let background_panel = BackgroundPanel::new(builder.clone());
+        let file_drop = FileDrop::new(builder.clone());
+        let crop_panel = CropPanel::new(builder.clone());
+        let document_tabs = DocumentTabs::new(builder.clone());
+        let autosave = Autosave::new(builder.clone());
 
         MainWindow {
             parent: main_window,
@@ -103,6 +142,19 @@ impl MainWindow {
             drawing_area: Rc::new(RefCell::new(drawing_area)),
             toolbar: Rc::new(RefCell::new(toolbar)),
             controlbar: Rc::new(RefCell::new(controlbar)),
+            clipboard: Rc::new(RefCell::new(clipboard)),
+            layers_panel: Rc::new(RefCell::new(layers_panel)),
+            pages_panel: Rc::new(RefCell::new(pages_panel)),
+            grid_panel: Rc::new(RefCell::new(grid_panel)),
+            rulers: Rc::new(RefCell::new(rulers)),
+            preview: Rc::new(RefCell::new(preview)),
+            export: Rc::new(RefCell::new(export)),
+            notes_panel: Rc::new(RefCell::new(notes_panel)),
+            background_panel: Rc::new(RefCell::new(background_panel)),
+            file_drop: Rc::new(RefCell::new(file_drop)),
+            crop_panel: Rc::new(RefCell::new(crop_panel)),
+            document_tabs: Rc::new(RefCell::new(document_tabs)),
+            autosave: Rc::new(RefCell::new(autosave)),
             documents: vec![],
             active_document_index: 0,
             root_stack: root_stack,
@@ -141,6 +193,58 @@ impl MainWindow {
         self.controlbar.clone()
     }
 
+    pub fn get_clipboard(&self) -> Rc<RefCell<Clipboard>> {
+        self.clipboard.clone()
+    }
+
+    pub fn get_layers_panel(&self) -> Rc<RefCell<LayersPanel>> {
+        self.layers_panel.clone()
+    }
+
+    pub fn get_pages_panel(&self) -> Rc<RefCell<PagesPanel>> {
+        self.pages_panel.clone()
+    }
+
+    pub fn get_grid_panel(&self) -> Rc<RefCell<GridPanel>> {
+        self.grid_panel.clone()
+    }
+
+    pub fn get_rulers(&self) -> Rc<RefCell<Rulers>> {
+        self.rulers.clone()
+    }
+
+    pub fn get_preview(&self) -> Rc<RefCell<Preview>> {
+        self.preview.clone()
+    }
+
+    pub fn get_export(&self) -> Rc<RefCell<Export>> {
+        self.export.clone()
+    }
+
+    pub fn get_notes_panel(&self) -> Rc<RefCell<NotesPanel>> {
+        self.notes_panel.clone()
+    }
+
+    pub fn get_background_panel(&self) -> Rc<RefCell<BackgroundPanel>> {
+        self.background_panel.clone()
+    }
+
+    pub fn get_file_drop(&self) -> Rc<RefCell<FileDrop>> {
+        self.file_drop.clone()
+    }
+
+    pub fn get_crop_panel(&self) -> Rc<RefCell<CropPanel>> {
+        self.crop_panel.clone()
+    }
+
+    pub fn get_document_tabs(&self) -> Rc<RefCell<DocumentTabs>> {
+        self.document_tabs.clone()
+    }
+
+    pub fn get_autosave(&self) -> Rc<RefCell<Autosave>> {
+        self.autosave.clone()
+    }
+
     pub fn get_documents(&self) -> &Vec<Document> {
         &self.documents
     }
@@ -228,8 +332,46 @@ impl MainWindow {
         }
     }
 
+    // add the document and make it the active one
     pub fn new_documents(&mut self, document: Document) {
+        self.save_scroll();
         self.documents.push(document);
+        self.active_document_index = self.documents.len() - 1;
+    }
+
+    // make the document at @index the active one, the scroll position of the
+    // current document is kept so it can be restored when we switch back.
+    pub fn switch_document(&mut self, index: usize) -> bool {
+        if index >= self.documents.len() {
+            return false;
+        }
+
+        self.save_scroll();
+        self.active_document_index = index;
+        true
+    }
+
+    pub fn close_document(&mut self, index: usize) -> Option<Document> {
+        if index >= self.documents.len() {
+            return None;
+        }
+
+        let document = self.documents.remove(index);
+        if self.active_document_index > index ||
+           (self.active_document_index >= self.documents.len() &&
+            self.active_document_index > 0) {
+            self.active_document_index -= 1;
+        }
+        Some(document)
+    }
+
+    fn save_scroll(&mut self) {
+        if self.documents.is_empty() {
+            return;
+        }
+
+        let scroll = self.drawing_area.borrow().get_scroll();
+        self.get_mut_active_document().set_scroll(scroll);
     }
 
     pub fn connect_ui(app: Rc<RefCell<Application>>) {
@@ -250,10 +392,30 @@ impl MainWindow {
             });
         }
 
+        // ask the user about unsaved changes before closing the window
+        {
+            let app = app.clone();
+            window.parent.connect_delete_event(move |_me, _event| {
+                gtk::Inhibit(!DocumentTabs::close_all_documents(&app))
+            });
+        }
+
         HeaderBar::connect_ui(app.clone());
         DrawingArea::connect_ui(app.clone());
         Toolbar::connect_ui(app.clone());
         Controlbar::connect_ui(app.clone());
+        LayersPanel::connect_ui(app.clone());
+        PagesPanel::connect_ui(app.clone());
+        GridPanel::connect_ui(app.clone());
+        Rulers::connect_ui(app.clone());
+        Preview::connect_ui(app.clone());
+        Export::connect_ui(app.clone());
+        NotesPanel::connect_ui(app.clone());
+        BackgroundPanel::connect_ui(app.clone());
+        FileDrop::connect_ui(app.clone());
+        CropPanel::connect_ui(app.clone());
+        DocumentTabs::connect_ui(app.clone());
+        Autosave::connect_ui(app.clone());
     }
 }
 
@@ -23,6 +23,20 @@ pub mod header_bar;
 pub mod drawing_area;
 pub mod toolbar;
 pub mod controlbar;
+pub mod clipboard;
+pub mod layers_panel;
+pub mod pages_panel;
+pub mod document_tabs;
+pub mod dialogs;
+pub mod autosave;
+pub mod grid_panel;
+pub mod rulers;
+pub mod preview;
+pub mod export;
+pub mod notes_panel;
+pub mod background_panel;
+pub mod file_drop;
+pub mod crop_panel;
 
 #[derive(Copy, Clone)]
 pub enum ViewMode {
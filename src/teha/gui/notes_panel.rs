@@ -0,0 +1,207 @@
+//
+// notes_panel.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::{RefCell, Cell};
+
+use gtk;
+use gtk::prelude::*;
+
+use gettextrs::*;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+
+pub struct NotesPanel {
+    parent: gtk::Grid,
+    notes: gtk::TextBuffer,
+    bold: gtk::Button,
+    italic: gtk::Button,
+    show_caption: gtk::Switch,
+    // true while the widgets are filled from the active page, so their
+    // signals don't mark the document as modified.
+    updating: Rc<Cell<bool>>,
+}
+
+impl NotesPanel {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let side_panel: gtk::Notebook =
+            builder.get_object("ed_side_panel").unwrap();
+
+        let grid = gtk::Grid::new();
+        grid.set_property_margin(10);
+        grid.set_row_spacing(6);
+        grid.set_column_spacing(10);
+
+        let create_button = |icon: &str, tooltip: &str| {
+            let button = gtk::Button::new_from_icon_name(icon, 1);
+            button.set_tooltip_text(tooltip);
+            button
+        };
+
+        let bold = create_button(
+            "format-text-bold-symbolic", gettext("Bold").as_str()
+        );
+        let italic = create_button(
+            "format-text-italic-symbolic", gettext("Italic").as_str()
+        );
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        buttons.get_style_context()
+               .map(|style| style.add_class("linked"));
+        buttons.pack_start(&bold, false, false, 0);
+        buttons.pack_start(&italic, false, false, 0);
+        grid.attach(&buttons, 0, 0, 2, 1);
+
+        let text_view = gtk::TextView::new();
+        text_view.set_wrap_mode(gtk::WrapMode::WordChar);
+        text_view.set_left_margin(6);
+        text_view.set_right_margin(6);
+        text_view.set_tooltip_text(
+            gettext("The notes are exported with the page, text between ** is \
+                     bold and between * is italic").as_str()
+        );
+        let scrolled = gtk::ScrolledWindow::new(None, None);
+        scrolled.set_shadow_type(gtk::ShadowType::In);
+        scrolled.set_hexpand(true);
+        scrolled.set_vexpand(true);
+        scrolled.add(&text_view);
+        grid.attach(&scrolled, 0, 1, 2, 1);
+
+        let show_caption_label = gtk::Label::new(
+            gettext("Show as Caption:").as_str()
+        );
+        show_caption_label.set_halign(gtk::Align::End);
+        let show_caption = gtk::Switch::new();
+        show_caption.set_halign(gtk::Align::Start);
+        show_caption.set_tooltip_text(
+            gettext("Draw the notes in a strip below the exported page").as_str()
+        );
+        grid.attach(&show_caption_label, 0, 2, 1, 1);
+        grid.attach(&show_caption, 1, 2, 1, 1);
+
+        grid.show_all();
+        let tab_label = gtk::Label::new(gettext("Notes").as_str());
+        side_panel.append_page(&grid, Some(&tab_label));
+
+        NotesPanel {
+            parent: grid,
+            notes: text_view.get_buffer().unwrap(),
+            bold: bold,
+            italic: italic,
+            show_caption: show_caption,
+            updating: Rc::new(Cell::new(false)),
+        }
+    }
+
+    pub fn get_parent(&self) -> gtk::Grid {
+        self.parent.clone()
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let notes_panel = window.borrow().get_notes_panel();
+        let notes_panel = notes_panel.borrow();
+
+        {
+            let window = window.clone();
+            let updating = notes_panel.updating.clone();
+            notes_panel.notes.connect_changed(move |me| {
+                if updating.get() || window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let (start, end) = me.get_bounds();
+                let notes = me.get_text(&start, &end, false)
+                              .unwrap_or(String::new());
+                window.borrow_mut()
+                      .get_mut_active_document()
+                      .get_mut_active_page()
+                      .set_notes(notes);
+                DocumentTabs::mark_modified(&window);
+            });
+        }
+
+        for &(button, marker) in [(&notes_panel.bold, "**"),
+                                  (&notes_panel.italic, "*")].iter() {
+            let notes = notes_panel.notes.clone();
+            button.connect_clicked(move |_| {
+                wrap_selection(&notes, marker);
+            });
+        }
+
+        {
+            let window = window.clone();
+            let updating = notes_panel.updating.clone();
+            notes_panel.show_caption.connect_property_active_notify(move |me| {
+                if updating.get() || window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                window.borrow_mut()
+                      .get_mut_active_document()
+                      .get_mut_active_page()
+                      .set_show_caption(me.get_active());
+                DocumentTabs::mark_modified(&window);
+            });
+        }
+    }
+
+    // show the notes of the active page
+    pub fn update(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let notes_panel = window.borrow().get_notes_panel();
+        let notes_panel = notes_panel.borrow();
+        let (notes, show_caption) = {
+            let window = window.borrow();
+            let page = window.get_active_document().get_active_page();
+            (page.get_notes().clone(), page.get_show_caption())
+        };
+
+        notes_panel.updating.set(true);
+        notes_panel.notes.set_text(notes.as_str());
+        notes_panel.show_caption.set_active(show_caption);
+        notes_panel.updating.set(false);
+    }
+}
+
+// put @marker around the selected text of @buffer, the markers are added as
+// one user action and the text stays selected
+fn wrap_selection(buffer: &gtk::TextBuffer, marker: &str) {
+    let (start, end) = match buffer.get_selection_bounds() {
+        Some((start, end)) => (start.get_offset(), end.get_offset()),
+        None => return,
+    };
+
+    buffer.begin_user_action();
+    // the end goes first, so the start offset stay valid
+    buffer.insert(&mut buffer.get_iter_at_offset(end), marker);
+    buffer.insert(&mut buffer.get_iter_at_offset(start), marker);
+    buffer.end_user_action();
+
+    let length = marker.chars().count() as i32;
+    buffer.select_range(
+        &buffer.get_iter_at_offset(start + length),
+        &buffer.get_iter_at_offset(end + length)
+    );
+}
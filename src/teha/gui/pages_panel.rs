@@ -0,0 +1,379 @@
+//
+// pages_panel.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use gtk;
+use gtk::prelude::*;
+use gdk;
+use gdk_pixbuf::Pixbuf;
+use cairo;
+
+use gettextrs::*;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::layers_panel::LayersPanel;
+use super::grid_panel::GridPanel;
+use super::notes_panel::NotesPanel;
+use super::background_panel::BackgroundPanel;
+use super::crop_panel::CropPanel;
+use super::header_bar::HeaderBar;
+use super::document_tabs::DocumentTabs;
+use super::drawing_area::DrawingArea;
+use core::draw_objects::Page;
+
+const THUMBNAIL_WIDTH: i32 = 120;
+const DRAG_TARGET: &'static str = "TEACHER_HAND_PAGE";
+
+pub struct PagesPanel {
+    parent: gtk::Box,
+    list: gtk::ListBox,
+    thumbnails: Vec<gtk::Image>,
+    add: gtk::Button,
+    duplicate: gtk::Button,
+    remove: gtk::Button,
+}
+
+impl PagesPanel {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let side_panel: gtk::Notebook =
+            builder.get_object("ed_side_panel").unwrap();
+
+        let create_button = |icon: &str, tooltip: &str| {
+            let button = gtk::Button::new_from_icon_name(icon, 1);
+            button.set_tooltip_text(tooltip);
+            button
+        };
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::Browse);
+        let scrolled = gtk::ScrolledWindow::new(None, None);
+        scrolled.set_vexpand(true);
+        scrolled.set_policy(gtk::PolicyType::Never, gtk::PolicyType::Automatic);
+        scrolled.add(&list);
+
+        let add = create_button(
+            "list-add-symbolic", gettext("Add Page").as_str()
+        );
+        let duplicate = create_button(
+            "edit-copy-symbolic", gettext("Duplicate Page").as_str()
+        );
+        let remove = create_button(
+            "list-remove-symbolic", gettext("Remove Page").as_str()
+        );
+
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        buttons.get_style_context()
+               .map(|style| style.add_class("linked"));
+        buttons.pack_start(&add, false, false, 0);
+        buttons.pack_start(&duplicate, false, false, 0);
+        buttons.pack_start(&remove, false, false, 0);
+
+        let parent = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        parent.set_border_width(6);
+        parent.pack_start(&scrolled, true, true, 0);
+        parent.pack_start(&buttons, false, false, 0);
+        parent.show_all();
+
+        let tab_label = gtk::Label::new(gettext("Pages").as_str());
+        side_panel.append_page(&parent, Some(&tab_label));
+
+        PagesPanel {
+            parent: parent,
+            list: list,
+            thumbnails: vec![],
+            add: add,
+            duplicate: duplicate,
+            remove: remove,
+        }
+    }
+
+    pub fn get_parent(&self) -> gtk::Box {
+        self.parent.clone()
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let pages_panel = window.borrow().get_pages_panel();
+
+        // switch to the page of the selected row
+        {
+            let window = window.clone();
+            pages_panel.borrow()
+                       .list
+                       .connect_row_selected(move |_me, row| {
+                let row = match *row {
+                    Some(ref val) => val.clone(),
+                    None => return,
+                };
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let index = row.get_index() as usize;
+                {
+                    let mut window = window.borrow_mut();
+                    let document = window.get_mut_active_document();
+                    if index >= document.get_pages().len() ||
+                       index == document.get_active_page_index() {
+                        return;
+                    }
+                    {
+                        let page = document.get_mut_active_page();
+                        page.remove_shapes_in_creating_mode();
+                        page.unselect_all_shapes();
+                    }
+                    document.set_active_page_index(index);
+                }
+                HeaderBar::sync_zoom_level(&window);
+                LayersPanel::update(&window);
+                GridPanel::update(&window);
+                NotesPanel::update(&window);
+                BackgroundPanel::update(&window);
+                CropPanel::update(&window);
+                DrawingArea::queue_draw(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            pages_panel.borrow()
+                       .add
+                       .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                window.borrow_mut()
+                      .get_mut_active_document()
+                      .add_page();
+                PagesPanel::update(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            pages_panel.borrow()
+                       .duplicate
+                       .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    let document = window.get_mut_active_document();
+                    let index = document.get_active_page_index();
+                    if let Err(_) = document.duplicate_page(index) {
+                        return;
+                    }
+                }
+                PagesPanel::update(&window);
+            });
+        }
+
+        {
+            let window = window.clone();
+            pages_panel.borrow()
+                       .remove
+                       .connect_clicked(move |_me| {
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                {
+                    let mut window = window.borrow_mut();
+                    let document = window.get_mut_active_document();
+                    let index = document.get_active_page_index();
+                    document.remove_page(index);
+                }
+                PagesPanel::update(&window);
+            });
+        }
+    }
+
+    // rebuild the pages list from the active document, this also update the
+    // layers and grid panels since they show the active page.
+    pub fn update(window: &Rc<RefCell<MainWindow>>) {
+        let pages_panel = window.borrow().get_pages_panel();
+        let list = pages_panel.borrow().list.clone();
+
+        for child in list.get_children() {
+            list.remove(&child);
+        }
+        pages_panel.borrow_mut().thumbnails.clear();
+
+        if window.borrow().get_documents().len() == 0 {
+            LayersPanel::update(window);
+            return;
+        }
+
+        let (rows, active_page_index) = {
+            let window = window.borrow();
+            let document = window.get_active_document();
+            let rows: Vec<(String, Option<Pixbuf>)> = document.get_pages()
+                .iter()
+                .map(|page| {
+                    let thumbnail =
+                        render_thumbnail(page, document.get_transparent());
+                    (page.get_name().clone(), thumbnail)
+                })
+                .collect();
+            (rows, document.get_active_page_index())
+        };
+
+        let targets = vec![
+            gtk::TargetEntry::new(DRAG_TARGET, gtk::TARGET_SAME_APP, 0),
+        ];
+
+        for (index, &(ref name, ref thumbnail)) in rows.iter().enumerate() {
+            let image = gtk::Image::new();
+            image.set_from_pixbuf(thumbnail.as_ref());
+
+            // the thumbnail is the handle used to drag the page around
+            let handle = gtk::EventBox::new();
+            handle.add(&image);
+            handle.drag_source_set(
+                gdk::BUTTON1_MASK, &targets, gdk::ACTION_MOVE
+            );
+            handle.connect_drag_data_get(
+                move |_me, _context, selection_data, _info, _time| {
+                selection_data.set_text(index.to_string().as_str(), -1);
+            });
+
+            let name_entry = gtk::Entry::new();
+            name_entry.set_has_frame(false);
+            name_entry.set_text(name.as_str());
+            {
+                let window = window.clone();
+                name_entry.connect_changed(move |me| {
+                    let text = match me.get_text() {
+                        Some(val) => val,
+                        None => return,
+                    };
+                    {
+                        let mut window = window.borrow_mut();
+                        let document = window.get_mut_active_document();
+                        if let Some(page) = document.get_mut_pages()
+                                                    .get_mut(index) {
+                            page.set_name(text);
+                        }
+                    }
+                    DocumentTabs::mark_modified(&window);
+                });
+            }
+
+            let row_box = gtk::Box::new(gtk::Orientation::Vertical, 2);
+            row_box.set_border_width(4);
+            row_box.pack_start(&handle, false, false, 0);
+            row_box.pack_start(&name_entry, false, false, 0);
+
+            // dropping a page on a row move it to the row's place
+            row_box.drag_dest_set(
+                gtk::DEST_DEFAULT_ALL, &targets, gdk::ACTION_MOVE
+            );
+            {
+                let window = window.clone();
+                row_box.connect_drag_data_received(
+                    move |_me, _context, _x, _y, selection_data, _info, _time| {
+                    let from = match selection_data.get_text()
+                                                   .and_then(|text| text.parse().ok()) {
+                        Some(val) => val,
+                        None => return,
+                    };
+
+                    let moved = window.borrow_mut()
+                                      .get_mut_active_document()
+                                      .move_page(from, index);
+                    if moved {
+                        // note: rebuilding the list destroys the widget that
+                        // emitted this signal, so we do it when idle.
+                        let window = window.clone();
+                        gtk::idle_add(move || {
+                            PagesPanel::update(&window);
+                            gtk::Continue(false)
+                        });
+                    }
+                });
+            }
+
+            row_box.show_all();
+            list.add(&row_box);
+            pages_panel.borrow_mut().thumbnails.push(image);
+        }
+
+        if let Some(row) = list.get_row_at_index(active_page_index as i32) {
+            list.select_row(&row);
+        }
+        LayersPanel::update(window);
+        GridPanel::update(window);
+        NotesPanel::update(window);
+        BackgroundPanel::update(window);
+        CropPanel::update(window);
+        DocumentTabs::update_titles(window);
+        DrawingArea::queue_draw(window);
+    }
+
+    // re-render the thumbnail of the active page
+    pub fn update_thumbnail(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let pages_panel = window.borrow().get_pages_panel();
+        let window = window.borrow();
+        let document = window.get_active_document();
+        let index = document.get_active_page_index();
+        if let Some(image) = pages_panel.borrow().thumbnails.get(index) {
+            let thumbnail = render_thumbnail(
+                document.get_active_page(), document.get_transparent()
+            );
+            image.set_from_pixbuf(thumbnail.as_ref());
+        }
+    }
+}
+
+// render the whole page in a small image as it's exported, the caption strip
+// below the page is left out of the image.
+fn render_thumbnail(page: &Page, transparent: bool) -> Option<Pixbuf> {
+    let size = page.get_size();
+    if size.width <= 0 || size.height <= 0 {
+        return None;
+    }
+
+    let scale = THUMBNAIL_WIDTH as f64 / size.width as f64;
+    let height = (size.height as f64 * scale).ceil() as i32;
+    let surface = match cairo::ImageSurface::create(
+        cairo::Format::ARgb32, THUMBNAIL_WIDTH, height
+    ) {
+        Ok(val) => val,
+        Err(_) => return None,
+    };
+
+    {
+        let cr = cairo::Context::new(&surface);
+        cr.scale(scale, scale);
+        page.render(&cr, transparent, None);
+    }
+
+    gdk::pixbuf_get_from_surface(&surface, 0, 0, THUMBNAIL_WIDTH, height)
+}
@@ -0,0 +1,397 @@
+//
+// preview.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use gtk;
+use gtk::prelude::*;
+use gdk::{self, EventKey, EventButton};
+use cairo;
+
+use gettextrs::*;
+
+use super::*;
+use super::app::Application;
+use super::main_window::MainWindow;
+
+// the empty space (in pixels) around the previewed page
+const PADDING: f64 = 20.0;
+// the size (in pixels) of the squares drawn behind transparent pages
+const CHECKER_SIZE: f64 = 8.0;
+
+pub struct Preview {
+    drawing_area: gtk::DrawingArea,
+    action_bar: gtk::ActionBar,
+    back: gtk::Button,
+    forward: gtk::Button,
+    fullscreen: gtk::Button,
+    page_label: gtk::Label,
+    edpr_stack: gtk::Stack,
+    // the index of the previewed page in the active document
+    page_index: usize,
+    // the last reveal step shown from the previewed page
+    step: u32,
+    in_fullscreen: bool,
+}
+
+impl Preview {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let drawing_area: gtk::DrawingArea =
+            builder.get_object("prev_drawing_area").unwrap();
+        let action_bar: gtk::ActionBar =
+            builder.get_object("prev_action_bar").unwrap();
+        let back: gtk::Button =
+            builder.get_object("prev_back").unwrap();
+        let forward: gtk::Button =
+            builder.get_object("prev_forward").unwrap();
+        let fullscreen: gtk::Button =
+            builder.get_object("prev_fullscreen").unwrap();
+        let page_label: gtk::Label =
+            builder.get_object("prev_page_label").unwrap();
+        let edpr_stack: gtk::Stack =
+            builder.get_object("edpr_stack").unwrap();
+
+        Preview {
+            drawing_area: drawing_area,
+            action_bar: action_bar,
+            back: back,
+            forward: forward,
+            fullscreen: fullscreen,
+            page_label: page_label,
+            edpr_stack: edpr_stack,
+            page_index: 0,
+            step: 0,
+            in_fullscreen: false,
+        }
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let preview = window.borrow().get_preview();
+        let preview = preview.borrow();
+
+        // the preview start from the page being edited
+        {
+            let window = window.clone();
+            preview.edpr_stack.connect_property_visible_child_name_notify(move |me| {
+                let previewing: &str = ViewMode::Previewing.into();
+                if me.get_visible_child_name().as_ref().map(|name| name.as_str()) !=
+                   Some(previewing) {
+                    Preview::set_fullscreen(&window, false);
+                    return;
+                }
+                if window.borrow().get_documents().len() == 0 {
+                    return;
+                }
+
+                let index = window.borrow()
+                                  .get_active_document()
+                                  .get_active_page_index();
+                Preview::show_page(&window, index, 0);
+                let preview = window.borrow().get_preview();
+                preview.borrow().drawing_area.grab_focus();
+            });
+        }
+
+        {
+            let window = window.clone();
+            preview.drawing_area.connect_draw(move |me, cr| {
+                if window.borrow().get_documents().len() == 0 {
+                    return Inhibit(false);
+                }
+
+                let (page_index, step) = {
+                    let preview = window.borrow().get_preview();
+                    let preview = preview.borrow();
+                    (preview.page_index, preview.step)
+                };
+                let window = window.borrow();
+                let document = window.get_active_document();
+                let page = match document.get_pages().get(page_index) {
+                    Some(val) => val,
+                    None => return Inhibit(false),
+                };
+
+                let size = page.render_size();
+                if size.width <= 0 || size.height <= 0 {
+                    return Inhibit(false);
+                }
+                let width = size.width as f64;
+                let height = size.height as f64;
+
+                // fit the page in the window
+                let allocated_width = me.get_allocated_width() as f64;
+                let allocated_height = me.get_allocated_height() as f64;
+                let scale = ((allocated_width - 2.0 * PADDING) / width)
+                    .min((allocated_height - 2.0 * PADDING) / height);
+                if scale <= 0.0 {
+                    return Inhibit(false);
+                }
+
+                cr.translate(
+                    ((allocated_width - width * scale) / 2.0).round(),
+                    ((allocated_height - height * scale) / 2.0).round()
+                );
+                cr.scale(scale, scale);
+                if document.get_transparent() {
+                    draw_checkerboard(cr, width, height, CHECKER_SIZE / scale);
+                }
+                document.render_page(page_index, Some(step), cr);
+                Inhibit(true)
+            });
+        }
+
+        {
+            let window = window.clone();
+            preview.back.connect_clicked(move |_me| {
+                let index = window.borrow().get_preview().borrow().page_index;
+                if index > 0 {
+                    Preview::show_page(&window, index - 1, 0);
+                }
+            });
+        }
+
+        {
+            let window = window.clone();
+            preview.forward.connect_clicked(move |_me| {
+                let index = window.borrow().get_preview().borrow().page_index;
+                Preview::show_page(&window, index + 1, 0);
+            });
+        }
+
+        {
+            let window = window.clone();
+            preview.fullscreen.connect_clicked(move |_me| {
+                Preview::set_fullscreen(&window, true);
+            });
+        }
+
+        {
+            let window = window.clone();
+            preview.drawing_area.connect_key_press_event(move |_me, event| {
+                Inhibit(Preview::key_press(&window, event))
+            });
+        }
+
+        {
+            let window = window.clone();
+            preview.drawing_area.connect_button_press_event(move |me, event| {
+                me.grab_focus();
+                Inhibit(Preview::button_press(&window, event))
+            });
+        }
+    }
+
+    // show the page at @index with the shapes revealed up to @step, if the
+    // active document have that page.
+    pub fn show_page(window: &Rc<RefCell<MainWindow>>, index: usize, step: u32) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let (n_pages, last_step) = {
+            let window = window.borrow();
+            let pages = window.get_active_document().get_pages();
+            match pages.get(index) {
+                Some(page) => (pages.len(), page.last_step()),
+                None => return,
+            }
+        };
+        let step = step.min(last_step);
+
+        let preview = window.borrow().get_preview();
+        preview.borrow_mut().page_index = index;
+        preview.borrow_mut().step = step;
+
+        let preview = preview.borrow();
+        if last_step > 0 {
+            preview.page_label.set_text(
+                format!(
+                    /* TRANSLATORS: the full sentence is "[PAGE] / [PAGES]   Step [STEP] / [STEPS]" */
+                    "{} / {}   {} {} / {}",
+                    index + 1,
+                    n_pages,
+                    gettext("Step"),
+                    step + 1,
+                    last_step + 1
+                ).as_str()
+            );
+        } else {
+            preview.page_label.set_text(
+                format!("{} / {}", index + 1, n_pages).as_str()
+            );
+        }
+        preview.back.set_sensitive(index > 0);
+        preview.forward.set_sensitive(index + 1 < n_pages);
+        preview.drawing_area.queue_draw();
+    }
+
+    // reveal the next step of the page, or move to the next page when all of
+    // its shapes are revealed.
+    pub fn next_step(window: &Rc<RefCell<MainWindow>>) {
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let (index, step) = {
+            let preview = window.borrow().get_preview();
+            let preview = preview.borrow();
+            (preview.page_index, preview.step)
+        };
+        let last_step = match window.borrow()
+                                    .get_active_document()
+                                    .get_pages()
+                                    .get(index) {
+            Some(page) => page.last_step(),
+            None => return,
+        };
+
+        if step < last_step {
+            Preview::show_page(window, index, step + 1);
+        } else {
+            Preview::show_page(window, index + 1, 0);
+        }
+    }
+
+    // hide the last revealed step of the page, or move to the end of the
+    // previous page when nothing is left to hide.
+    pub fn previous_step(window: &Rc<RefCell<MainWindow>>) {
+        let (index, step) = {
+            let preview = window.borrow().get_preview();
+            let preview = preview.borrow();
+            (preview.page_index, preview.step)
+        };
+
+        if step > 0 {
+            Preview::show_page(window, index, step - 1);
+        } else if index > 0 {
+            Preview::show_page(window, index - 1, u32::max_value());
+        }
+    }
+
+    // present the preview on the whole screen, without the header bar and
+    // the action bar.
+    pub fn set_fullscreen(window: &Rc<RefCell<MainWindow>>, fullscreen: bool) {
+        let preview = window.borrow().get_preview();
+        if preview.borrow().in_fullscreen == fullscreen {
+            return;
+        }
+        preview.borrow_mut().in_fullscreen = fullscreen;
+
+        let parent = window.borrow().get_parent();
+        let action_bar = preview.borrow().action_bar.clone();
+        if fullscreen {
+            action_bar.hide();
+            parent.fullscreen();
+            preview.borrow().drawing_area.grab_focus();
+        } else {
+            parent.unfullscreen();
+            action_bar.show();
+        }
+    }
+
+    // play the steps and navigate the pages with the keyboard, escape leave
+    // the fullscreen or go back to the editor. return true if the event was
+    // handled.
+    fn key_press(window: &Rc<RefCell<MainWindow>>, event: &EventKey) -> bool {
+        if window.borrow().get_documents().len() == 0 {
+            return false;
+        }
+
+        let (index, in_fullscreen) = {
+            let preview = window.borrow().get_preview();
+            let preview = preview.borrow();
+            (preview.page_index, preview.in_fullscreen)
+        };
+        let n_pages = window.borrow().get_active_document().get_pages().len();
+        let key = event.get_keyval();
+        if key == gdk::enums::key::space || key == gdk::enums::key::Right ||
+           key == gdk::enums::key::Down {
+            Preview::next_step(window);
+        } else if key == gdk::enums::key::BackSpace ||
+                  key == gdk::enums::key::Left ||
+                  key == gdk::enums::key::Up {
+            Preview::previous_step(window);
+        } else if key == gdk::enums::key::Page_Up {
+            if index > 0 {
+                Preview::show_page(window, index - 1, 0);
+            }
+        } else if key == gdk::enums::key::Page_Down {
+            Preview::show_page(window, index + 1, 0);
+        } else if key == gdk::enums::key::Home {
+            Preview::show_page(window, 0, 0);
+        } else if key == gdk::enums::key::End {
+            Preview::show_page(window, n_pages - 1, 0);
+        } else if key == gdk::enums::key::F5 || key == gdk::enums::key::F11 {
+            Preview::set_fullscreen(window, !in_fullscreen);
+        } else if key == gdk::enums::key::Escape {
+            if in_fullscreen {
+                Preview::set_fullscreen(window, false);
+            } else {
+                let editing: &str = ViewMode::Editing.into();
+                let preview = window.borrow().get_preview();
+                let edpr_stack = preview.borrow().edpr_stack.clone();
+                edpr_stack.set_visible_child_name(editing);
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    // like presentation apps, the primary button reveal the next step and the
+    // secondary button hide the last one.
+    fn button_press(window: &Rc<RefCell<MainWindow>>, event: &EventButton) -> bool {
+        if event.get_event_type() != gdk::EventType::ButtonPress {
+            return false;
+        }
+
+        match event.get_button() {
+            1 => Preview::next_step(window),
+            3 => Preview::previous_step(window),
+            _ => return false,
+        };
+        true
+    }
+}
+
+// draw gray squares behind transparent pages, like image viewers do
+fn draw_checkerboard(cr: &cairo::Context, width: f64, height: f64, size: f64) {
+    cr.save();
+    cr.rectangle(0.0, 0.0, width, height);
+    cr.clip();
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    cr.paint();
+
+    cr.set_source_rgb(0.8, 0.8, 0.8); // #CCCCCC
+    let columns = (width / size).ceil() as i32;
+    let rows = (height / size).ceil() as i32;
+    for row in 0..rows {
+        for column in 0..columns {
+            if (row + column) % 2 == 0 {
+                continue;
+            }
+            cr.rectangle(column as f64 * size, row as f64 * size, size, size);
+        }
+    }
+    cr.fill();
+    cr.restore();
+}
@@ -0,0 +1,396 @@
+//
+// rulers.rs
+//
+// Copyright (C) 2017 Muhannad Alrusayni <0x3UH4224D@gmail.com>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+//
+
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::f64::consts::PI;
+
+use gtk;
+use gtk::prelude::*;
+use cairo;
+
+use super::app::Application;
+use super::main_window::MainWindow;
+use super::document_tabs::DocumentTabs;
+use super::drawing_area::DrawingArea;
+use core::snap::Guide;
+use common::types::*;
+
+// labeled ticks are at least this far (in pixels) from each other
+const MIN_MAJOR_GAP: f64 = 50.0;
+// number of small ticks between two labeled ticks
+const MINOR_TICKS: i32 = 5;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+pub struct Rulers {
+    hruler: gtk::DrawingArea,
+    vruler: gtk::DrawingArea,
+    drawing_area: gtk::DrawingArea,
+    scrolled_drawing_area: gtk::ScrolledWindow,
+    // the pointer position in the drawing area coordinates
+    pointer: Option<Point>,
+    // the index of the guide being dragged in the active page
+    dragged_guide: Option<usize>,
+}
+
+impl Rulers {
+    pub fn new(builder: gtk::Builder) -> Self {
+        let hruler: gtk::DrawingArea =
+            builder.get_object("ed_hruler").unwrap();
+        let vruler: gtk::DrawingArea =
+            builder.get_object("ed_vruler").unwrap();
+        let drawing_area: gtk::DrawingArea =
+            builder.get_object("drawing_area").unwrap();
+        let scrolled_drawing_area: gtk::ScrolledWindow =
+            builder.get_object("scrolled_drawing_area").unwrap();
+
+        Rulers {
+            hruler: hruler,
+            vruler: vruler,
+            drawing_area: drawing_area,
+            scrolled_drawing_area: scrolled_drawing_area,
+            pointer: None,
+            dragged_guide: None,
+        }
+    }
+
+    pub fn queue_draw(&self) {
+        self.hruler.queue_draw();
+        self.vruler.queue_draw();
+    }
+
+    pub fn connect_ui(app: Rc<RefCell<Application>>) {
+        let teha_app = app.borrow();
+        let window = teha_app.get_main_window();
+        let rulers = window.borrow().get_rulers();
+
+        let rulers_widgets = vec![
+            (rulers.borrow().hruler.clone(), Orientation::Horizontal),
+            (rulers.borrow().vruler.clone(), Orientation::Vertical),
+        ];
+        let drawing_area = rulers.borrow().drawing_area.clone();
+
+        for (ruler, orientation) in rulers_widgets.into_iter() {
+            {
+                let window = window.clone();
+                ruler.connect_draw(move |me, cr| {
+                    Rulers::draw(&window, me, cr, orientation);
+                    Inhibit(true)
+                });
+            }
+
+            // pressing on the ruler drag out a new guide, horizontal guides
+            // come from the top ruler and vertical ones from the left ruler.
+            {
+                let window = window.clone();
+                let drawing_area = drawing_area.clone();
+                ruler.connect_button_press_event(move |me, event| {
+                    if event.get_button() != 1 ||
+                       window.borrow().get_documents().len() == 0 {
+                        return Inhibit(false);
+                    }
+
+                    let pos = to_drawing_area(
+                        me, &drawing_area, event.get_position()
+                    );
+                    let pos = match pos {
+                        Some(val) => val,
+                        None => return Inhibit(false),
+                    };
+                    let index = {
+                        let mut window = window.borrow_mut();
+                        let page = window.get_mut_active_document()
+                                         .get_mut_active_page();
+                        let pos = page.device_to_page(&pos);
+                        page.add_guide(match orientation {
+                            Orientation::Horizontal => Guide::Horizontal(pos.y),
+                            Orientation::Vertical => Guide::Vertical(pos.x),
+                        })
+                    };
+                    let rulers = window.borrow().get_rulers();
+                    rulers.borrow_mut().dragged_guide = Some(index);
+                    Inhibit(true)
+                });
+            }
+
+            // note: the ruler keep getting the pointer events while the
+            // button is pressed, even after the pointer move to the drawing
+            // area.
+            {
+                let window = window.clone();
+                let drawing_area = drawing_area.clone();
+                ruler.connect_motion_notify_event(move |me, event| {
+                    let pos = to_drawing_area(
+                        me, &drawing_area, event.get_position()
+                    );
+                    if let Some(pos) = pos {
+                        Rulers::pointer_moved(&window, &pos);
+                    }
+                    Inhibit(true)
+                });
+            }
+
+            {
+                let window = window.clone();
+                let drawing_area = drawing_area.clone();
+                ruler.connect_button_release_event(move |me, event| {
+                    let pos = to_drawing_area(
+                        me, &drawing_area, event.get_position()
+                    );
+                    if let Some(pos) = pos {
+                        Rulers::end_drag(&window, &pos);
+                    }
+                    Inhibit(true)
+                });
+            }
+        }
+    }
+
+    // show the pointer position on the rulers and move the dragged guide,
+    // return true if a guide is being dragged.
+    pub fn pointer_moved(window: &Rc<RefCell<MainWindow>>, pos: &Point) -> bool {
+        let rulers = window.borrow().get_rulers();
+        rulers.borrow_mut().pointer = Some(pos.clone());
+        rulers.borrow().queue_draw();
+
+        let index = match rulers.borrow().dragged_guide {
+            Some(val) => val,
+            None => return false,
+        };
+        if window.borrow().get_documents().len() == 0 {
+            return false;
+        }
+
+        {
+            let mut window = window.borrow_mut();
+            let page = window.get_mut_active_document().get_mut_active_page();
+            let guide = match page.get_guides().get(index) {
+                Some(val) => val.clone(),
+                None => return false,
+            };
+            let pos = page.device_to_page(pos);
+            page.set_guide(index, match guide {
+                Guide::Horizontal(_) => Guide::Horizontal(pos.y),
+                Guide::Vertical(_) => Guide::Vertical(pos.x),
+            });
+        }
+        DrawingArea::queue_draw(window);
+        true
+    }
+
+    // start dragging the guide under @pos, return true if there is one.
+    pub fn begin_drag(window: &Rc<RefCell<MainWindow>>, pos: &Point) -> bool {
+        if window.borrow().get_documents().len() == 0 {
+            return false;
+        }
+
+        let index = window.borrow()
+                          .get_active_document()
+                          .get_active_page()
+                          .guide_at(pos);
+        if index.is_some() {
+            window.borrow().get_rulers().borrow_mut().dragged_guide = index;
+        }
+        index.is_some()
+    }
+
+    // drop the dragged guide, guides dropped outside the drawing area (e.g.
+    // back on the rulers) get removed. return true if a guide was dragged.
+    pub fn end_drag(window: &Rc<RefCell<MainWindow>>, pos: &Point) -> bool {
+        let rulers = window.borrow().get_rulers();
+        let index = match rulers.borrow_mut().dragged_guide.take() {
+            Some(val) => val,
+            None => return false,
+        };
+        if window.borrow().get_documents().len() == 0 {
+            return false;
+        }
+
+        if !rulers.borrow().is_inside_view(pos) {
+            window.borrow_mut()
+                  .get_mut_active_document()
+                  .get_mut_active_page()
+                  .remove_guide(index);
+        }
+        DocumentTabs::mark_modified(window);
+        true
+    }
+
+    // return true if @pos (in the drawing area coordinates) is shown in the
+    // scrolled window.
+    fn is_inside_view(&self, pos: &Point) -> bool {
+        let view = &self.scrolled_drawing_area;
+        let view_pos = self.drawing_area.translate_coordinates(
+            view, pos.x as i32, pos.y as i32
+        );
+        match view_pos {
+            Some((x, y)) => {
+                x >= 0 && y >= 0 &&
+                x < view.get_allocated_width() &&
+                y < view.get_allocated_height()
+            },
+            None => false,
+        }
+    }
+
+    fn draw(
+        window: &Rc<RefCell<MainWindow>>,
+        ruler: &gtk::DrawingArea,
+        cr: &cairo::Context,
+        orientation: Orientation
+    ) {
+        let width = ruler.get_allocated_width() as f64;
+        let height = ruler.get_allocated_height() as f64;
+
+        cr.set_source_rgb(0.96, 0.96, 0.96);
+        cr.paint();
+
+        // the border between the ruler and the drawing area
+        cr.set_source_rgb(0.47, 0.47, 0.47);
+        cr.set_line_width(1.0);
+        match orientation {
+            Orientation::Horizontal => {
+                cr.move_to(0.0, height - 0.5);
+                cr.line_to(width, height - 0.5);
+            },
+            Orientation::Vertical => {
+                cr.move_to(width - 0.5, 0.0);
+                cr.line_to(width - 0.5, height);
+            },
+        }
+        cr.stroke();
+
+        if window.borrow().get_documents().len() == 0 {
+            return;
+        }
+
+        let rulers = window.borrow().get_rulers();
+        let rulers = rulers.borrow();
+        let (zoom_level, translate) = {
+            let window = window.borrow();
+            let page = window.get_active_document().get_active_page();
+            (page.get_zoom_level(), page.get_translate().clone())
+        };
+        let origin = rulers.drawing_area.translate_coordinates(ruler, 0, 0);
+        let origin = match origin {
+            Some((x, y)) => Vector::new(x as f64, y as f64),
+            None => return,
+        };
+
+        // (offset, length, thickness) along the ruler, offset is where the
+        // page origin is.
+        let (offset, length, thickness) = match orientation {
+            Orientation::Horizontal => (origin.x + translate.x, width, height),
+            Orientation::Vertical => (origin.y + translate.y, height, width),
+        };
+
+        // the draw functions take the position along the ruler and the
+        // distance from its outer edge.
+        let line = |along: f64, from: f64, to: f64| {
+            match orientation {
+                Orientation::Horizontal => {
+                    cr.move_to(along.floor() + 0.5, from);
+                    cr.line_to(along.floor() + 0.5, to);
+                },
+                Orientation::Vertical => {
+                    cr.move_to(from, along.floor() + 0.5);
+                    cr.line_to(to, along.floor() + 0.5);
+                },
+            }
+        };
+
+        let step = major_step(zoom_level);
+        let minor_step = step / MINOR_TICKS as f64;
+        let first = ((-offset / zoom_level) / step).floor() as i64;
+        let last = (((length - offset) / zoom_level) / step).ceil() as i64;
+
+        cr.set_source_rgb(0.33, 0.33, 0.33);
+        cr.set_font_size(9.0);
+        for index in first..last + 1 {
+            let value = index as f64 * step;
+            let along = offset + value * zoom_level;
+            line(along, 0.0, thickness);
+
+            for minor in 1..MINOR_TICKS {
+                let minor_along =
+                    along + minor as f64 * minor_step * zoom_level;
+                line(minor_along, thickness * 0.7, thickness);
+            }
+            cr.stroke();
+
+            let label = format!("{}", value);
+            cr.save();
+            match orientation {
+                Orientation::Horizontal => {
+                    cr.move_to(along + 3.0, 10.0);
+                },
+                Orientation::Vertical => {
+                    cr.move_to(10.0, along - 3.0);
+                    cr.rotate(-PI / 2.0);
+                },
+            }
+            cr.show_text(label.as_str());
+            cr.restore();
+        }
+
+        // the pointer position
+        if let Some(ref pointer) = rulers.pointer {
+            let along = match orientation {
+                Orientation::Horizontal => origin.x + pointer.x,
+                Orientation::Vertical => origin.y + pointer.y,
+            };
+            cr.set_source_rgb(0.21, 0.52, 0.89); // #3584E4
+            line(along, 0.0, thickness);
+            cr.stroke();
+        }
+    }
+}
+
+// the smallest of 1, 2, 5, 10, 20, 50 ... page units that keep the labeled
+// ticks far enough from each other at @zoom_level.
+fn major_step(zoom_level: f64) -> f64 {
+    if zoom_level <= 0.0 {
+        return 1.0;
+    }
+
+    let mut magnitude = 1.0;
+    loop {
+        for factor in [1.0, 2.0, 5.0].iter() {
+            let step = factor * magnitude;
+            if step * zoom_level >= MIN_MAJOR_GAP {
+                return step;
+            }
+        }
+        magnitude *= 10.0;
+    }
+}
+
+// convert @pos from @widget coordinates to the drawing area coordinates
+fn to_drawing_area(
+    widget: &gtk::DrawingArea, drawing_area: &gtk::DrawingArea, pos: (f64, f64)
+) -> Option<Point> {
+    let (x, y) = pos;
+    widget.translate_coordinates(drawing_area, x as i32, y as i32)
+          .map(|(x, y)| Point::new(x as f64, y as f64))
+}
@@ -25,7 +25,9 @@ use gtk::prelude::*;
 
 use super::app::Application;
 use super::main_window::MainWindow;
+use super::drawing_area::DrawingArea;
 use core::draw_objects::line_arrow::{self, LineArrow};
+use core::draw_objects::{ShapeTrait, Magnifier, Spotlight};
 use common::types::*;
 
 pub struct Toolbar {
@@ -38,6 +40,8 @@ pub struct Toolbar {
     highlighter: gtk::RadioButton,
     sticker: gtk::RadioButton,
     blur_box: gtk::RadioButton,
+    magnifier: gtk::RadioButton,
+    spotlight: gtk::RadioButton,
 }
 
 impl Toolbar {
@@ -59,6 +63,10 @@ impl Toolbar {
             builder.get_object("ed_tb_sticker").unwrap();
         let blur_box: gtk::RadioButton =
             builder.get_object("ed_tb_blur_box").unwrap();
+        let magnifier: gtk::RadioButton =
+            builder.get_object("ed_tb_magnifier").unwrap();
+        let spotlight: gtk::RadioButton =
+            builder.get_object("ed_tb_spotlight").unwrap();
 
         Toolbar {
             parent: parent,
@@ -69,6 +77,8 @@ impl Toolbar {
             highlighter: highlighter,
             sticker: sticker,
             blur_box: blur_box,
+            magnifier: magnifier,
+            spotlight: spotlight,
         }
     }
 
@@ -104,6 +114,14 @@ impl Toolbar {
         self.blur_box.clone()
     }
 
+    pub fn get_magnifier(&self) -> gtk::RadioButton {
+        self.magnifier.clone()
+    }
+
+    pub fn get_spotlight(&self) -> gtk::RadioButton {
+        self.spotlight.clone()
+    }
+
     pub fn connect_ui(app: Rc<RefCell<Application>>) {
         let teha_app = app.borrow();
         let window = teha_app.get_main_window();
@@ -130,7 +148,35 @@ impl Toolbar {
                       .get_mut_active_page()
                       .get_mut_active_layer()
                       .add(Box::new(line_arrow));
+
+                // the removed shapes that were being created
+                DrawingArea::queue_draw(window);
+            }
+        }
+
+        // add @shape to the active layer, it's created by the next drag on
+        // the page.
+        fn add_creating_shape(
+            radio: &gtk::RadioButton,
+            shape: Box<ShapeTrait>,
+            window: &Rc<RefCell<MainWindow>>
+        ) {
+            if !radio.get_active() {
+                return;
             }
+
+            window.borrow_mut()
+                  .get_mut_active_document()
+                  .get_mut_active_page()
+                  .remove_shapes_in_creating_mode();
+
+            window.borrow_mut()
+                  .get_mut_active_document()
+                  .get_mut_active_page()
+                  .get_mut_active_layer()
+                  .add(shape);
+
+            DrawingArea::queue_draw(window);
         }
 
         // message_box
@@ -154,5 +200,25 @@ impl Toolbar {
                 line_arrow(me, line_arrow::Mode::CreatingLineArrow, &window);
             });
         }
+
+        // magnifier, the user drag the region to magnify on the page
+        {
+            let window = window.clone();
+            toolbar.borrow()
+                   .magnifier
+                   .connect_property_active_notify(move |me| {
+                add_creating_shape(me, Box::new(Magnifier::new()), &window);
+            });
+        }
+
+        // spotlight, the user drag its first hole on the page
+        {
+            let window = window.clone();
+            toolbar.borrow()
+                   .spotlight
+                   .connect_property_active_notify(move |me| {
+                add_creating_shape(me, Box::new(Spotlight::new()), &window);
+            });
+        }
     }
 }
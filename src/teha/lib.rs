@@ -27,6 +27,14 @@ extern crate palette;
 extern crate ncollide;
 extern crate nalgebra as na;
 extern crate alga;
+extern crate gdk_pixbuf;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate base64;
+extern crate gif;
+extern crate libc;
 
 pub mod gui;
 pub mod core;